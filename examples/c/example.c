@@ -0,0 +1,65 @@
+/* Runs a rom for some frames through the C API : example <rom> [frames] */
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "nessy.h"
+
+int main(int argc, char **argv) {
+    if (argc < 2) {
+        fprintf(stderr, "Usage : %s <rom> [frames]\n", argv[0]);
+        return 1;
+    }
+
+    int frames = argc > 2 ? atoi(argv[2]) : 60;
+
+    FILE *file = fopen(argv[1], "rb");
+    if (file == NULL) {
+        fprintf(stderr, "Failed opening %s\n", argv[1]);
+        return 1;
+    }
+
+    fseek(file, 0, SEEK_END);
+    long rom_size = ftell(file);
+    fseek(file, 0, SEEK_SET);
+
+    uint8_t *rom = malloc(rom_size);
+    size_t read_size = fread(rom, 1, rom_size, file);
+    fclose(file);
+
+    Nessy *nes = nessy_create(rom, read_size);
+    free(rom);
+    if (nes == NULL) {
+        fprintf(stderr, "Failed loading %s\n", argv[1]);
+        return 1;
+    }
+
+    size_t total_samples = 0;
+    for (int frame = 0; frame < frames; frame++) {
+        nessy_set_buttons(nes, 0, frame % 2 ? NESSY_BUTTON_A : 0);
+
+        int result = nessy_run_frame(nes);
+        if (result != NESSY_OK) {
+            fprintf(stderr, "Frame %d failed with %d\n", frame, result);
+            nessy_destroy(nes);
+            return 1;
+        }
+
+        size_t sample_count = 0;
+        nessy_get_audio_samples(nes, &sample_count);
+        total_samples += sample_count;
+    }
+
+    /* Saves the state into our own buffer and loads it back */
+    size_t state_size = nessy_save_state(nes, NULL, 0);
+    uint8_t *state = malloc(state_size);
+    nessy_save_state(nes, state, state_size);
+    int load_result = nessy_load_state(nes, state, state_size);
+    free(state);
+
+    const uint8_t *framebuffer = nessy_get_framebuffer(nes);
+    printf("Ran %d frames with nessy %s, %zu audio samples, %zu byte state (load %d), first pixel alpha %d\n",
+        frames, nessy_version(), total_samples, state_size, load_result, framebuffer[NESSY_FRAME_BYTES_PER_PIXEL - 1]);
+
+    nessy_destroy(nes);
+    return load_result == NESSY_OK ? 0 : 1;
+}
@@ -0,0 +1,144 @@
+// Javascript bindings for nessy.wasm, built with :
+//   cargo build --lib --release --target wasm32-unknown-unknown
+// The module exports the C API of include/nessy.h, this wraps it with typed arrays.
+
+export const FRAME_WIDTH = 256;
+export const FRAME_HEIGHT = 240;
+export const AUDIO_SAMPLE_RATE = 44100;
+
+// Controller buttons, combined into the mask given to setButtons
+export const BUTTON_A = 0x01;
+export const BUTTON_B = 0x02;
+export const BUTTON_SELECT = 0x04;
+export const BUTTON_START = 0x08;
+export const BUTTON_UP = 0x10;
+export const BUTTON_DOWN = 0x20;
+export const BUTTON_LEFT = 0x40;
+export const BUTTON_RIGHT = 0x80;
+
+const LOG_FUNCTIONS = [null, console.error, console.warn, console.info, console.debug, console.debug];
+
+export class NessyError extends Error {}
+
+export class Nessy {
+    // source is the wasm bytes or a fetch response, logLevel goes from 0 for none up to 5 for traces
+    static async load(source, logLevel = 2) {
+        let exports = null;
+        const imports = {
+            env: {
+                nessy_console_log(level, message, length) {
+                    const text = new TextDecoder().decode(new Uint8Array(exports.memory.buffer, message, length));
+                    (LOG_FUNCTIONS[level] || console.log)(text);
+                },
+            },
+        };
+
+        const { instance } = source instanceof Response
+            ? await WebAssembly.instantiateStreaming(source, imports)
+            : await WebAssembly.instantiate(source, imports);
+        exports = instance.exports;
+        exports.nessy_init_logging(logLevel);
+
+        return new Nessy(exports);
+    }
+
+    constructor(exports) {
+        this.exports = exports;
+        this.nes = 0;
+    }
+
+    // Replaces the running game, rom is a Uint8Array of an iNES file
+    loadRom(rom) {
+        this.unloadRom();
+
+        const buffer = this.exports.nessy_alloc(rom.length);
+        new Uint8Array(this.exports.memory.buffer, buffer, rom.length).set(rom);
+        this.nes = this.exports.nessy_create(buffer, rom.length);
+        this.exports.nessy_free(buffer, rom.length);
+
+        if (this.nes === 0) {
+            throw new NessyError("Invalid rom");
+        }
+    }
+
+    unloadRom() {
+        if (this.nes !== 0) {
+            this.exports.nessy_destroy(this.nes);
+            this.nes = 0;
+        }
+    }
+
+    reset() {
+        this.check(this.exports.nessy_reset(this.getInstance()));
+    }
+
+    runFrame() {
+        this.check(this.exports.nessy_run_frame(this.getInstance()));
+    }
+
+    // port is 0 or 1, buttons a mask of the BUTTON_ values
+    setButtons(port, buttons) {
+        this.check(this.exports.nessy_set_buttons(this.getInstance(), port, buttons));
+    }
+
+    // FRAME_WIDTH * FRAME_HEIGHT RGBA pixels, ready for an ImageData.
+    // The view is over the wasm memory, it's only valid until the next call.
+    getFramebuffer() {
+        const framebuffer = this.exports.nessy_get_framebuffer(this.getInstance());
+        return new Uint8ClampedArray(this.exports.memory.buffer, framebuffer, FRAME_WIDTH * FRAME_HEIGHT * 4);
+    }
+
+    // Mono samples at AUDIO_SAMPLE_RATE from the last frame, a view valid until the next call
+    getAudioSamples() {
+        const samples = this.exports.nessy_get_audio_samples(this.getInstance(), this.getScratch());
+        const count = new Uint32Array(this.exports.memory.buffer, this.getScratch(), 1)[0];
+        return new Float32Array(this.exports.memory.buffer, samples, count);
+    }
+
+    // A copy of the state, it stays valid after the game moves on
+    saveState() {
+        const size = this.exports.nessy_save_state(this.getInstance(), 0, 0);
+        const buffer = this.exports.nessy_alloc(size);
+        this.exports.nessy_save_state(this.nes, buffer, size);
+        const state = new Uint8Array(this.exports.memory.buffer, buffer, size).slice();
+        this.exports.nessy_free(buffer, size);
+
+        return state;
+    }
+
+    loadState(state) {
+        const buffer = this.exports.nessy_alloc(state.length);
+        new Uint8Array(this.exports.memory.buffer, buffer, state.length).set(state);
+        const result = this.exports.nessy_load_state(this.getInstance(), buffer, state.length);
+        this.exports.nessy_free(buffer, state.length);
+
+        this.check(result);
+    }
+
+    getInstance() {
+        if (this.nes === 0) {
+            throw new NessyError("No rom loaded");
+        }
+
+        return this.nes;
+    }
+
+    // Where nessy_get_audio_samples writes the count it reports, a usize is 4 bytes on wasm32
+    getScratch() {
+        if (this.scratch === undefined) {
+            this.scratch = this.exports.nessy_alloc(4);
+        }
+
+        return this.scratch;
+    }
+
+    check(result) {
+        switch (result) {
+            case 0: return;
+            case -1: throw new NessyError("Invalid argument");
+            case -2: throw new NessyError("The cpu failed");
+            case -3: throw new NessyError("The save state didn't load");
+            default: throw new NessyError(`Unknown error ${result}`);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+/* Generated by src/capi/header.rs, run NESSY_UPDATE_HEADER=1 cargo test c_header to update */
+#ifndef NESSY_H
+#define NESSY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define NESSY_OK 0
+#define NESSY_ERROR_INVALID_ARGUMENT (-1)
+#define NESSY_ERROR_CPU (-2)
+#define NESSY_ERROR_SAVE_STATE (-3)
+
+#define NESSY_FRAME_WIDTH 256
+#define NESSY_FRAME_HEIGHT 240
+#define NESSY_FRAME_BYTES_PER_PIXEL 4
+#define NESSY_AUDIO_SAMPLE_RATE 44100
+
+#define NESSY_BUTTON_A 1
+#define NESSY_BUTTON_B 2
+#define NESSY_BUTTON_SELECT 4
+#define NESSY_BUTTON_START 8
+#define NESSY_BUTTON_UP 16
+#define NESSY_BUTTON_DOWN 32
+#define NESSY_BUTTON_LEFT 64
+#define NESSY_BUTTON_RIGHT 128
+
+typedef struct Nessy Nessy;
+
+/* Returns NULL when the rom doesn't load */
+Nessy *nessy_create(const uint8_t *rom, size_t rom_size);
+
+void nessy_destroy(Nessy *nes);
+
+int nessy_reset(Nessy *nes);
+
+int nessy_run_frame(Nessy *nes);
+
+/* buttons is a mask of the NESSY_BUTTON_ values, port is 0 or 1 */
+int nessy_set_buttons(Nessy *nes, uint32_t port, uint8_t buttons);
+
+/* NESSY_FRAME_WIDTH * NESSY_FRAME_HEIGHT RGBA pixels, valid until the next call on the instance */
+const uint8_t *nessy_get_framebuffer(const Nessy *nes);
+
+/* Mono samples at NESSY_AUDIO_SAMPLE_RATE from the last frame, valid until the next call on the instance */
+const float *nessy_get_audio_samples(const Nessy *nes, size_t *sample_count);
+
+/* Returns the size of the state, it's only written when it fits in the buffer, pass NULL to get the size */
+size_t nessy_save_state(const Nessy *nes, uint8_t *buffer, size_t buffer_size);
+
+/* The instance is unchanged when the state doesn't load */
+int nessy_load_state(Nessy *nes, const uint8_t *state, size_t state_size);
+
+const char *nessy_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
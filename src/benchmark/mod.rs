@@ -0,0 +1,83 @@
+pub mod suite;
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::core::consts;
+use crate::cpu::CpuError;
+use crate::nes::Region;
+use crate::rom_parser::ParserError;
+
+pub use suite::run_suite;
+
+#[derive(Debug)]
+pub enum BenchmarkError {
+    MapperFailed(ParserError),
+    CpuFailed{workload: &'static str, err: CpuError},
+}
+
+impl fmt::Display for BenchmarkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BenchmarkError::MapperFailed(err) => write!(f, "Failed getting mapper from rom parser : {:?}", err),
+            BenchmarkError::CpuFailed{workload, err} => write!(f, "Cpu failed in the {} workload : {:?}", workload, err),
+        }
+    }
+}
+
+// How long a workload took to run its cycles, the rates are per second of host time
+#[derive(Debug, Clone)]
+pub struct BenchmarkResult {
+    workload: &'static str,
+    instructions: usize,
+    cycles: usize,
+    elapsed: Duration,
+}
+
+impl BenchmarkResult {
+    pub fn new(workload: &'static str, instructions: usize, cycles: usize, elapsed: Duration) -> BenchmarkResult {
+        BenchmarkResult{workload, instructions, cycles, elapsed}
+    }
+
+    pub fn get_workload(&self) -> &'static str {
+        self.workload
+    }
+
+    pub fn get_instructions(&self) -> usize {
+        self.instructions
+    }
+
+    pub fn get_cycles(&self) -> usize {
+        self.cycles
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn get_instructions_per_second(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn get_emulated_mhz(&self) -> f64 {
+        self.cycles as f64 / self.elapsed.as_secs_f64() / 1_000_000.0
+    }
+
+    // Frames go by cycles, the same way Nes::get_frame_counter counts NTSC ones
+    pub fn get_frames_per_second(&self) -> f64 {
+        self.get_speed() * Region::Ntsc.get_frames_per_second()
+    }
+
+    // How many times faster than a real nes
+    pub fn get_speed(&self) -> f64 {
+        self.cycles as f64 / consts::CPU_CYCLES_PER_SECOND as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl fmt::Display for BenchmarkResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<10} {:>10} cycles {:>10} instructions in {:>7.3}s : {:>7.2} MHz {:>8.1} fps {:>6.2}x realtime {:>6.2}M instructions/s",
+            self.workload, self.cycles, self.instructions, self.elapsed.as_secs_f64(), self.get_emulated_mhz(),
+            self.get_frames_per_second(), self.get_speed(), self.get_instructions_per_second() / 1_000_000.0)
+    }
+}
@@ -0,0 +1,145 @@
+use super::{BenchmarkError, BenchmarkResult};
+
+use std::time::Instant;
+
+use crate::core::Double;
+use crate::core::consts;
+use crate::cpu::CpuBus;
+use crate::cpu::cpu::Cpu;
+use crate::mapper::{DebugMapper, Mapper};
+use crate::rom_parser::ines::InesRom;
+
+// Where nestest returns from its test list with an empty stack
+const NESTEST_END_ADDR: u16 = 0xC66E;
+const SYNTHETIC_WORKLOAD_ADDR: u16 = 0x0200;
+
+// A program that loops forever, loaded at $0200 of a flat ram
+pub struct SyntheticWorkload {
+    pub name: &'static str,
+    pub program: &'static [u8],
+}
+
+pub const SYNTHETIC_WORKLOADS: [SyntheticWorkload; 4] = [
+    // Shifts, rotates, flag pushes and arithmetic
+    SyntheticWorkload{name: "alu", program: &[
+        0xA9, 0x5A,       // LDA #$5A
+        0x2A, 0x6A, 0x0A, // ROL A ; ROR A ; ASL A
+        0x4A, 0x26, 0x10, // LSR A ; ROL $10
+        0x66, 0x11, 0x08, // ROR $11 ; PHP
+        0x28, 0x69, 0x13, // PLP ; ADC #$13
+        0xE9, 0x07, 0xE8, // SBC #$07 ; INX
+        0xD0, 0xEF,       // BNE $0202
+        0x4C, 0x00, 0x02, // JMP $0200
+    ]},
+    // Copies pages through indirect and absolute indexed addressing
+    SyntheticWorkload{name: "memory", program: &[
+        0xA9, 0x00, 0x85, 0x00, // LDA #$00 ; STA $00
+        0x85, 0x02, 0xA9, 0x10, // STA $02 ; LDA #$10
+        0x85, 0x01, 0xA9, 0x20, // STA $01 ; LDA #$20
+        0x85, 0x03,             // STA $03
+        0xA0, 0x00,             // LDY #$00
+        0xB1, 0x00, 0x91, 0x02, // LDA ($00),Y ; STA ($02),Y
+        0xC8, 0xD0, 0xF9,       // INY ; BNE $0210
+        0xE6, 0x01,             // INC $01
+        0xBD, 0x00, 0x10,       // LDA $1000,X
+        0x9D, 0x00, 0x30,       // STA $3000,X
+        0xE8,                   // INX
+        0x4C, 0x0E, 0x02,       // JMP $020E
+    ]},
+    // Subroutine calls and stack traffic
+    SyntheticWorkload{name: "stack", program: &[
+        0x20, 0x06, 0x02, // JSR $0206
+        0x4C, 0x00, 0x02, // JMP $0200
+        0x48, 0x8A, 0x48, // PHA ; TXA ; PHA
+        0x08, 0x28, 0x68, // PHP ; PLP ; PLA
+        0xAA, 0x68, 0xE8, // TAX ; PLA ; INX
+        0x60,             // RTS
+    ]},
+    // Nested counting loops, mostly taken and untaken branches
+    SyntheticWorkload{name: "branches", program: &[
+        0xA2, 0x00,       // LDX #$00
+        0xA0, 0x08,       // LDY #$08
+        0x88, 0xD0, 0xFD, // DEY ; BNE $0204
+        0xE8, 0xE0, 0x80, // INX ; CPX #$80
+        0x90, 0xF6,       // BCC $0202
+        0xF0, 0xF2,       // BEQ $0200
+        0x4C, 0x00, 0x02, // JMP $0200
+    ]},
+];
+
+// Runs until the cycle counter moved by the budget or the stop condition holds, returns the instructions and cycles that ran
+fn run_cpu<B: CpuBus>(cpu: &mut Cpu<B>, cycle_budget: usize, workload: &'static str, should_stop: fn(&Cpu<B>) -> bool)
+    -> Result<(usize, usize), BenchmarkError> {
+    let start_cycle = cpu.get_cycle_counter();
+    let mut instructions = 0;
+
+    while cpu.get_cycle_counter() - start_cycle < cycle_budget && !should_stop(cpu) {
+        cpu.execute_instruction().map_err(|err| BenchmarkError::CpuFailed{workload, err})?;
+        instructions += 1;
+    }
+
+    Ok((instructions, cpu.get_cycle_counter() - start_cycle))
+}
+
+pub fn run_synthetic(workload: &SyntheticWorkload, cycle_budget: usize) -> Result<BenchmarkResult, BenchmarkError> {
+    let mut mapper = DebugMapper::new();
+    mapper.load(SYNTHETIC_WORKLOAD_ADDR, workload.program);
+    // Through the same dynamic bus as a cartridge
+    let bus: Box<dyn Mapper> = Box::new(mapper);
+    let mut cpu = Cpu::new_with_entry_point(bus, Double::from(SYNTHETIC_WORKLOAD_ADDR));
+
+    let start = Instant::now();
+    let (instructions, cycles) = run_cpu(&mut cpu, cycle_budget, workload.name, |_| false)?;
+
+    Ok(BenchmarkResult::new(workload.name, instructions, cycles, start.elapsed()))
+}
+
+// Runs nestest from power on over and over until the budget is spent
+pub fn run_nestest(rom: &InesRom, cycle_budget: usize) -> Result<BenchmarkResult, BenchmarkError> {
+    let is_done = |cpu: &Cpu| cpu.get_program_counter().get_value() == NESTEST_END_ADDR && cpu.get_stack_pointer().get_value() == consts::STACK_SIZE;
+    let mut instructions = 0;
+    let mut cycles = 0;
+
+    let start = Instant::now();
+    while cycles < cycle_budget {
+        let mut cpu = Cpu::new(rom.get_mapper().map_err(BenchmarkError::MapperFailed)?);
+        let (run_instructions, run_cycles) = run_cpu(&mut cpu, cycle_budget - cycles, "nestest", is_done)?;
+        instructions += run_instructions;
+        cycles += run_cycles;
+    }
+
+    Ok(BenchmarkResult::new("nestest", instructions, cycles, start.elapsed()))
+}
+
+// Every synthetic workload, after nestest when a rom is given
+pub fn run_suite(nestest_rom: Option<&InesRom>, cycle_budget: usize) -> Result<Vec<BenchmarkResult>, BenchmarkError> {
+    let mut results = Vec::<BenchmarkResult>::new();
+    if let Some(rom) = nestest_rom {
+        results.push(run_nestest(rom, cycle_budget)?);
+    }
+
+    for workload in SYNTHETIC_WORKLOADS.iter() {
+        results.push(run_synthetic(workload, cycle_budget)?);
+    }
+
+    Ok(results)
+}
+
+#[test]
+fn benchmark_suite() {
+    let rom_buffer = std::fs::read(std::path::Path::new("samples").join("nestest.nes")).unwrap();
+    let rom = InesRom::new(rom_buffer).unwrap();
+
+    // Long enough for nestest to restart once
+    let cycle_budget = 40_000;
+    let results = run_suite(Some(&rom), cycle_budget).unwrap();
+
+    let workloads: Vec<&str> = results.iter().map(|result| result.get_workload()).collect();
+    assert_eq!(workloads, vec!["nestest", "alu", "memory", "stack", "branches"]);
+    for result in results.iter() {
+        // Instructions take up to 7 cycles, the last one may go over the budget
+        assert!(result.get_cycles() >= cycle_budget && result.get_cycles() < cycle_budget + 8, "{}", result);
+        assert!(result.get_instructions() >= cycle_budget / 8, "{}", result);
+        assert!(result.get_emulated_mhz() > 0.0);
+    }
+}
@@ -0,0 +1,47 @@
+// The windowed frontend, built with the desktop feature : nessy-desktop <rom> [scale]
+use simplelog::{ConfigBuilder, Level, TermLogger, LevelFilter, TerminalMode, Color};
+
+use std::path::Path;
+
+use nessy::Nes;
+use nessy::core::consts;
+use nessy::desktop::Frontend;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage : nessy-desktop <rom> [scale]");
+        eprintln!("Arrows, Z, X, Right Shift and Enter for the first controller, WASD, Q, E, 1 and 2 for the second");
+        eprintln!("P pause, \\ frame advance, F1 reset, F5 save state, F7 load state, F12 screenshot, Escape quit");
+        std::process::exit(1);
+    }
+
+    let mut config_builder = ConfigBuilder::new();
+    config_builder.set_level_color(Level::Info, Color::Green);
+    config_builder.set_location_level(LevelFilter::Off);
+    config_builder.set_target_level(LevelFilter::Off);
+    let _ = TermLogger::init(LevelFilter::Info, config_builder.build(), TerminalMode::Mixed);
+
+    let scale = match args.get(2).map(|scale| scale.parse::<usize>()) {
+        Some(Ok(scale)) if (1..=consts::DESKTOP_MAX_SCALE).contains(&scale) => scale,
+        Some(_) => {
+            eprintln!("Invalid scale {}, it goes from 1 to {}", args[2], consts::DESKTOP_MAX_SCALE);
+            std::process::exit(1);
+        },
+        None => consts::DESKTOP_DEFAULT_SCALE,
+    };
+
+    let rom_path = Path::new(&args[1]);
+    let nes = match std::fs::read(rom_path).map_err(|err| err.to_string()).and_then(|rom| Nes::new(&rom).map_err(|err| err.to_string())) {
+        Ok(nes) => nes,
+        Err(err) => {
+            eprintln!("Failed loading {} : {}", rom_path.display(), err);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(err) = Frontend::new(nes, rom_path, scale).and_then(|mut frontend| frontend.run()) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,267 @@
+// Runs blargg's test roms, which report their results in prg ram
+// Protocol docs : https://github.com/christopherpow/nes-test-roms/blob/master/instr_test-v5/readme.txt
+//
+// $6000 holds the status, $6001-$6003 the signature DE B0 61 and $6004 a NUL terminated message.
+// The rom sets are not shipped with the repo, place them in samples/blargg or point NESSY_BLARGG_ROMS_DIR to them,
+// then run `cargo test -- --ignored`.
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::core::Byte;
+use crate::core::Double;
+use crate::core::consts;
+use crate::cpu::CpuError;
+use crate::cpu::cpu::Cpu;
+use crate::rom_parser::ParserError;
+use crate::rom_parser::ines::InesRom;
+
+const BLARGG_ROMS_DIR_ENV: &str = "NESSY_BLARGG_ROMS_DIR";
+
+const BLARGG_STATUS_ADDR: u16 = 0x6000;
+const BLARGG_SIGNATURE_ADDR: u16 = 0x6001;
+const BLARGG_TEXT_ADDR: u16 = 0x6004;
+const BLARGG_SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+
+const BLARGG_STATUS_RUNNING: u8 = 0x80;
+const BLARGG_STATUS_RESET_REQUIRED: u8 = 0x81;
+const BLARGG_STATUS_PASSED: u8 = 0x00;
+
+// The roms ask to be reset no sooner than 100 msec after requesting it
+const BLARGG_RESET_DELAY_CYCLES: usize = consts::CPU_CYCLES_PER_SECOND / 10;
+const BLARGG_TIMEOUT_CYCLES: usize = consts::CPU_CYCLES_PER_SECOND * 60;
+
+const PPU_STATUS_ADDR: u16 = 0x2002;
+const PPU_STATUS_VBLANK: u8 = 0x80;
+
+#[derive(Debug)]
+pub enum BlarggError {
+    InvalidRom(ParserError),
+    CpuError(CpuError),
+    Timeout(String),
+}
+
+impl fmt::Display for BlarggError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlarggError::InvalidRom(err) => write!(f, "Invalid rom : {:?}", err),
+            BlarggError::CpuError(err) => write!(f, "Cpu error : {:?}", err),
+            BlarggError::Timeout(text) => write!(f, "Timed out\n{}", text),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BlarggResult {
+    pub status: u8,
+    pub text: String,
+}
+
+fn has_signature(cpu: &Cpu) -> bool {
+    BLARGG_SIGNATURE.iter().enumerate()
+        .all(|(i, b)| cpu.get_memory_addr(Double::from(BLARGG_SIGNATURE_ADDR + i as u16)).get_value() == *b)
+}
+
+fn get_text(cpu: &Cpu) -> String {
+    let mut text = Vec::<u8>::new();
+    let mut addr = BLARGG_TEXT_ADDR;
+
+    while addr <= consts::NROM_PRG_RAM_RANGE_END {
+        let value = cpu.get_memory_addr(Double::from(addr)).get_value();
+        if value == 0 {
+            break;
+        }
+
+        text.push(value);
+        addr += 1;
+    }
+
+    String::from_utf8_lossy(&text).trim_end().to_string()
+}
+
+// There's no ppu yet, raise the vblank flag during the vblank scanlines so waiting for it works
+fn update_ppu_status(cpu: &mut Cpu) {
+    let frame_dot = (cpu.get_cycle_counter() * consts::PPU_DOTS_PER_CPU_CYCLE)
+        % (consts::PPU_DOTS_PER_SCANLINE * consts::PPU_SCANLINES_PER_FRAME);
+    let scanline = frame_dot / consts::PPU_DOTS_PER_SCANLINE;
+
+    let in_vblank = (consts::PPU_VBLANK_START_SCANLINE..consts::PPU_SCANLINES_PER_FRAME - 1).contains(&scanline);
+    cpu.set_memory_addr(Double::from(PPU_STATUS_ADDR), Byte::new(if in_vblank { PPU_STATUS_VBLANK } else { 0x00 }));
+}
+
+pub fn run_blargg_rom(rom_content: Vec<u8>) -> Result<BlarggResult, BlarggError> {
+    let parser = InesRom::new(rom_content).map_err(BlarggError::InvalidRom)?;
+    let mapper = parser.get_mapper().map_err(BlarggError::InvalidRom)?;
+    let mut cpu = Cpu::new(mapper);
+
+    let mut reset_cycle: Option<usize> = None;
+
+    while cpu.get_cycle_counter() < BLARGG_TIMEOUT_CYCLES {
+        update_ppu_status(&mut cpu);
+        cpu.execute_instruction().map_err(BlarggError::CpuError)?;
+
+        if let Some(cycle) = reset_cycle {
+            if cpu.get_cycle_counter() >= cycle {
+                log::info!("Resetting as requested by the rom");
+                cpu.reset();
+                reset_cycle = None;
+            }
+
+            continue;
+        }
+
+        if !has_signature(&cpu) {
+            continue;
+        }
+
+        match cpu.get_memory_addr(Double::from(BLARGG_STATUS_ADDR)).get_value() {
+            BLARGG_STATUS_RUNNING => {},
+            BLARGG_STATUS_RESET_REQUIRED => {
+                reset_cycle = Some(cpu.get_cycle_counter() + BLARGG_RESET_DELAY_CYCLES);
+
+                // Clear the status so we don't schedule the same reset again
+                cpu.set_memory_addr(Double::from(BLARGG_STATUS_ADDR), Byte::new(BLARGG_STATUS_RUNNING));
+            },
+            status => {
+                return Ok(BlarggResult{status, text: get_text(&cpu)});
+            }
+        }
+    }
+
+    Err(BlarggError::Timeout(get_text(&cpu)))
+}
+
+fn run_blargg_rom_file(rom_path: &Path) -> Result<BlarggResult, BlarggError> {
+    let mut rom_buffer = Vec::<u8>::new();
+    File::open(rom_path).unwrap().read_to_end(&mut rom_buffer).unwrap();
+
+    run_blargg_rom(rom_buffer)
+}
+
+fn get_rom_paths(dir: &Path) -> Vec<PathBuf> {
+    let mut rom_paths = Vec::<PathBuf>::new();
+
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            rom_paths.extend(get_rom_paths(&path));
+        } else if path.extension() == Some(OsStr::new("nes")) {
+            rom_paths.push(path);
+        }
+    }
+
+    rom_paths.sort();
+    rom_paths
+}
+
+// Runs every rom in the set, panics with a summary of the failed ones
+fn run_blargg_rom_set(set_name: &str) {
+    let set_dir = match std::env::var(BLARGG_ROMS_DIR_ENV) {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => Path::new("samples").join("blargg"),
+    }.join(set_name);
+
+    if !set_dir.is_dir() {
+        panic!("{} not found at {:?} (set {})", set_name, set_dir, BLARGG_ROMS_DIR_ENV);
+    }
+
+    let mut failures = Vec::<String>::new();
+    let rom_paths = get_rom_paths(&set_dir);
+
+    for rom_path in &rom_paths {
+        let rom_name = rom_path.strip_prefix(&set_dir).unwrap().display().to_string();
+        match run_blargg_rom_file(rom_path) {
+            Ok(result) if result.status == BLARGG_STATUS_PASSED => {
+                println!("{} : Passed", rom_name);
+            },
+            Ok(result) => {
+                println!("{} : Failed with status {}\n{}", rom_name, result.status, result.text);
+                failures.push(rom_name);
+            },
+            Err(err) => {
+                println!("{} : {}", rom_name, err);
+                failures.push(rom_name);
+            }
+        }
+    }
+
+    println!("{} : {}/{} roms passed", set_name, rom_paths.len() - failures.len(), rom_paths.len());
+    if !failures.is_empty() {
+        panic!("{} failed : {}", set_name, failures.join(", "));
+    }
+}
+
+#[test]
+fn blargg_reset_required() {
+    // Requests a reset on the first run and reports "OK" after it, $0300 counts the runs
+    let program: [u8; 52] = [
+        0xAD, 0x00, 0x03, 0xD0, 0x1A, 0xEE, 0x00, 0x03, 0xA9, 0x81, 0x8D, 0x00, 0x60, 0xA9, 0xDE, 0x8D,
+        0x01, 0x60, 0xA9, 0xB0, 0x8D, 0x02, 0x60, 0xA9, 0x61, 0x8D, 0x03, 0x60, 0x4C, 0x1C, 0xC0, 0xA9,
+        0x4F, 0x8D, 0x04, 0x60, 0xA9, 0x4B, 0x8D, 0x05, 0x60, 0xA9, 0x00, 0x8D, 0x06, 0x60, 0x8D, 0x00,
+        0x60, 0x4C, 0x31, 0xC0,
+    ];
+
+    let mut rom_content = vec![b'N', b'E', b'S', 0x1A, 0x01, 0x00];
+    rom_content.resize(0x10, 0x00);
+
+    let mut prg_rom_content = vec![0x00u8; 0x4000];
+    prg_rom_content[..program.len()].copy_from_slice(&program);
+    prg_rom_content[0x3FFC] = 0x00;
+    prg_rom_content[0x3FFD] = 0xC0;
+    rom_content.extend(prg_rom_content);
+
+    let result = run_blargg_rom(rom_content).unwrap();
+    assert_eq!(result.status, BLARGG_STATUS_PASSED);
+    assert_eq!(result.text, "OK");
+}
+
+#[test]
+fn blargg_instr_misc() {
+    let result = run_blargg_rom_file(&Path::new("samples").join("instr_misc.nes")).unwrap();
+
+    // 03-dummy_reads needs dummy reads and ppu register mirroring, which aren't emulated yet
+    if result.status != BLARGG_STATUS_PASSED {
+        assert_eq!(get_failed_tests(&result.text), ["03-dummy_reads"], "Status {}\n{}", result.status, result.text);
+    }
+}
+
+// Multi test roms print the name of a failing sub-test, then a line starting with Failed
+fn get_failed_tests(text: &str) -> Vec<&str> {
+    let lines: Vec<&str> = text.lines().map(|line| line.trim()).filter(|line| !line.is_empty()).collect();
+    lines.windows(2).filter(|pair| pair[1].starts_with("Failed")).map(|pair| pair[0]).collect()
+}
+
+#[test]
+fn blargg_failed_tests() {
+    let text = "\nTest requires $2002 mirroring every 8 bytes to $3FFA\n\n03-dummy_reads\n\nFailed #2\n\nWhile running test 3 of 4";
+    assert_eq!(get_failed_tests(text), ["03-dummy_reads"]);
+    assert_eq!(get_failed_tests("01-abs_x_wrap\n\nFailed #2\n\n03-dummy_reads\n\nFailed #2\n"), ["01-abs_x_wrap", "03-dummy_reads"]);
+    assert!(get_failed_tests("\nPassed\n").is_empty());
+}
+
+#[test]
+#[ignore = "needs the blargg rom sets"]
+fn blargg_instr_test() {
+    run_blargg_rom_set("instr_test-v5");
+}
+
+#[test]
+#[ignore = "needs the blargg rom sets"]
+fn blargg_cpu_timing() {
+    run_blargg_rom_set("cpu_timing_test6");
+}
+
+#[test]
+#[ignore = "needs a ppu"]
+fn blargg_ppu() {
+    run_blargg_rom_set("ppu_vbl_nmi");
+}
+
+#[test]
+#[ignore = "needs an apu"]
+fn blargg_apu() {
+    run_blargg_rom_set("apu_test");
+}
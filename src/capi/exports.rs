@@ -0,0 +1,175 @@
+// Every pointer argument is either null or valid for the given length, instances come from nessy_create.
+// Null instances and buffers are reported with NESSY_ERROR_INVALID_ARGUMENT instead of crashing.
+
+use super::{NESSY_OK, NESSY_ERROR_INVALID_ARGUMENT, NESSY_ERROR_CPU, NESSY_ERROR_SAVE_STATE};
+
+use std::slice;
+
+use crate::nes::{Nes, NesError};
+
+fn get_result(result: Result<(), NesError>) -> i32 {
+    match result {
+        Ok(()) => NESSY_OK,
+        Err(NesError::InvalidRom(_)) => NESSY_ERROR_INVALID_ARGUMENT,
+        Err(NesError::CpuFailed(_)) => NESSY_ERROR_CPU,
+        Err(NesError::SaveStateFailed(_)) => NESSY_ERROR_SAVE_STATE,
+    }
+}
+
+// Returns null when the rom doesn't load
+#[no_mangle]
+pub unsafe extern "C" fn nessy_create(rom: *const u8, rom_size: usize) -> *mut Nes {
+    if rom.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    match Nes::new(slice::from_raw_parts(rom, rom_size)) {
+        Ok(nes) => Box::into_raw(Box::new(nes)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nessy_destroy(nes: *mut Nes) {
+    if !nes.is_null() {
+        drop(Box::from_raw(nes));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nessy_reset(nes: *mut Nes) -> i32 {
+    match nes.as_mut() {
+        Some(nes) => {
+            nes.reset();
+            NESSY_OK
+        },
+        None => NESSY_ERROR_INVALID_ARGUMENT,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nessy_run_frame(nes: *mut Nes) -> i32 {
+    match nes.as_mut() {
+        Some(nes) => get_result(nes.run_frame()),
+        None => NESSY_ERROR_INVALID_ARGUMENT,
+    }
+}
+
+// buttons is a mask of the NESSY_BUTTON_ values
+#[no_mangle]
+pub unsafe extern "C" fn nessy_set_buttons(nes: *mut Nes, port: u32, buttons: u8) -> i32 {
+    match nes.as_mut() {
+        Some(nes) if port < 2 => {
+            nes.set_buttons(port as usize, buttons);
+            NESSY_OK
+        },
+        _ => NESSY_ERROR_INVALID_ARGUMENT,
+    }
+}
+
+// NESSY_FRAME_WIDTH * NESSY_FRAME_HEIGHT RGBA pixels, valid until the next call on the instance
+#[no_mangle]
+pub unsafe extern "C" fn nessy_get_framebuffer(nes: *const Nes) -> *const u8 {
+    match nes.as_ref() {
+        Some(nes) => nes.get_framebuffer().as_ptr(),
+        None => std::ptr::null(),
+    }
+}
+
+// Mono samples at NESSY_AUDIO_SAMPLE_RATE from the last frame, valid until the next call on the instance
+#[no_mangle]
+pub unsafe extern "C" fn nessy_get_audio_samples(nes: *const Nes, sample_count: *mut usize) -> *const f32 {
+    match (nes.as_ref(), sample_count.as_mut()) {
+        (Some(nes), Some(sample_count)) => {
+            *sample_count = nes.get_audio_samples().len();
+            nes.get_audio_samples().as_ptr()
+        },
+        _ => std::ptr::null(),
+    }
+}
+
+// Returns the size of the state, it's only written when it fits in the buffer.
+// Call with a null buffer to get the size first.
+#[no_mangle]
+pub unsafe extern "C" fn nessy_save_state(nes: *const Nes, buffer: *mut u8, buffer_size: usize) -> usize {
+    let nes = match nes.as_ref() {
+        Some(nes) => nes,
+        None => return 0,
+    };
+
+    let state = nes.save_state();
+    if !buffer.is_null() && state.len() <= buffer_size {
+        slice::from_raw_parts_mut(buffer, state.len()).copy_from_slice(&state);
+    }
+
+    state.len()
+}
+
+// The instance is unchanged when the state doesn't load
+#[no_mangle]
+pub unsafe extern "C" fn nessy_load_state(nes: *mut Nes, state: *const u8, state_size: usize) -> i32 {
+    match nes.as_mut() {
+        Some(nes) if !state.is_null() => get_result(nes.load_state(slice::from_raw_parts(state, state_size))),
+        _ => NESSY_ERROR_INVALID_ARGUMENT,
+    }
+}
+
+// A static string, never freed
+#[no_mangle]
+pub extern "C" fn nessy_version() -> *const std::os::raw::c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const std::os::raw::c_char
+}
+
+#[test]
+fn capi_calls() {
+    let rom = crate::nes::console::get_test_rom();
+    unsafe {
+        assert!(nessy_create(rom.as_ptr(), 8).is_null());
+        assert!(nessy_create(std::ptr::null(), 0).is_null());
+
+        let nes = nessy_create(rom.as_ptr(), rom.len());
+        assert!(!nes.is_null());
+        assert_eq!(nessy_set_buttons(nes, 0, crate::core::consts::BUTTON_A), NESSY_OK);
+        assert_eq!(nessy_set_buttons(nes, 2, 0), NESSY_ERROR_INVALID_ARGUMENT);
+        assert_eq!(nessy_run_frame(nes), NESSY_OK);
+
+        let mut sample_count = 0;
+        assert!(!nessy_get_audio_samples(nes, &mut sample_count).is_null());
+        assert!(sample_count > 0);
+        assert!(!nessy_get_framebuffer(nes).is_null());
+
+        let state_size = nessy_save_state(nes, std::ptr::null_mut(), 0);
+        let mut state = vec![0u8; state_size];
+        assert_eq!(nessy_save_state(nes, state.as_mut_ptr(), state.len()), state_size);
+        assert_eq!(nessy_run_frame(nes), NESSY_OK);
+        assert_eq!(nessy_load_state(nes, state.as_ptr(), state.len()), NESSY_OK);
+        assert_eq!(nessy_load_state(nes, state.as_ptr(), 4), NESSY_ERROR_SAVE_STATE);
+        assert_eq!((*nes).get_frame_counter(), 1);
+
+        nessy_destroy(nes);
+        assert_eq!(nessy_run_frame(std::ptr::null_mut()), NESSY_ERROR_INVALID_ARGUMENT);
+
+        // NROM-256 loads and runs
+        let mut large_nrom = rom.clone();
+        large_nrom[4] = 2;
+        large_nrom.extend_from_slice(&rom[0x10..]);
+        let nes = nessy_create(large_nrom.as_ptr(), large_nrom.len());
+        assert!(!nes.is_null());
+        assert_eq!(nessy_run_frame(nes), NESSY_OK);
+        nessy_destroy(nes);
+
+        // Prg sizes the mapper can't bank are rejected, not a crash
+        let mut no_prg_rom = rom[..0x10].to_vec();
+        no_prg_rom[4] = 0;
+        assert!(nessy_create(no_prg_rom.as_ptr(), no_prg_rom.len()).is_null());
+
+        let mut mmc1_no_prg_rom = no_prg_rom.clone();
+        mmc1_no_prg_rom[6] = 0x10;
+        assert!(nessy_create(mmc1_no_prg_rom.as_ptr(), mmc1_no_prg_rom.len()).is_null());
+
+        let mut three_bank_nrom = large_nrom.clone();
+        three_bank_nrom[4] = 3;
+        three_bank_nrom.extend_from_slice(&rom[0x10..]);
+        assert!(nessy_create(three_bank_nrom.as_ptr(), three_bank_nrom.len()).is_null());
+    }
+}
@@ -0,0 +1,171 @@
+use super::{NESSY_OK, NESSY_ERROR_INVALID_ARGUMENT, NESSY_ERROR_CPU, NESSY_ERROR_SAVE_STATE};
+
+use crate::core::consts;
+
+// The functions of the exports module with their header comments. c_header_matches_exports checks the prototypes
+// against the exports signatures and c_header checks include/nessy.h against the generated header
+const FUNCTIONS: [&str; 10] = [
+    "/* Returns NULL when the rom doesn't load */\nNessy *nessy_create(const uint8_t *rom, size_t rom_size);",
+    "void nessy_destroy(Nessy *nes);",
+    "int nessy_reset(Nessy *nes);",
+    "int nessy_run_frame(Nessy *nes);",
+    "/* buttons is a mask of the NESSY_BUTTON_ values, port is 0 or 1 */\nint nessy_set_buttons(Nessy *nes, uint32_t port, uint8_t buttons);",
+    "/* NESSY_FRAME_WIDTH * NESSY_FRAME_HEIGHT RGBA pixels, valid until the next call on the instance */\nconst uint8_t *nessy_get_framebuffer(const Nessy *nes);",
+    "/* Mono samples at NESSY_AUDIO_SAMPLE_RATE from the last frame, valid until the next call on the instance */\nconst float *nessy_get_audio_samples(const Nessy *nes, size_t *sample_count);",
+    "/* Returns the size of the state, it's only written when it fits in the buffer, pass NULL to get the size */\nsize_t nessy_save_state(const Nessy *nes, uint8_t *buffer, size_t buffer_size);",
+    "/* The instance is unchanged when the state doesn't load */\nint nessy_load_state(Nessy *nes, const uint8_t *state, size_t state_size);",
+    "const char *nessy_version(void);",
+];
+
+pub fn get_header() -> String {
+    let defines = [
+        ("NESSY_OK", NESSY_OK as i64),
+        ("NESSY_ERROR_INVALID_ARGUMENT", NESSY_ERROR_INVALID_ARGUMENT as i64),
+        ("NESSY_ERROR_CPU", NESSY_ERROR_CPU as i64),
+        ("NESSY_ERROR_SAVE_STATE", NESSY_ERROR_SAVE_STATE as i64),
+        ("", 0),
+        ("NESSY_FRAME_WIDTH", consts::FRAME_WIDTH as i64),
+        ("NESSY_FRAME_HEIGHT", consts::FRAME_HEIGHT as i64),
+        ("NESSY_FRAME_BYTES_PER_PIXEL", consts::FRAME_BYTES_PER_PIXEL as i64),
+        ("NESSY_AUDIO_SAMPLE_RATE", consts::AUDIO_SAMPLE_RATE as i64),
+        ("", 0),
+        ("NESSY_BUTTON_A", consts::BUTTON_A as i64),
+        ("NESSY_BUTTON_B", consts::BUTTON_B as i64),
+        ("NESSY_BUTTON_SELECT", consts::BUTTON_SELECT as i64),
+        ("NESSY_BUTTON_START", consts::BUTTON_START as i64),
+        ("NESSY_BUTTON_UP", consts::BUTTON_UP as i64),
+        ("NESSY_BUTTON_DOWN", consts::BUTTON_DOWN as i64),
+        ("NESSY_BUTTON_LEFT", consts::BUTTON_LEFT as i64),
+        ("NESSY_BUTTON_RIGHT", consts::BUTTON_RIGHT as i64),
+    ];
+
+    let mut header = String::from("/* Generated by src/capi/header.rs, run NESSY_UPDATE_HEADER=1 cargo test c_header to update */\n\
+        #ifndef NESSY_H\n#define NESSY_H\n\n#include <stddef.h>\n#include <stdint.h>\n\n\
+        #ifdef __cplusplus\nextern \"C\" {\n#endif\n\n");
+
+    for (name, value) in defines.iter() {
+        if name.is_empty() {
+            header += "\n";
+        } else {
+            // Negative values in parentheses so they expand safely
+            let value = if *value < 0 { format!("({})", value) } else { value.to_string() };
+            header += &format!("#define {} {}\n", name, value);
+        }
+    }
+
+    header += "\ntypedef struct Nessy Nessy;\n";
+    for function in FUNCTIONS.iter() {
+        header += &format!("\n{}\n", function);
+    }
+
+    header += "\n#ifdef __cplusplus\n}\n#endif\n\n#endif\n";
+    header
+}
+
+#[test]
+fn c_header() {
+    let header_path = std::path::Path::new("include").join("nessy.h");
+    if std::env::var_os("NESSY_UPDATE_HEADER").is_some() {
+        std::fs::write(&header_path, get_header()).unwrap();
+    }
+
+    assert_eq!(std::fs::read_to_string(&header_path).unwrap(), get_header(), "include/nessy.h is out of date");
+}
+
+// The C type of a parameter or return type of the exports, followed by a space unless it's a pointer
+#[cfg(test)]
+fn get_c_type(rust_type: &str) -> String {
+    let get_base_type = |rust_type: &str| match rust_type {
+        "Nes" => "Nessy",
+        "u8" => "uint8_t",
+        "u32" => "uint32_t",
+        "i32" => "int",
+        "usize" => "size_t",
+        "f32" => "float",
+        "std::os::raw::c_char" => "char",
+        rust_type => panic!("No C type for {}", rust_type),
+    };
+
+    match (rust_type.strip_prefix("*const "), rust_type.strip_prefix("*mut ")) {
+        (Some(pointee), _) => format!("const {} *", get_base_type(pointee)),
+        (_, Some(pointee)) => format!("{} *", get_base_type(pointee)),
+        _ => format!("{} ", get_base_type(rust_type)),
+    }
+}
+
+// The C prototype of every #[no_mangle] function in the source, in order
+#[cfg(test)]
+fn get_exported_prototypes(source: &str) -> Vec<String> {
+    source.lines().zip(source.lines().skip(1))
+        .filter(|(attribute, _)| attribute.trim() == "#[no_mangle]")
+        .map(|(_, signature)| {
+            let (name, rest) = signature.split_once("fn ").unwrap().1.split_once('(').unwrap();
+            let (params, rest) = rest.split_once(')').unwrap();
+            let return_type = rest.split_once("->")
+                .map_or(String::from("void "), |(_, return_type)| get_c_type(return_type.trim().trim_end_matches('{').trim()));
+
+            let params: Vec<String> = params.split(", ").filter(|param| !param.is_empty()).map(|param| {
+                let (param_name, param_type) = param.split_once(": ").unwrap();
+                format!("{}{}", get_c_type(param_type), param_name)
+            }).collect();
+            let params = if params.is_empty() { String::from("void") } else { params.join(", ") };
+
+            format!("{}{}({});", return_type, name, params)
+        })
+        .collect()
+}
+
+#[test]
+fn c_header_matches_exports() {
+    let prototypes: Vec<&str> = FUNCTIONS.iter().map(|function| function.lines().last().unwrap()).collect();
+    assert_eq!(prototypes, get_exported_prototypes(include_str!("exports.rs")));
+}
+
+// Builds examples/c/example.c against the cdylib and runs it on a small rom
+#[cfg(unix)]
+#[test]
+fn c_example() {
+    use std::process::Command;
+
+    // cargo test leaves the cdylib next to the test binary in target/<profile>/deps, cargo build one directory up.
+    // A lone cargo test --lib doesn't build it.
+    let deps_dir = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    let library_dir = [deps_dir.clone(), deps_dir.parent().unwrap().to_path_buf()].iter()
+        .find(|dir| ["libnessy.so", "libnessy.dylib"].iter().any(|name| dir.join(name).exists()))
+        .cloned();
+    let library_dir = match library_dir {
+        Some(library_dir) => library_dir,
+        None => {
+            println!("Skipping the C example, the cdylib isn't built (run cargo build --lib)");
+            return;
+        }
+    };
+    let work_dir = std::env::temp_dir().join(format!("nessy-c-example-{}", std::process::id()));
+    std::fs::create_dir_all(&work_dir).unwrap();
+
+    let rom_path = work_dir.join("test.nes");
+    std::fs::write(&rom_path, crate::nes::console::get_test_rom()).unwrap();
+
+    let example_path = work_dir.join("example");
+    let compile = Command::new("cc")
+        .args(["examples/c/example.c", "-Iinclude", "-o"]).arg(&example_path)
+        .arg(format!("-L{}", library_dir.display())).arg("-lnessy")
+        .output();
+    let compile = match compile {
+        Ok(compile) => compile,
+        Err(err) => {
+            println!("Skipping the C example, no C compiler : {}", err);
+            return;
+        }
+    };
+    assert!(compile.status.success(), "{}", String::from_utf8_lossy(&compile.stderr));
+
+    let run = Command::new(&example_path).arg(&rom_path).arg("10")
+        .env("LD_LIBRARY_PATH", &library_dir).env("DYLD_LIBRARY_PATH", &library_dir)
+        .output().unwrap();
+    let stdout = String::from_utf8_lossy(&run.stdout);
+    assert!(run.status.success(), "{}{}", stdout, String::from_utf8_lossy(&run.stderr));
+    assert!(stdout.contains("Ran 10 frames"), "{}", stdout);
+
+    std::fs::remove_dir_all(&work_dir).unwrap();
+}
@@ -0,0 +1,10 @@
+// The C API, include/nessy.h declares it and the header tests check it against the exports
+#[allow(clippy::missing_safety_doc)]
+pub mod exports;
+pub mod header;
+
+// Results of the functions returning an int
+pub const NESSY_OK: i32 = 0;
+pub const NESSY_ERROR_INVALID_ARGUMENT: i32 = -1;
+pub const NESSY_ERROR_CPU: i32 = -2;
+pub const NESSY_ERROR_SAVE_STATE: i32 = -3;
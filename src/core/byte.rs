@@ -1,86 +1,92 @@
 use super::consts;
 use std::ops::{Index, BitAnd, BitAndAssign, Shr, ShrAssign, Shl, ShlAssign, Sub, SubAssign, Add, AddAssign, BitOr, BitOrAssign, BitXor, BitXorAssign};
-use std::cmp::Ordering;
-use std::convert::{From, TryInto};
-use std::num::Wrapping;
+use std::convert::From;
 use std::fmt;
 
-fn _as_array(value: u8) -> [bool; consts::BYTE_SIZE] {
-    let mut arr: [bool ;8] = [false; consts::BYTE_SIZE];
-    let mut new_value = value;
+// A u8 with wrapping arithmetic and bit access, the same size and layout as the u8 itself
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Byte(u8);
 
-    for i in 0..consts::BYTE_SIZE {
-        arr[i] = new_value % 2 != 0;
-        new_value >>= 1;
+impl Byte {
+    #[inline]
+    pub const fn new(value: u8) -> Byte {
+        Byte(value)
     }
 
-    return arr;
-}
-
-// Byte struct
-#[derive(Copy, Clone)]
-pub struct Byte {
-    value: u8,
-    value_arr: [bool; consts::BYTE_SIZE],
-}
+    #[inline]
+    pub const fn get_value(&self) -> u8 {
+        self.0
+    }
 
-impl Byte {
-    pub fn new(value: u8) -> Byte {
-        Byte{value: value, value_arr: _as_array(value)}
+    #[inline]
+    pub fn set_value(&mut self, value: u8) {
+        self.0 = value;
     }
 
-    
-    pub fn from_bool_array(arr: [bool; 8]) -> Byte {
-        let mut new_value: u8 = 0;
+    #[inline]
+    pub const fn get_bit(&self, i: usize) -> bool {
+        (self.0 >> i) & 0x01 != 0
+    }
 
-        for i in 0..consts::BYTE_SIZE {
-            // FIXME : 0 ^ 0 is not 0 :(
-            new_value += 2u8.pow(i as u32) * (arr[i] as u8);
-        }
+    #[inline]
+    pub fn set_bit(&mut self, i: usize, value: bool) {
+        self.0 = (self.0 & !(1 << i)) | ((value as u8) << i);
+    }
 
-        Byte::new(new_value)
+    #[inline]
+    pub const fn is_negative(&self) -> bool {
+        self.0 & 0x80 != 0
     }
 
-    pub fn get_value(&self) -> u8 {
-        self.value
+    #[inline]
+    pub const fn is_zero(&self) -> bool {
+        self.0 == 0
     }
 
-    pub fn set_value(&mut self, value: u8) {
-        self.value = value;
-        self.value_arr = _as_array(value);
+    #[inline]
+    pub const fn get_i8(&self) -> i8 {
+        self.0 as i8
     }
 
-    pub fn is_negative(&self) -> bool {
-        self[0x07]
+    // The same memory as plain u8s, for handing ram to C frontends
+    #[inline]
+    pub fn as_u8_slice_mut(bytes: &mut [Byte]) -> &mut [u8] {
+        // Byte is a repr(transparent) u8
+        unsafe { std::slice::from_raw_parts_mut(bytes.as_mut_ptr() as *mut u8, bytes.len()) }
     }
 
-    pub fn get_i8(&self) -> i8 {
-        if self.value >= 0x80u8 {
-            (self.value as i16 - 0x100i16).try_into().unwrap()
-        } else {
-            self.value.try_into().unwrap()
-        }
+    // Shifts the carry in at bit 0, returns the result and the bit shifted out of bit 7
+    #[inline]
+    pub const fn rotate_left_through(&self, carry: bool) -> (Byte, bool) {
+        (Byte((self.0 << 1) | carry as u8), self.is_negative())
     }
 
-    pub fn as_array(&self) -> [bool; consts::BYTE_SIZE] {
-        self.value_arr
+    // Shifts the carry in at bit 7, returns the result and the bit shifted out of bit 0
+    #[inline]
+    pub const fn rotate_right_through(&self, carry: bool) -> (Byte, bool) {
+        (Byte((self.0 >> 1) | ((carry as u8) << 7)), self.0 & 0x01 != 0)
     }
 }
 
 // Trait implementation
 impl Index<usize> for Byte {
     type Output = bool;
-    fn index<'a>(&'a self, i: usize) -> &'a bool {
+
+    #[inline]
+    fn index(&self, i: usize) -> &bool {
         if i >= consts::BYTE_SIZE {
             panic!("Requested invalid index in byte")
         }
-        &self.value_arr[i as usize]
+
+        if self.get_bit(i) { &true } else { &false }
     }
 }
 
 impl From<u8> for Byte {
+    #[inline]
     fn from(item: u8) -> Byte {
-        Byte::new(item)
+        Byte(item)
     }
 }
 
@@ -96,181 +102,205 @@ impl fmt::Debug for Byte {
     }
 }
 
-impl PartialEq for Byte {
-    fn eq(&self, other: &Self) -> bool {
-        self.value == other.value
-    }
-}
-
-impl Eq for Byte {}
-
 impl BitAnd for Byte {
     type Output = Byte;
 
+    #[inline]
     fn bitand(self, rhs: Byte) -> Byte {
-        Byte::new(self.get_value() & rhs.get_value())
+        Byte(self.0 & rhs.0)
     }
 }
 
 impl BitAndAssign for Byte {
+    #[inline]
     fn bitand_assign(&mut self, rhs: Byte) {
-        self.set_value(self.get_value() & rhs.get_value());
+        self.0 &= rhs.0;
     }
 }
 
 impl BitOr for Byte {
     type Output = Byte;
+
+    #[inline]
     fn bitor(self, rhs: Byte) -> Byte {
-        Byte::new(self.get_value() | rhs.get_value())
+        Byte(self.0 | rhs.0)
     }
 }
 
 impl BitOrAssign for Byte {
+    #[inline]
     fn bitor_assign(&mut self, rhs: Byte) {
-        self.set_value(self.get_value() | rhs.get_value());
+        self.0 |= rhs.0;
     }
 }
 
 impl BitXor for Byte {
     type Output = Byte;
+
+    #[inline]
     fn bitxor(self, rhs: Byte) -> Byte {
-        Byte::new(self.get_value() ^ rhs.get_value())
+        Byte(self.0 ^ rhs.0)
     }
 }
 
 impl BitXorAssign for Byte {
+    #[inline]
     fn bitxor_assign(&mut self, rhs: Byte) {
-        self.set_value(self.get_value() ^ rhs.get_value());
+        self.0 ^= rhs.0;
     }
 }
 
 impl Add for Byte {
     type Output = Byte;
+
+    #[inline]
     fn add(self, rhs: Byte) -> Byte {
-        Byte::new(self.get_value() + rhs.get_value())
+        Byte(self.0.wrapping_add(rhs.0))
     }
 }
 
 impl Sub for Byte {
     type Output = Byte;
+
+    #[inline]
     fn sub(self, rhs: Byte) -> Byte {
-        Byte::new((Wrapping(self.get_value()) - Wrapping(rhs.get_value())).0)
+        Byte(self.0.wrapping_sub(rhs.0))
     }
 }
 
 impl AddAssign for Byte {
+    #[inline]
     fn add_assign(&mut self, rhs: Byte) {
-        self.set_value(self.get_value() + rhs.get_value());
+        self.0 = self.0.wrapping_add(rhs.0);
     }
 }
 
 impl SubAssign for Byte {
+    #[inline]
     fn sub_assign(&mut self, rhs: Byte) {
-        self.set_value((Wrapping(self.get_value()) - Wrapping(rhs.get_value())).0);
-    }
-}
-
-impl Ord for Byte {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.get_value().cmp(&other.get_value())
-    }
-}
-
-impl PartialOrd for Byte {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.get_value().cmp(&other.get_value()))
+        self.0 = self.0.wrapping_sub(rhs.0);
     }
 }
 
-
 impl Shr<usize> for Byte {
     type Output = Byte;
 
-    fn shr(self, _: usize) -> Byte {
-        Byte::new(self.get_value() >> 1)
+    #[inline]
+    fn shr(self, rhs: usize) -> Byte {
+        Byte(self.0 >> rhs)
     }
 }
 
 impl Shl<usize> for Byte {
     type Output = Byte;
 
-    fn shl(self, _: usize) -> Byte {
-        Byte::new(self.get_value() << 1)
+    #[inline]
+    fn shl(self, rhs: usize) -> Byte {
+        Byte(self.0 << rhs)
     }
 }
 
 impl ShrAssign<usize> for Byte {
+    #[inline]
     fn shr_assign(&mut self, rhs: usize) {
-        self.set_value(self.get_value() >> rhs);
+        self.0 >>= rhs;
     }
 }
 
 impl ShlAssign<usize> for Byte {
+    #[inline]
     fn shl_assign(&mut self, rhs: usize) {
-        self.set_value(self.get_value() << rhs);
+        self.0 <<= rhs;
     }
 }
 
 
 // Tests
 
+#[cfg(test)]
+fn get_bits(b: Byte) -> [bool; consts::BYTE_SIZE] {
+    let mut bits = [false; consts::BYTE_SIZE];
+    for (i, bit) in bits.iter_mut().enumerate() {
+        *bit = b[i];
+    }
+
+    bits
+}
+
+#[cfg(test)]
+fn from_bits(bits: [bool; consts::BYTE_SIZE]) -> Byte {
+    let mut b = Byte::new(0x00);
+    for (i, bit) in bits.iter().enumerate() {
+        b.set_bit(i, *bit);
+    }
+
+    b
+}
+
 #[test]
 fn array_convertion_1() {
-    assert_eq!(Byte::new(0x01).as_array(), [true, false, false, false, false, false, false, false]);
+    assert_eq!(get_bits(Byte::new(0x01)), [true, false, false, false, false, false, false, false]);
 }
 
 #[test]
 fn array_convertion_2() {
-    assert_eq!(Byte::new(0x05).as_array(), [true, false, true, false, false, false, false, false]);
+    assert_eq!(get_bits(Byte::new(0x05)), [true, false, true, false, false, false, false, false]);
 }
 
 #[test]
 fn array_convertion_3() {
-    assert_eq!(Byte::new(0x09).as_array(), [true, false, false, true, false, false, false, false]);
+    assert_eq!(get_bits(Byte::new(0x09)), [true, false, false, true, false, false, false, false]);
 }
 
 #[test]
 fn array_convertion_4() {
-    assert_eq!(Byte::new(u8::MAX).as_array(), [true, true, true, true, true, true, true, true]);
+    assert_eq!(get_bits(Byte::new(u8::MAX)), [true, true, true, true, true, true, true, true]);
 }
 
 #[test]
 fn array_convertion_5() {
-    assert_eq!(Byte::new(0x00).as_array(), [false, false, false, false, false, false, false, false]);
+    assert_eq!(get_bits(Byte::new(0x00)), [false, false, false, false, false, false, false, false]);
 }
 
 #[test]
 fn byte_index() {
     let b: Byte = 0x26.into();
-    assert_eq!(b.as_array(), [false, true, true, false, false, true, false, false]);
+    assert_eq!(get_bits(b), [false, true, true, false, false, true, false, false]);
     assert_eq!(b[0], false);
     assert_eq!(b[5], true);
+    assert!(b.get_bit(5));
+}
+
+#[test]
+#[should_panic]
+fn byte_index_out_of_range() {
+    let _ = Byte::new(0xFF)[8];
 }
 
 #[test]
 fn update_value() {
     let mut b: Byte = 0x26.into();
-    assert_eq!(b.as_array(), [false, true, true, false, false, true, false, false]);
+    assert_eq!(get_bits(b), [false, true, true, false, false, true, false, false]);
     assert_eq!(b[0], false);
     assert_eq!(b[5], true);
 
     b.set_value(0xBD);
-    assert_eq!(b.as_array(), [true, false, true, true, true, true, false, true]);
+    assert_eq!(get_bits(b), [true, false, true, true, true, true, false, true]);
     b >>= 1;
-    assert_eq!(b.as_array(), [false, true, true, true, true, false, true, false]);
+    assert_eq!(get_bits(b), [false, true, true, true, true, false, true, false]);
 }
 
 #[test]
 fn from_array() {
-    assert_eq!(Byte::new(0x57), Byte::from_bool_array(Byte::new(0x57).as_array()));
-    assert_eq!(Byte::new(0x32), Byte::from_bool_array(Byte::new(0x32).as_array()));
-    assert_eq!(Byte::new(0xFF), Byte::from_bool_array(Byte::new(0xFF).as_array()));
-    assert_eq!(Byte::new(0xAC), Byte::from_bool_array(Byte::new(0xAC).as_array()));
-    assert_eq!(Byte::new(0xB0), Byte::from_bool_array(Byte::new(0xB0).as_array()));
-    assert_eq!(Byte::new(0x00), Byte::from_bool_array(Byte::new(0x00).as_array()));
+    assert_eq!(Byte::new(0x57), from_bits(get_bits(Byte::new(0x57))));
+    assert_eq!(Byte::new(0x32), from_bits(get_bits(Byte::new(0x32))));
+    assert_eq!(Byte::new(0xFF), from_bits(get_bits(Byte::new(0xFF))));
+    assert_eq!(Byte::new(0xAC), from_bits(get_bits(Byte::new(0xAC))));
+    assert_eq!(Byte::new(0xB0), from_bits(get_bits(Byte::new(0xB0))));
+    assert_eq!(Byte::new(0x00), from_bits(get_bits(Byte::new(0x00))));
 
     let mut b = Byte::new(0xC2);
-    let mut b_arr = b.as_array();
+    let mut b_arr = get_bits(b);
 
     // 0b11000010
     assert_eq!(b_arr, [false, true, false, false, false, false, true, true]);
@@ -278,22 +308,55 @@ fn from_array() {
     b_arr[0] = true;
     assert_eq!(b_arr, [true, true, false, false, false, false, true, true]);
 
-    let mut b_new = Byte::from_bool_array(b_arr);
+    let mut b_new = from_bits(b_arr);
     assert_eq!(b_new.get_value(), 0xC3);
 
     b = Byte::new(0xD3);
-    b_arr = b.as_array();
+    b_arr = get_bits(b);
 
     // 0b11010011
     assert_eq!(b_arr, [true, true, false, false, true, false, true, true]);
 
     b_arr[0] = false;
     assert_eq!(b_arr, [false, true, false, false, true, false, true, true]);
-    
-    b_new = Byte::from_bool_array(b_arr);
+
+    b_new = from_bits(b_arr);
     assert_eq!(b_new.get_value(), 0xD2);
 }
 
+#[test]
+fn set_bit() {
+    // 0b11000010
+    let mut b = Byte::new(0xC2);
+    b.set_bit(0, true);
+    assert_eq!(b.get_value(), 0xC3);
+    b.set_bit(0, true);
+    assert_eq!(b.get_value(), 0xC3);
+
+    // 0b11010011
+    b = Byte::new(0xD3);
+    b.set_bit(0, false);
+    assert_eq!(b.get_value(), 0xD2);
+    b.set_bit(7, false);
+    assert_eq!(b.get_value(), 0x52);
+    assert!(!b.is_negative());
+    assert!(!b.is_zero());
+    assert!(Byte::new(0x00).is_zero());
+}
+
+#[test]
+fn rotate_through_carry() {
+    assert_eq!(Byte::new(0x81).rotate_left_through(false), (Byte::new(0x02), true));
+    assert_eq!(Byte::new(0x40).rotate_left_through(true), (Byte::new(0x81), false));
+    assert_eq!(Byte::new(0x81).rotate_right_through(false), (Byte::new(0x40), true));
+    assert_eq!(Byte::new(0x02).rotate_right_through(true), (Byte::new(0x81), false));
+}
+
+#[test]
+fn same_size_as_u8() {
+    assert_eq!(std::mem::size_of::<Byte>(), std::mem::size_of::<u8>());
+}
+
 #[test]
 fn format_string() {
     assert_eq!(format!("{}", Byte::new(0xA6)), "0xA6");
@@ -348,4 +411,26 @@ fn shift_left() {
 
     assert_eq!((b >> 1).get_value(), 0x02);
     assert_eq!(b.get_value(), 0x05);
-}
\ No newline at end of file
+}
+
+#[test]
+fn shift_by_count() {
+    assert_eq!(Byte::new(0xF0) >> 4, Byte::new(0x0F));
+    assert_eq!(Byte::new(0x0F) << 4, Byte::new(0xF0));
+
+    let mut b = Byte::new(0x81);
+    b <<= 3;
+    assert_eq!(b, Byte::new(0x08));
+    b >>= 2;
+    assert_eq!(b, Byte::new(0x02));
+}
+
+#[test]
+fn byte_slice_as_u8() {
+    let mut bytes = [Byte::new(0x12), Byte::new(0x34)];
+    let values = Byte::as_u8_slice_mut(&mut bytes);
+    assert_eq!(values, &[0x12, 0x34]);
+
+    values[1] = 0x56;
+    assert_eq!(bytes[1], Byte::new(0x56));
+}
@@ -6,6 +6,21 @@ pub const STACK_SIZE: u8 = 0xFD;
 
 pub const PROGRAM_MEMORY_ADDR: u16 = 0x0600;
 
+pub const NMI_VECTOR_ADDR: u16 = 0xFFFA;
+pub const RESET_VECTOR_ADDR: u16 = 0xFFFC;
+// Shared by BRK and IRQ
+pub const IRQ_VECTOR_ADDR: u16 = 0xFFFE;
+
+// Status register bits, B and the unused bit only exist in the copy pushed to the stack
+pub const STATUS_CARRY: u8 = 0x01;
+pub const STATUS_ZERO: u8 = 0x02;
+pub const STATUS_INTERRUPT_DISABLE: u8 = 0x04;
+pub const STATUS_DECIMAL_MODE: u8 = 0x08;
+pub const STATUS_BREAK: u8 = 0x10;
+pub const STATUS_UNUSED: u8 = 0x20;
+pub const STATUS_OVERFLOW: u8 = 0x40;
+pub const STATUS_NEGATIVE: u8 = 0x80;
+
 pub const PAGE_CROSS_EXTRA_CYCLE_WHITELIST: [u8;49] = 
 [0x85, 0x95, 0x8D, 0x9D, 0x99, 0x81, 0x91,
 0xC3, 0xC7, 0xCF, 0xD3, 0xD7, 0xDB, 0xDF,
@@ -15,6 +30,19 @@ pub const PAGE_CROSS_EXTRA_CYCLE_WHITELIST: [u8;49] =
 0x23, 0x27, 0x2F, 0x33, 0x37, 0x3B, 0x3F,
 0x43, 0x47, 0x4F, 0x53, 0x57, 0x5B, 0x5F];
 
+pub const UNOFFICIAL_OPCODES: [u8;80] = 
+[0x1A, 0x3A, 0x5A, 0x7A, 0xDA, 0xFA, 0x80, 0x82, 0x89, 0xC2, 0xE2,
+0x04, 0x44, 0x64, 0x0C, 0x14, 0x34, 0x54, 0x74, 0xD4, 0xF4, 0x1C, 0x3C, 0x5C, 0x7C, 0xDC, 0xFC,
+0xA3, 0xA7, 0xAF, 0xB3, 0xB7, 0xBF, 0x83, 0x87, 0x8F, 0x97, 0xEB,
+0x03, 0x07, 0x0F, 0x13, 0x17, 0x1B, 0x1F,
+0x23, 0x27, 0x2F, 0x33, 0x37, 0x3B, 0x3F,
+0x43, 0x47, 0x4F, 0x53, 0x57, 0x5B, 0x5F,
+0x63, 0x67, 0x6F, 0x73, 0x77, 0x7B, 0x7F,
+0xC3, 0xC7, 0xCF, 0xD3, 0xD7, 0xDB, 0xDF,
+0xE3, 0xE7, 0xEF, 0xF3, 0xF7, 0xFB, 0xFF];
+
+pub const INES_PRG_ROM_BANK_SIZE: usize = 0x4000;
+
 pub const NROM_PRG_RAM_RANGE_START: u16 = 0x6000;
 pub const NROM_PRG_RAM_RANGE_END: u16 = 0x7FFF;
 
@@ -24,4 +52,70 @@ pub const NROM_FIRST_PRG_ROM_RANGE_END: u16 = 0xBFFF;
 pub const NROM_SECOND_PRG_ROM_RANGE_START: u16 = 0xC000;
 pub const NROM_SECOND_PRG_ROM_RANGE_END: u16 = 0xFFFF;
 
-pub const NROM_MAPPER_ID: u8 = 0x00;
\ No newline at end of file
+pub const NROM_MAPPER_ID: u8 = 0x00;
+
+pub const MMC1_PRG_RAM_RANGE_START: u16 = 0x6000;
+pub const MMC1_PRG_RAM_RANGE_END: u16 = 0x7FFF;
+
+pub const MMC1_FIRST_PRG_ROM_RANGE_START: u16 = 0x8000;
+pub const MMC1_SECOND_PRG_ROM_RANGE_START: u16 = 0xC000;
+pub const MMC1_SECOND_PRG_ROM_RANGE_END: u16 = 0xFFFF;
+
+pub const MMC1_PRG_ROM_BANK_SIZE: usize = 0x4000;
+pub const MMC1_CONTROL_POWER_ON: u8 = 0x0C;
+
+pub const MMC1_MAPPER_ID: u8 = 0x01;
+
+pub const IO_REGISTERS_RANGE_START: u16 = 0x2000;
+pub const IO_REGISTERS_RANGE_END: u16 = 0x401F;
+
+// The console's own 2KB of ram, mirrored up to $1FFF
+pub const INTERNAL_RAM_SIZE: u16 = 0x0800;
+
+pub const CONTROLLER_1_ADDR: u16 = 0x4016;
+pub const CONTROLLER_2_ADDR: u16 = 0x4017;
+// The upper bits of controller reads come from the open bus, usually the high byte of the address
+pub const CONTROLLER_OPEN_BUS: u8 = 0x40;
+
+// Controller buttons in the order the shift register reports them
+pub const BUTTON_A: u8 = 0x01;
+pub const BUTTON_B: u8 = 0x02;
+pub const BUTTON_SELECT: u8 = 0x04;
+pub const BUTTON_START: u8 = 0x08;
+pub const BUTTON_UP: u8 = 0x10;
+pub const BUTTON_DOWN: u8 = 0x20;
+pub const BUTTON_LEFT: u8 = 0x40;
+pub const BUTTON_RIGHT: u8 = 0x80;
+
+pub const PPU_DOTS_PER_CPU_CYCLE: usize = 3;
+pub const PPU_DOTS_PER_SCANLINE: usize = 341;
+pub const PPU_SCANLINES_PER_FRAME: usize = 262;
+pub const PPU_VBLANK_START_SCANLINE: usize = 241;
+
+pub const CPU_CYCLES_PER_SECOND: usize = 1789773;
+// PAL consoles run the cpu slower and the ppu at 3.2 dots per cpu cycle, over 312 scanlines
+pub const PAL_CPU_CYCLES_PER_SECOND: usize = 1662607;
+pub const PAL_PPU_DOTS_PER_CPU_CYCLE_NUMERATOR: usize = 16;
+pub const PAL_PPU_DOTS_PER_CPU_CYCLE_DENOMINATOR: usize = 5;
+pub const PAL_PPU_SCANLINES_PER_FRAME: usize = 312;
+pub const GDB_DEFAULT_PORT: u16 = 6502;
+// How long a trace runs when no frame count is given, about 10 seconds of NTSC time
+pub const TRACE_DEFAULT_FRAMES: usize = 600;
+
+pub const SAVE_STATE_MAGIC: &[u8; 4] = b"NSST";
+pub const SAVE_STATE_VERSION: u16 = 1;
+
+// Cycles each benchmark workload runs for, about 11 seconds of nes time
+pub const BENCHMARK_DEFAULT_CYCLE_BUDGET: usize = 20_000_000;
+
+// The picture the ppu outputs, as RGBA pixels
+pub const FRAME_WIDTH: usize = 256;
+pub const FRAME_HEIGHT: usize = 240;
+pub const FRAME_BYTES_PER_PIXEL: usize = 4;
+pub const AUDIO_SAMPLE_RATE: usize = 44100;
+
+// Window sizes of the desktop frontend, as multiples of the frame
+pub const DESKTOP_DEFAULT_SCALE: usize = 3;
+pub const DESKTOP_MAX_SCALE: usize = 8;
+// Falling behind by more than this restarts the pacing instead of rushing frames to catch up
+pub const DESKTOP_RESYNC_THRESHOLD_MS: u64 = 100;
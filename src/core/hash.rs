@@ -0,0 +1,10 @@
+// FNV-1a, fast and stable across runs and platforms, used to tell roms and machine states apart
+pub fn get_fnv1a_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xCBF29CE484222325u64, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001B3))
+}
+
+#[test]
+fn fnv1a_hash() {
+    assert_eq!(get_fnv1a_hash(&[]), 0xCBF29CE484222325);
+    assert_eq!(get_fnv1a_hash(b"a"), 0xAF63DC4C8601EC8C);
+}
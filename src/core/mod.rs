@@ -1,7 +1,9 @@
 mod byte;
 mod double;
+mod hash;
 pub mod memory;
 pub mod consts;
 
 pub use byte::Byte;
-pub use double::Double;
\ No newline at end of file
+pub use double::Double;
+pub use hash::get_fnv1a_hash;
\ No newline at end of file
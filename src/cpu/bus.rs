@@ -0,0 +1,66 @@
+use crate::core::Byte;
+use crate::core::Double;
+use crate::savestate::{SaveStateError, StateReader, StateWriter};
+
+// Everything the cpu is connected to, the nes cartridge and memory map is one implementation
+pub trait CpuBus {
+    fn read(&self, addr: Double) -> Byte;
+    fn write(&mut self, addr: Double, value: Byte);
+
+    // A read without side effects for debuggers and tracers, buses with side effecting registers override it
+    fn peek(&self, addr: Double) -> Byte {
+        self.read(addr)
+    }
+
+    // Called after every instruction with the cycles it took, so other hardware can catch up
+    fn tick(&mut self, _cycles: usize) {}
+
+    // The prg rom bank mapped at the address, None outside of prg rom or on buses without banks
+    fn get_prg_bank(&self, _addr: Double) -> Option<usize> {
+        None
+    }
+
+    // Save states, buses keeping state outside of the cpu memory map must write all of it
+    fn save_state(&self, _state: &mut StateWriter) {}
+
+    fn load_state(&mut self, _state: &mut StateReader) -> Result<(), SaveStateError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+struct TestBus {
+    memory: [u8; 0x10000],
+    ticked_cycles: usize,
+}
+
+#[cfg(test)]
+impl CpuBus for TestBus {
+    fn read(&self, addr: Double) -> Byte {
+        Byte::new(self.memory[addr.get_value() as usize])
+    }
+
+    fn write(&mut self, addr: Double, value: Byte) {
+        self.memory[addr.get_value() as usize] = value.get_value();
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        self.ticked_cycles += cycles;
+    }
+}
+
+#[test]
+fn cpu_runs_on_custom_bus() {
+    use super::cpu::Cpu;
+
+    // LDA #$2A ; STA $10
+    let mut bus = TestBus{memory: [0x00; 0x10000], ticked_cycles: 0};
+    bus.memory[0x0200..0x0204].copy_from_slice(&[0xA9, 0x2A, 0x85, 0x10]);
+
+    let mut cpu = Cpu::new_with_entry_point(bus, Double::from(0x0200u16));
+    cpu.execute_instruction().unwrap();
+    cpu.execute_instruction().unwrap();
+
+    assert_eq!(cpu.get_bus().memory[0x10], 0x2A);
+    assert_eq!(cpu.get_bus().ticked_cycles, 5);
+}
@@ -9,14 +9,25 @@ use crate::core::Double;
 use crate::mapper::Mapper;
 
 use super::CpuError;
-use super::instructions::{Instruction, get_instruction_set, get_unknown_instruction};
-
-extern crate simplelog;
-use simplelog::{ConfigBuilder, Level, CombinedLogger, TermLogger, LevelFilter, TerminalMode, Color};
-
+use super::CpuBus;
+use super::CpuVariant;
+use super::StatusRegister;
+use super::instructions::{Instruction, get_instruction_set, get_cmos_instruction_set, get_cycle_table};
+use crate::disasm::{self, DisassembledInstruction};
+use crate::tracer::Tracer;
+use crate::savestate::{SaveStateError, StateReader, StateWriter};
+
+// Logs of the addressing and stack helpers, they run for every instruction so they're only built with the cpu-log feature
+macro_rules! cpu_log {
+    ($($arg:tt)*) => {
+        if cfg!(feature = "cpu-log") {
+            log::trace!($($arg)*);
+        }
+    };
+}
 
 // #[derive(Clone)]
-pub struct Cpu {
+pub struct Cpu<B: CpuBus = Box<dyn Mapper>> {
     reg_a: Byte,
     reg_x: Byte,
     reg_y: Byte,
@@ -24,73 +35,115 @@ pub struct Cpu {
     stack_pointer: Byte,
     // memory: Memory,
 
-    flag_carry: bool,
-    flag_zero: bool,
-    flag_interrupt_disable: bool,
-    flag_decimal_mode: bool,
-    flag_break: bool,
-    flag_overflow: bool,
-    flag_negative: bool,
+    status: StatusRegister,
 
     cycle_counter: usize,
 
     instruction_set: HashMap<u8, Instruction>,
+    // Looked up for every instruction, the instruction set is only for disassembly
+    cycle_table: [u8; 0x100],
     current_opcode: Byte,
-    mapper: Box<dyn Mapper>,
+    variant: CpuVariant,
+    // Boxed so a cpu without a tracer stays small, checked before every instruction
+    tracer: Option<Box<Tracer>>,
+    bus: B,
 }
 
-impl fmt::Display for Cpu {
+impl<B: CpuBus> fmt::Display for Cpu<B> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "#{} -> {}", self.program_counter, self.get_memory_addr(self.program_counter))
     }
 }
 
-impl fmt::Debug for Cpu {
+impl<B: CpuBus> fmt::Debug for Cpu<B> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "#{} -> {}", self.program_counter, self.get_memory_addr(self.program_counter))
     }
 }
 
-impl Cpu {
-    pub fn new(mapper: Box<dyn Mapper>) -> Result<Cpu, CpuError> {
-        // Calculate starting point
-        let entry_point_least = mapper.get_memory_addr(0xFFFCu16.into());
-        let entry_point_most = mapper.get_memory_addr(0xFFFDu16.into());
+fn get_vector<B: CpuBus>(bus: &B, vector_addr: u16) -> Double {
+    let least_significant = bus.read(vector_addr.into());
+    let most_significant = bus.read((vector_addr + 1).into());
 
-        if entry_point_least.is_err() || entry_point_most.is_err() {
-            return Err(CpuError::FailedParsingEntryPoint)
-        }
+    Double::new_from_significant(least_significant, most_significant)
+}
 
-        let entry_point = Double::new_from_significant(entry_point_least.unwrap(), entry_point_most.unwrap());
+fn get_entry_point<B: CpuBus>(bus: &B) -> Double {
+    get_vector(bus, consts::RESET_VECTOR_ADDR)
+}
+
+impl<B: CpuBus> Cpu<B> {
+    pub fn new(bus: B) -> Cpu<B> {
+        // Calculate starting point
+        let entry_point = get_entry_point(&bus);
         log::info!("Program Entry point is {}", entry_point);
 
-        Ok(Cpu {
+        Cpu::new_with_entry_point(bus, entry_point)
+    }
+
+    // Starts at the given address instead of the reset vector, for programs that aren't nes roms
+    pub fn new_with_entry_point(bus: B, entry_point: Double) -> Cpu<B> {
+        Cpu::new_with_variant(bus, entry_point, CpuVariant::Ricoh2A03)
+    }
+
+    pub fn new_with_variant(bus: B, entry_point: Double, variant: CpuVariant) -> Cpu<B> {
+        let instruction_set = match variant {
+            CpuVariant::Cmos65C02 => get_cmos_instruction_set(),
+            _ => get_instruction_set(),
+        };
+        let cycle_table = get_cycle_table(&instruction_set);
+
+        Cpu {
             reg_a: Byte::new(0x00),
             reg_x: Byte::new(0x00),
             reg_y: Byte::new(0x00),
             program_counter: entry_point,
             stack_pointer: Byte::new(consts::STACK_SIZE),
-            mapper: mapper,
-            flag_carry: false, // TODO : Verify flag start state
-            flag_zero: false,
-            flag_interrupt_disable: true,
-            flag_decimal_mode: false,
-            flag_break: false,
-            flag_overflow: false,
-            flag_negative: false,
-            instruction_set: get_instruction_set(),
+            bus,
+            status: StatusRegister::new(),
+            instruction_set,
+            cycle_table,
             cycle_counter:7,
             current_opcode: Byte::new(0x00),
-        })
+            variant,
+            tracer: None,
+        }
+    }
+
+    pub fn get_variant(&self) -> CpuVariant {
+        self.variant
+    }
+
+    // Like pressing the reset button, registers are kept and the stack pointer moves as if an interrupt happened
+    pub fn reset(&mut self) {
+        self.program_counter = get_entry_point(&self.bus);
+        log::info!("Reset, program entry point is {}", self.program_counter);
+
+        self.stack_pointer -= Byte::new(3);
+        self.status.set_interrupt_disable(true);
+        self.cycle_counter += 7;
     }
 
     // Getters
     pub fn get_memory_addr(&self, index: Double) -> Byte {
-        self.mapper.get_memory_addr(index).unwrap()
+        self.bus.read(index)
     }
 
     pub fn set_memory_addr(&mut self, index: Double, b: Byte) {
-        self.mapper.set_memory_addr(index, b).unwrap()
+        self.bus.write(index, b)
+    }
+
+    // Reads memory without side effects, for debugging tools
+    pub fn peek_memory_addr(&self, index: Double) -> Byte {
+        self.bus.peek(index)
+    }
+
+    pub fn get_bus(&self) -> &B {
+        &self.bus
+    }
+
+    pub fn get_bus_mut(&mut self) -> &mut B {
+        &mut self.bus
     }
 
     pub fn get_program_counter(&self) -> Double {
@@ -113,6 +166,85 @@ impl Cpu {
         self.reg_y
     }
 
+    pub fn set_reg_a(&mut self, value: Byte) {
+        self.reg_a = value;
+    }
+
+    pub fn set_reg_x(&mut self, value: Byte) {
+        self.reg_x = value;
+    }
+
+    pub fn set_reg_y(&mut self, value: Byte) {
+        self.reg_y = value;
+    }
+
+    pub fn get_stack_pointer(&self) -> Byte {
+        self.stack_pointer
+    }
+
+    pub fn set_stack_pointer(&mut self, stack_pointer: Byte) {
+        self.stack_pointer = stack_pointer;
+    }
+
+    pub fn get_cycle_counter(&self) -> usize {
+        self.cycle_counter
+    }
+
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        self.tracer = tracer.map(Box::new);
+    }
+
+    pub fn get_tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_deref()
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take().map(|tracer| *tracer)
+    }
+
+    // The registers and counters, the bus saves its own state
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.variant as u8);
+        state.write_u8(self.reg_a.get_value());
+        state.write_u8(self.reg_x.get_value());
+        state.write_u8(self.reg_y.get_value());
+        state.write_u16(self.program_counter.get_value());
+        state.write_u8(self.stack_pointer.get_value());
+        state.write_u8(Byte::from(self.status).get_value());
+        state.write_u64(self.cycle_counter as u64);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> std::result::Result<(), SaveStateError> {
+        // The instruction set depends on the variant, states only load on the same variant
+        if state.read_u8()? != self.variant as u8 {
+            return Err(SaveStateError::InvalidValue("cpu variant"));
+        }
+
+        let registers = [state.read_u8()?, state.read_u8()?, state.read_u8()?];
+        let program_counter = state.read_u16()?;
+        let stack_pointer = state.read_u8()?;
+        let status = state.read_u8()?;
+        let cycle_counter = state.read_u64()?;
+
+        self.reg_a = Byte::new(registers[0]);
+        self.reg_x = Byte::new(registers[1]);
+        self.reg_y = Byte::new(registers[2]);
+        self.program_counter = Double::from(program_counter);
+        self.stack_pointer = Byte::new(stack_pointer);
+        self.status = StatusRegister::from(Byte::new(status));
+        self.cycle_counter = cycle_counter as usize;
+
+        Ok(())
+    }
+
+    pub fn get_status(&self) -> StatusRegister {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: StatusRegister) {
+        self.status = status;
+    }
+
     // Arguments parsing
     fn get_first_arg(&self) -> Byte {
         self.get_memory_addr(self.program_counter + 1)
@@ -142,10 +274,10 @@ impl Cpu {
     fn get_zero_page_y_addr(&self) -> Byte {
         // Like zero_page, but reg_y is appended to it
         let start_addr = self.get_first_arg();
-        log::trace!("Zero Page addr (For ZeroPage,Y) is {}", start_addr);
+        cpu_log!("Zero Page addr (For ZeroPage,Y) is {}", start_addr);
 
         let start_addr_y = Byte::new(start_addr.get_value().wrapping_add(self.reg_y.get_value()));
-        log::trace!("Zero Page Y addr is {}", start_addr_y);
+        cpu_log!("Zero Page Y addr is {}", start_addr_y);
 
         return start_addr_y;
     }
@@ -160,8 +292,8 @@ impl Cpu {
         // A memory address represented as two little endian bytes
         let addr = Double::new_from_significant(self.get_first_arg(), self.get_second_arg());
 
-        log::trace!("Absolute addr is {}", addr);
-        log::trace!("Value at Absolute addr is {}", self.get_memory_addr(addr));
+        cpu_log!("Absolute addr is {}", addr);
+        cpu_log!("Value at Absolute addr is {}", self.peek_memory_addr(addr));
 
         return addr;
     }
@@ -172,12 +304,12 @@ impl Cpu {
         let absolute_x_addr = Double::from(absolute_addr.get_value().wrapping_add(self.reg_x.get_value() as u16));
 
         if absolute_addr.get_most_significant() != absolute_x_addr.get_most_significant() {
-            log::trace!("Crossed Page in absolute,X");
+            cpu_log!("Crossed Page in absolute,X");
             if !consts::PAGE_CROSS_EXTRA_CYCLE_WHITELIST.contains(&self.current_opcode.get_value()) {
-                log::trace!("Increasing cycle counter by one");
+                cpu_log!("Increasing cycle counter by one");
                 self.cycle_counter += 1;
             } else {
-                log::trace!("Opcode in extra cycle whitelist");
+                cpu_log!("Opcode in extra cycle whitelist");
             }
         }
 
@@ -203,39 +335,48 @@ impl Cpu {
         // This function return the latter
 
         let first_memory_addr = Double::new_from_significant(self.get_first_arg(), self.get_second_arg());
-        let second_memory_addr = Double::new_from_significant(self.get_first_arg(), 
-            self.get_second_arg().get_value().wrapping_add(1).into());
+        // The least significant byte wraps without carrying into the page, like the 6502 does
+        let second_memory_addr = Double::new_from_significant(self.get_first_arg().get_value().wrapping_add(1).into(), 
+            self.get_second_arg());
 
         let target_memory_addr = Double::new_from_significant(self.get_memory_addr(first_memory_addr), 
                     self.get_memory_addr(second_memory_addr));
 
-        log::trace!("Indirect memory addr in {} -> {}", first_memory_addr, target_memory_addr);
+        cpu_log!("Indirect memory addr in {} -> {}", first_memory_addr, target_memory_addr);
 
         return target_memory_addr;
     }
 
+    // 65C02 (zp) addressing, like (Indirect),Y without the index
+    fn get_zero_page_indirect_addr(&self) -> Double {
+        let zero_page_addr = self.get_first_arg();
+
+        Double::new_from_significant(self.get_memory_addr(zero_page_addr.into()),
+            self.get_memory_addr(Byte::new(zero_page_addr.get_value().wrapping_add(1)).into()))
+    }
+
     fn get_indexed_indirect_x_addr(&self) -> Double {
         let start_addr = self.get_zero_page_x_addr();
         
-        log::trace!("ZeroPage,X Address (for Indirect,X) is {}", start_addr);
+        cpu_log!("ZeroPage,X Address (for Indirect,X) is {}", start_addr);
 
         let addr = Double::new_from_significant(self.get_memory_addr(start_addr.into()), 
             self.get_memory_addr(Byte::new(start_addr.get_value().wrapping_add(1)).into()));
         
-        log::trace!("Indirect,X address is {} -> {}", addr, self.get_memory_addr(addr));
+        cpu_log!("Indirect,X address is {} -> {}", addr, self.peek_memory_addr(addr));
         return addr;
     }
 
     fn get_indirect_indexed_y_addr(&mut self) -> Double {
         let least_addr = self.get_first_arg();
 
-        log::trace!("ZeroPage Address of Indirect,Y is {}", least_addr);
+        cpu_log!("ZeroPage Address of Indirect,Y is {}", least_addr);
 
         let least = self.get_memory_addr(least_addr.into());
         let most = self.get_memory_addr(Byte::new(least_addr.get_value().wrapping_add(1)).into());
 
         let indirect_addr = Double::new_from_significant(least, most);
-        log::trace!("Indirect address (of Indirect,Y) is {}", indirect_addr);
+        cpu_log!("Indirect address (of Indirect,Y) is {}", indirect_addr);
 
         let target_addr = Double::from(indirect_addr.get_value().wrapping_add(self.reg_y.get_value().into()));
         
@@ -245,27 +386,24 @@ impl Cpu {
             }
         }
 
-        log::trace!("Indirect,Y address is {} -> {}", target_addr, self.get_memory_addr(target_addr));
+        cpu_log!("Indirect,Y address is {} -> {}", target_addr, self.peek_memory_addr(target_addr));
 
         return target_addr;
     }
 
     // Utils for flag usage
     fn set_negative_flag(&mut self, b: Byte) {
-        self.flag_negative = b.is_negative();
+        self.status.set_negative(b.is_negative());
     }
 
     fn set_zero_flag(&mut self, b: Byte) {
-        self.flag_zero = b.get_value() == 0;
+        self.status.set_zero(b.is_zero());
     }
 
     fn push_stack(&mut self, value: Byte) -> std::result::Result<(), CpuError> {
-        log::trace!("Pushing {} to stack", value);
-
-        if self.stack_pointer.get_value() == 0 {
-            return Err(CpuError::StackOverflow);
-        }
+        cpu_log!("Pushing {} to stack", value);
 
+        // The stack pointer wraps around inside the stack page, like the 6502 does
         self.set_memory_addr(Double::from(consts::STACK_ADDR) + Double::from(self.stack_pointer), value);
         self.stack_pointer -= Byte::new(1);
 
@@ -273,35 +411,20 @@ impl Cpu {
     }
 
     fn pop_stack(&mut self) -> std::result::Result<Byte, CpuError> {
-        if self.stack_pointer.get_value() == consts::STACK_SIZE {
-            return Err(CpuError::StackEmpty);
-        }
-
         self.stack_pointer += Byte::new(1);
         let stack_value = self.get_memory_addr(Double::from(consts::STACK_ADDR) + Double::from(self.stack_pointer));
         
-        log::trace!("Popped {} from stack", stack_value);
+        cpu_log!("Popped {} from stack", stack_value);
 
         Ok(stack_value)
     }
 
-    fn get_processor_status_byte(&self) -> Byte {
-        let mut new_byte_arr: [bool; 8] = [false; 8];
-
-        new_byte_arr[0] = self.flag_carry;
-        new_byte_arr[1] = self.flag_zero;
-        new_byte_arr[2] = self.flag_interrupt_disable;
-        new_byte_arr[3] = self.flag_decimal_mode;
-        new_byte_arr[4] = self.flag_break;
-        new_byte_arr[5] = true;
-        new_byte_arr[6] = self.flag_overflow;
-        new_byte_arr[7] = self.flag_negative;
-
-        Byte::from_bool_array(new_byte_arr)
-    }
-
     // Instruction shortcuts
     fn execute_sbc(&mut self, value: Byte) -> Result<(), CpuError> {
+        if self.is_decimal_mode_active() {
+            return self.execute_decimal_sbc(value);
+        }
+
         self.execute_adc(Byte::new(0xFF) - value)?;
         Ok(())
     }
@@ -317,7 +440,7 @@ impl Cpu {
     }
 
     fn execute_asl(&mut self, mut value: Byte) -> Result<Byte, CpuError> {
-        self.flag_carry = value[7];
+        self.status.set_carry(value[7]);
 
         value <<= 1;
 
@@ -337,16 +460,8 @@ impl Cpu {
     }
 
     fn execute_rol(&mut self, value: Byte) -> Result<Byte, CpuError> {
-        let value_arr: [bool; 8] = value.clone().as_array();
-
-        let mut new_value_arr: [bool; 8] = [false; 8];
-        new_value_arr[0] = self.flag_carry;
-        for (i,x) in value_arr[0..7].iter().enumerate() {
-            new_value_arr[i + 1] = *x;
-        }
-
-        self.flag_carry = value_arr[7];
-        let new_value = Byte::from_bool_array(new_value_arr);
+        let (new_value, carry) = value.rotate_left_through(self.status.get_carry());
+        self.status.set_carry(carry);
 
         self.set_negative_flag(new_value);
         self.set_zero_flag(new_value);
@@ -355,18 +470,8 @@ impl Cpu {
     }
 
     fn execute_ror(&mut self, value: Byte) -> Result<Byte, CpuError> {
-        let value_arr: [bool; 8] = value.clone().as_array();
-
-
-        let mut new_value_arr: [bool; 8] = [false; 8];
-        new_value_arr[7] = self.flag_carry;
-        for (i,x) in value_arr[1..8].iter().enumerate() {
-            new_value_arr[i] = *x;
-        }
-
-        
-        self.flag_carry = value_arr[0];
-        let new_value = Byte::from_bool_array(new_value_arr);
+        let (new_value, carry) = value.rotate_right_through(self.status.get_carry());
+        self.status.set_carry(carry);
 
         self.set_negative_flag(new_value);
         self.set_zero_flag(new_value);
@@ -389,15 +494,23 @@ impl Cpu {
     }
 
     fn execute_adc(&mut self, value: Byte) -> Result<(), CpuError> {
+        if self.is_decimal_mode_active() {
+            return self.execute_decimal_adc(value);
+        }
+
+        self.execute_binary_adc(value)
+    }
+
+    fn execute_binary_adc(&mut self, value: Byte) -> Result<(), CpuError> {
         let add_result = self.reg_a.get_value().overflowing_add(value.get_value());
-        let add_result_2 = add_result.0.overflowing_add(self.flag_carry as u8);
+        let add_result_2 = add_result.0.overflowing_add(self.status.get_carry() as u8);
 
         // Taken from : http://www.righto.com/2012/12/the-6502-overflow-flag-explained.html#:~:text=The%20definition%20of%20the%206502,%3E%20127%20or%20%3C%20%2D128.
-        self.flag_overflow = ((self.reg_a.get_value() ^ add_result_2.0) & (value.get_value() ^ add_result_2.0) & 0x80) != 0;
+        self.status.set_overflow(((self.reg_a.get_value() ^ add_result_2.0) & (value.get_value() ^ add_result_2.0) & 0x80) != 0);
         
         
         self.reg_a = Byte::new(add_result_2.0);
-        self.flag_carry = add_result.1 | add_result_2.1;
+        self.status.set_carry(add_result.1 | add_result_2.1);
         
         self.set_negative_flag(self.reg_a);
         self.set_zero_flag(self.reg_a);
@@ -405,6 +518,75 @@ impl Cpu {
         Ok(())
     }
 
+    // The 2A03 stores the decimal flag but always does binary math
+    fn is_decimal_mode_active(&self) -> bool {
+        self.status.get_decimal_mode() && self.variant != CpuVariant::Ricoh2A03
+    }
+
+    // NMOS quirks : Z comes from the binary sum, N and V from the result before the high nibble is adjusted
+    // Taken from : http://www.6502.org/tutorials/decimal_mode.html#A
+    fn execute_decimal_adc(&mut self, value: Byte) -> Result<(), CpuError> {
+        let reg_a = self.reg_a.get_value() as u16;
+        let value = value.get_value() as u16;
+        let carry = self.status.get_carry() as u16;
+
+        let mut low = (reg_a & 0x0F) + (value & 0x0F) + carry;
+        if low > 0x09 {
+            low += 0x06;
+        }
+
+        let mut high = (reg_a >> 4) + (value >> 4) + (low > 0x0F) as u16;
+        let unadjusted = (((high << 4) | (low & 0x0F)) & 0xFF) as u8;
+
+        self.status.set_zero((reg_a + value + carry) & 0xFF == 0);
+        self.status.set_negative(unadjusted & 0x80 != 0);
+        self.status.set_overflow(((reg_a as u8 ^ unadjusted) & !(reg_a as u8 ^ value as u8) & 0x80) != 0);
+
+        if high > 0x09 {
+            high += 0x06;
+        }
+
+        self.status.set_carry(high > 0x0F);
+        self.reg_a = Byte::new((((high << 4) | (low & 0x0F)) & 0xFF) as u8);
+        self.fix_cmos_decimal_flags();
+
+        Ok(())
+    }
+
+    // On NMOS every flag comes from the binary subtraction, only the accumulator is decimal adjusted
+    fn execute_decimal_sbc(&mut self, value: Byte) -> Result<(), CpuError> {
+        let reg_a = self.reg_a.get_value() as i16;
+        let subtrahend = value.get_value() as i16;
+        let borrow = !self.status.get_carry() as i16;
+
+        let mut low = (reg_a & 0x0F) - (subtrahend & 0x0F) - borrow;
+        let mut high = (reg_a >> 4) - (subtrahend >> 4);
+        if low < 0 {
+            low -= 0x06;
+            high -= 1;
+        }
+
+        if high < 0 {
+            high -= 0x06;
+        }
+
+        self.execute_binary_adc(Byte::new(0xFF) - value)?;
+
+        self.reg_a = Byte::new((((high << 4) | (low & 0x0F)) & 0xFF) as u8);
+        self.fix_cmos_decimal_flags();
+
+        Ok(())
+    }
+
+    // The 65C02 spends an extra cycle in decimal mode to set N and Z from the adjusted result
+    fn fix_cmos_decimal_flags(&mut self) {
+        if self.variant == CpuVariant::Cmos65C02 {
+            self.set_negative_flag(self.reg_a);
+            self.set_zero_flag(self.reg_a);
+            self.cycle_counter += 1;
+        }
+    }
+
     fn execute_branch(&mut self, flag: bool, offset: i8) -> Result<(), CpuError> {
         if flag {
             self.cycle_counter += 1;
@@ -420,50 +602,217 @@ impl Cpu {
         Ok(())
     }
 
-    fn log_instruction(&self) {
-        let target_instruction = self.get_memory_addr(self.program_counter);
-        let instruction: Instruction = self.instruction_set.get(&target_instruction.get_value())
-            .unwrap_or(&get_unknown_instruction()).clone();
-        
-        
-        let mut instruction_args = Vec::<String>::new();
-        for x in self.program_counter.get_value()..self.program_counter.get_value() + instruction.bytes as u16 {
-            instruction_args.push(format!("{:02X}", self.get_memory_addr(Double::from(x)).get_value()));
-        }
-
-        let instruction_args_string = format!("{:width$}", instruction_args.join(" "), width=12);
+    pub fn disassemble_at(&self, addr: u16) -> DisassembledInstruction {
+        disasm::disassemble_instruction(&self.instruction_set, addr, |addr| self.peek_memory_addr(Double::from(addr)).get_value())
+    }
 
-        log::trace!("{:X} -> {} {} | A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}", self.program_counter.get_value(), 
-            format!("{:width$}", instruction.name, width=3), instruction_args_string,self.reg_a.get_value(), 
-            self.reg_x.get_value(), self.reg_y.get_value(), self.get_processor_status_byte().get_value(), 
-            self.stack_pointer.get_value(), self.cycle_counter);
+    // The address the instruction would access with the current registers
+    pub fn get_effective_addr(&self, instruction: &DisassembledInstruction) -> Option<u16> {
+        disasm::disassembler::get_effective_addr(instruction, self.variant, self.reg_x.get_value(), self.reg_y.get_value(),
+            |addr| self.peek_memory_addr(Double::from(addr)).get_value())
     }
 
     fn increment_cycle(&mut self, opcode: Byte) {
-        let instruction: Instruction = self.instruction_set.get(&opcode.get_value())
-            .unwrap_or(&get_unknown_instruction()).clone();
+        self.cycle_counter += self.cycle_table[opcode.get_value() as usize] as usize;
+    }
 
-        self.cycle_counter += instruction.cycles as usize;
+    // Opcodes the 65C02 added or changed, returns false for the ones that behave like on the NMOS 6502
+    fn execute_cmos_instruction(&mut self, opcode: Byte) -> std::result::Result<bool, CpuError> {
+        match opcode.get_value() {
+            0x80 => { //BRA
+                let offset = self.get_relative_addr();
+                self.program_counter += 2;
+                self.execute_branch(true, offset)?;
+            },
+            0x6C => { //JMP - Indirect, without the page wrap bug
+                let pointer_addr = self.get_absolute_addr();
+                self.program_counter = Double::new_from_significant(self.get_memory_addr(pointer_addr),
+                    self.get_memory_addr(Double::from(pointer_addr.get_value().wrapping_add(1))));
+            },
+            0x7C => { //JMP - (Absolute, X)
+                let pointer_addr = Double::from(self.get_absolute_addr().get_value().wrapping_add(self.reg_x.get_value() as u16));
+                self.program_counter = Double::new_from_significant(self.get_memory_addr(pointer_addr),
+                    self.get_memory_addr(Double::from(pointer_addr.get_value().wrapping_add(1))));
+            },
+            0xDA => { //PHX
+                self.push_stack(self.reg_x)?;
+                self.program_counter += 1;
+            },
+            0x5A => { //PHY
+                self.push_stack(self.reg_y)?;
+                self.program_counter += 1;
+            },
+            0xFA => { //PLX
+                self.reg_x = self.pop_stack()?;
+
+                self.set_zero_flag(self.reg_x);
+                self.set_negative_flag(self.reg_x);
+
+                self.program_counter += 1;
+            },
+            0x7A => { //PLY
+                self.reg_y = self.pop_stack()?;
+
+                self.set_zero_flag(self.reg_y);
+                self.set_negative_flag(self.reg_y);
+
+                self.program_counter += 1;
+            },
+            0x64 => { //STZ - Zero Page
+                let memory_addr = self.get_zero_page_addr();
+
+                self.set_memory_addr(memory_addr.into(), Byte::new(0x00));
+                self.program_counter += 2;
+            },
+            0x74 => { //STZ - Zero Page, X
+                let memory_addr = self.get_zero_page_x_addr();
+
+                self.set_memory_addr(memory_addr.into(), Byte::new(0x00));
+                self.program_counter += 2;
+            },
+            0x9C => { //STZ - Absolute
+                let memory_addr = self.get_absolute_addr();
+
+                self.set_memory_addr(memory_addr, Byte::new(0x00));
+                self.program_counter += 3;
+            },
+            0x9E => { //STZ - Absolute, X (no page cross penalty for stores)
+                let memory_addr = Double::from(self.get_absolute_addr().get_value().wrapping_add(self.reg_x.get_value() as u16));
+
+                self.set_memory_addr(memory_addr, Byte::new(0x00));
+                self.program_counter += 3;
+            },
+            0x04 | 0x0C | 0x14 | 0x1C => { //TSB, TRB
+                let (memory_addr, instruction_size) = match opcode.get_value() {
+                    0x04 | 0x14 => (self.get_zero_page_addr().into(), 2),
+                    _ => (self.get_absolute_addr(), 3),
+                };
+                let value = self.get_memory_addr(memory_addr);
+
+                self.set_zero_flag(value & self.reg_a);
+                if opcode.get_value() & 0x10 == 0 {
+                    self.set_memory_addr(memory_addr, value | self.reg_a);
+                } else {
+                    self.set_memory_addr(memory_addr, value & Byte::new(!self.reg_a.get_value()));
+                }
+
+                self.program_counter += instruction_size;
+            },
+            0x89 => { //BIT - Immediate, only the zero flag is affected
+                let and_result = self.get_immediate_value() & self.reg_a;
+
+                self.set_zero_flag(and_result);
+                self.program_counter += 2;
+            },
+            0x34 | 0x3C => { //BIT - Zero Page, X and Absolute, X
+                let (memory_addr, instruction_size) = match opcode.get_value() {
+                    0x34 => (self.get_zero_page_x_addr().into(), 2),
+                    _ => (self.get_absolute_addr_x(), 3),
+                };
+                let mask_pattern = self.get_memory_addr(memory_addr);
+
+                self.set_zero_flag(mask_pattern & self.reg_a);
+                self.status.set_overflow(mask_pattern[6]);
+                self.status.set_negative(mask_pattern[7]);
+
+                self.program_counter += instruction_size;
+            },
+            0x1A => { //INC - Accumulator
+                self.reg_a += Byte::new(1);
+
+                self.set_zero_flag(self.reg_a);
+                self.set_negative_flag(self.reg_a);
+
+                self.program_counter += 1;
+            },
+            0x3A => { //DEC - Accumulator
+                self.reg_a -= Byte::new(1);
+
+                self.set_zero_flag(self.reg_a);
+                self.set_negative_flag(self.reg_a);
+
+                self.program_counter += 1;
+            },
+            0x92 => { //STA - (Indirect)
+                let memory_addr = self.get_zero_page_indirect_addr();
+
+                self.set_memory_addr(memory_addr, self.reg_a);
+                self.program_counter += 2;
+            },
+            0x12 | 0x32 | 0x52 | 0x72 | 0xB2 | 0xD2 | 0xF2 => { //ORA, AND, EOR, ADC, LDA, CMP, SBC - (Indirect)
+                let value = self.get_memory_addr(self.get_zero_page_indirect_addr());
+
+                match opcode.get_value() {
+                    0x12 => self.execute_ora(value)?,
+                    0x32 => self.reg_a &= value,
+                    0x52 => self.reg_a ^= value,
+                    0x72 => self.execute_adc(value)?,
+                    0xB2 => self.reg_a = value,
+                    0xD2 => {
+                        self.status.set_carry(self.reg_a >= value);
+                        self.set_zero_flag(self.reg_a - value);
+                        self.set_negative_flag(self.reg_a - value);
+                    },
+                    _ => self.execute_sbc(value)?,
+                }
+
+                if let 0x32 | 0x52 | 0xB2 = opcode.get_value() {
+                    self.set_zero_flag(self.reg_a);
+                    self.set_negative_flag(self.reg_a);
+                }
+
+                self.program_counter += 2;
+            },
+            0xEA => return Ok(false),
+            _ => {
+                // Undefined opcode
+                let instruction_size = match self.instruction_set.get(&opcode.get_value()) {
+                    Some(instruction) if instruction.name == "NOP" => instruction.bytes,
+                    _ => return Ok(false),
+                };
+
+                self.program_counter += instruction_size;
+            }
+        }
+
+        Ok(true)
     }
 
     // Instruction parser
     pub fn execute_instruction(&mut self) -> std::result::Result<(), CpuError> {
         //Precheks and logs
+        let start_cycle = self.cycle_counter;
         let opcode = self.get_memory_addr(self.program_counter);
         self.current_opcode = opcode;
 
-        self.log_instruction();
+        // The tracer is taken out for the call so it can read the whole cpu
+        if let Some(mut tracer) = self.tracer.take() {
+            tracer.trace(self);
+            self.tracer = Some(tracer);
+        }
  
         // Executing instruction
         self.increment_cycle(opcode);
 
+        if self.variant == CpuVariant::Cmos65C02 && self.execute_cmos_instruction(opcode)? {
+            self.bus.tick(self.cycle_counter - start_cycle);
+            return Ok(());
+        }
+
         match opcode.get_value() {
             0x00 => { //BRK
-                // TODO : Set flags accordingly
-                self.program_counter += 1;
+                // The byte after BRK is skipped, RTI returns past it
+                let return_addr = self.program_counter + 2;
+                self.push_stack(return_addr.get_most_significant())?;
+                self.push_stack(return_addr.get_least_significant())?;
+                self.push_stack(self.status.get_pushed_byte(true))?;
+
+                self.status.set_interrupt_disable(true);
+                if self.variant == CpuVariant::Cmos65C02 {
+                    self.status.set_decimal_mode(false);
+                }
 
-                log::info!("Break opcode");
-                return Err(CpuError::BreakError);
+                self.program_counter = get_vector(&self.bus, consts::IRQ_VECTOR_ADDR);
             },
             0xAA => { //TAX
                 self.reg_x = self.reg_a.clone();
@@ -494,15 +843,15 @@ impl Cpu {
                 self.program_counter += 1;
             },
             0x78 => { //SEI
-                self.flag_interrupt_disable = true;
+                self.status.set_interrupt_disable(true);
                 self.program_counter += 1;
             },
             0xF8 => { //SED
-                self.flag_decimal_mode = true;
+                self.status.set_decimal_mode(true);
                 self.program_counter += 1;
             },
             0x38 => { //SEC
-                self.flag_carry = true;
+                self.status.set_carry(true);
                 self.program_counter += 1;
             },
             0xEA => { //NOP
@@ -619,19 +968,19 @@ impl Cpu {
                 self.program_counter += 2;
             },
             0x18 => { //CLS
-                self.flag_carry = false;
+                self.status.set_carry(false);
                 self.program_counter += 1;
             },
             0xD8 => { //CLD
-                self.flag_decimal_mode = false;
+                self.status.set_decimal_mode(false);
                 self.program_counter += 1;
             },
             0x58 => { //CLI
-                self.flag_interrupt_disable = false;
+                self.status.set_interrupt_disable(false);
                 self.program_counter += 1;
             },
             0xB8 => { //CLV
-                self.flag_overflow = false;
+                self.status.set_overflow(false);
                 self.program_counter += 1;
             },
             0xC6 => { //DEC - Zero page
@@ -862,7 +1211,7 @@ impl Cpu {
                 self.program_counter += 3;
             },
             0x4A => { //LSR - Accumulator
-                self.flag_carry = self.reg_a[0];
+                self.status.set_carry(self.reg_a[0]);
 
                 self.reg_a >>= 1;
 
@@ -873,7 +1222,7 @@ impl Cpu {
             },
             0x46 => { //LSR - Zero page
                 let target_memory_addr: Double = self.get_zero_page_addr().into();
-                self.flag_carry = self.get_memory_addr(target_memory_addr)[0];
+                self.status.set_carry(self.get_memory_addr(target_memory_addr)[0]);
 
                 self.set_memory_addr(target_memory_addr, self.get_memory_addr(target_memory_addr) >> 1);
 
@@ -884,7 +1233,7 @@ impl Cpu {
             },
             0x56 => { //LSR - Zero page, X
                 let target_memory_addr: Double = self.get_zero_page_x_addr().into();
-                self.flag_carry = self.get_memory_addr(target_memory_addr)[0];
+                self.status.set_carry(self.get_memory_addr(target_memory_addr)[0]);
 
                 self.set_memory_addr(target_memory_addr, self.get_memory_addr(target_memory_addr) >> 1);
 
@@ -895,7 +1244,7 @@ impl Cpu {
             },
             0x4E => { //LSR - Absolute
                 let target_memory_addr: Double = self.get_absolute_addr();
-                self.flag_carry = self.get_memory_addr(target_memory_addr)[0];
+                self.status.set_carry(self.get_memory_addr(target_memory_addr)[0]);
 
                 self.set_memory_addr(target_memory_addr, self.get_memory_addr(target_memory_addr) >> 1);
 
@@ -906,7 +1255,7 @@ impl Cpu {
             },
             0x5E => { //LSR - Absolute, X
                 let target_memory_addr: Double = self.get_absolute_addr_x();
-                self.flag_carry = self.get_memory_addr(target_memory_addr)[0];
+                self.status.set_carry(self.get_memory_addr(target_memory_addr)[0]);
 
                 self.set_memory_addr(target_memory_addr, self.get_memory_addr(target_memory_addr) >> 1);
 
@@ -1047,9 +1396,9 @@ impl Cpu {
                 let value = self.get_immediate_value();
                 let result = self.reg_x - value;
                 
-                self.flag_zero = self.reg_x == value;
-                self.flag_carry = self.reg_x >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_x == value);
+                self.status.set_carry(self.reg_x >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1057,9 +1406,9 @@ impl Cpu {
                 let value = self.get_memory_addr(self.get_zero_page_addr().into());
                 let result = self.reg_x - value;
                 
-                self.flag_zero = self.reg_x == value;
-                self.flag_carry = self.reg_x >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_x == value);
+                self.status.set_carry(self.reg_x >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1067,9 +1416,9 @@ impl Cpu {
                 let value = self.get_memory_addr(self.get_absolute_addr());
                 let result = self.reg_x - value;
                 
-                self.flag_zero = self.reg_x == value;
-                self.flag_carry = self.reg_x >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_x == value);
+                self.status.set_carry(self.reg_x >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 3;
             },
@@ -1077,9 +1426,9 @@ impl Cpu {
                 let value = self.get_immediate_value();
                 let result = self.reg_y - value;
                 
-                self.flag_zero = self.reg_y == value;
-                self.flag_carry = self.reg_y >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_y == value);
+                self.status.set_carry(self.reg_y >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1087,9 +1436,9 @@ impl Cpu {
                 let value = self.get_memory_addr(self.get_zero_page_addr().into());
                 let result = self.reg_y - value;
                 
-                self.flag_zero = self.reg_y == value;
-                self.flag_carry = self.reg_y >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_y == value);
+                self.status.set_carry(self.reg_y >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1097,59 +1446,59 @@ impl Cpu {
                 let value = self.get_memory_addr(self.get_absolute_addr());
                 let result = self.reg_y - value;
                 
-                self.flag_zero = self.reg_y == value;
-                self.flag_carry = self.reg_y >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_y == value);
+                self.status.set_carry(self.reg_y >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 3;
             },
             0xB0 => { //BCS
                 let offset = self.get_relative_addr();
                 self.program_counter += 2;
-                self.execute_branch(self.flag_carry, offset)?;
+                self.execute_branch(self.status.get_carry(), offset)?;
             },
             0x90 => { //BCC
                 let offset = self.get_relative_addr();
                 self.program_counter += 2;
-                self.execute_branch(!self.flag_carry, offset)?;
+                self.execute_branch(!self.status.get_carry(), offset)?;
             },
             0xF0 => { //BEQ
                 let offset = self.get_relative_addr();
                 self.program_counter += 2;
-                self.execute_branch(self.flag_zero, offset)?;
+                self.execute_branch(self.status.get_zero(), offset)?;
             },
             0xD0 => { //BNE
                 let offset = self.get_relative_addr();
                 self.program_counter += 2;
-                self.execute_branch(!self.flag_zero, offset)?;
+                self.execute_branch(!self.status.get_zero(), offset)?;
             },
             0x30 => { //BMI
                 let offset = self.get_relative_addr();
                 self.program_counter += 2;
-                self.execute_branch(self.flag_negative, offset)?;
+                self.execute_branch(self.status.get_negative(), offset)?;
             },
             0x10 => { //BPL
                 let offset = self.get_relative_addr();
                 self.program_counter += 2;
-                self.execute_branch(!self.flag_negative, offset)?;
+                self.execute_branch(!self.status.get_negative(), offset)?;
             },
             0x70 => { //BVS
                 let offset = self.get_relative_addr();
                 self.program_counter += 2;
-                self.execute_branch(self.flag_overflow, offset)?;
+                self.execute_branch(self.status.get_overflow(), offset)?;
             },
             0x50 => { //BVC
                 let offset = self.get_relative_addr();
                 self.program_counter += 2;
-                self.execute_branch(!self.flag_overflow, offset)?;
+                self.execute_branch(!self.status.get_overflow(), offset)?;
             },
             0xC9 => { //CMP - Immediate
                 let value = self.get_immediate_value();
                 let result = self.reg_a - value;
                 
-                self.flag_zero = self.reg_a == value;
-                self.flag_carry = self.reg_a >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == value);
+                self.status.set_carry(self.reg_a >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1157,9 +1506,9 @@ impl Cpu {
                 let value = self.get_memory_addr(self.get_zero_page_addr().into());
                 let result = self.reg_a - value;
                 
-                self.flag_zero = self.reg_a == value;
-                self.flag_carry = self.reg_a >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == value);
+                self.status.set_carry(self.reg_a >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1167,9 +1516,9 @@ impl Cpu {
                 let value = self.get_memory_addr(self.get_zero_page_x_addr().into());
                 let result = self.reg_a - value;
                 
-                self.flag_zero = self.reg_a == value;
-                self.flag_carry = self.reg_a >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == value);
+                self.status.set_carry(self.reg_a >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1177,9 +1526,9 @@ impl Cpu {
                 let value = self.get_memory_addr(self.get_absolute_addr());
                 let result = self.reg_a - value;
                 
-                self.flag_zero = self.reg_a == value;
-                self.flag_carry = self.reg_a >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == value);
+                self.status.set_carry(self.reg_a >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 3;
             },
@@ -1188,9 +1537,9 @@ impl Cpu {
                 let value = self.get_memory_addr(memory_addr);
                 let result = self.reg_a - value;
                 
-                self.flag_zero = self.reg_a == value;
-                self.flag_carry = self.reg_a >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == value);
+                self.status.set_carry(self.reg_a >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 3;
             },
@@ -1199,9 +1548,9 @@ impl Cpu {
                 let value = self.get_memory_addr(memory_addr);
                 let result = self.reg_a - value;
                 
-                self.flag_zero = self.reg_a == value;
-                self.flag_carry = self.reg_a >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == value);
+                self.status.set_carry(self.reg_a >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 3;
             },
@@ -1209,9 +1558,9 @@ impl Cpu {
                 let value = self.get_memory_addr(self.get_indexed_indirect_x_addr());
                 let result = self.reg_a - value;
                 
-                self.flag_zero = self.reg_a == value;
-                self.flag_carry = self.reg_a >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == value);
+                self.status.set_carry(self.reg_a >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1220,9 +1569,9 @@ impl Cpu {
                 let value = self.get_memory_addr(target_addr);
                 let result = self.reg_a - value;
                 
-                self.flag_zero = self.reg_a == value;
-                self.flag_carry = self.reg_a >= value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == value);
+                self.status.set_carry(self.reg_a >= value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1511,8 +1860,8 @@ impl Cpu {
                 let and_result = mask_pattern & self.reg_a;
 
                 self.set_zero_flag(and_result);
-                self.flag_overflow = mask_pattern[6];
-                self.flag_negative = mask_pattern[7];
+                self.status.set_overflow(mask_pattern[6]);
+                self.status.set_negative(mask_pattern[7]);
 
                 self.program_counter += 2;
             },
@@ -1521,38 +1870,19 @@ impl Cpu {
                 let and_result = mask_pattern & self.reg_a;
 
                 self.set_zero_flag(and_result);
-                self.flag_overflow = mask_pattern[6];
-                self.flag_negative = mask_pattern[7];
+                self.status.set_overflow(mask_pattern[6]);
+                self.status.set_negative(mask_pattern[7]);
 
                 self.program_counter += 3;
             },
             0x08 => { //PHP
-                let mut new_byte_arr: [bool; 8] = [false; 8];
-
-                new_byte_arr[0] = self.flag_carry;
-                new_byte_arr[1] = self.flag_zero;
-                new_byte_arr[2] = self.flag_interrupt_disable;
-                new_byte_arr[3] = self.flag_decimal_mode;
-                new_byte_arr[4] = true; // Further explanation : https://stackoverflow.com/questions/52017657/6502-emulator-testing-nestest
-                new_byte_arr[5] = true;
-                new_byte_arr[6] = self.flag_overflow;
-                new_byte_arr[7] = self.flag_negative;
-
-                self.push_stack(Byte::from_bool_array(new_byte_arr))?;
+                // The break bit is always set in the pushed copy, further explanation : https://stackoverflow.com/questions/52017657/6502-emulator-testing-nestest
+                self.push_stack(self.status.get_pushed_byte(true))?;
                 
                 self.program_counter += 1;
             },
             0x28 => { //PLP
-                let cpu_flags = self.pop_stack()?;
-
-                self.flag_carry = cpu_flags[0];
-                self.flag_zero = cpu_flags[1];
-                self.flag_interrupt_disable = cpu_flags[2];
-                self.flag_decimal_mode = cpu_flags[3];
-                self.flag_break = false;
-                // false = cpu_flags[5];
-                self.flag_overflow = cpu_flags[6];
-                self.flag_negative = cpu_flags[7];
+                self.status = StatusRegister::from(self.pop_stack()?);
 
                 self.program_counter += 1;
             },
@@ -1619,16 +1949,7 @@ impl Cpu {
             },
             0x40 => { //RTI
                 // Pull CPU Flags
-                let cpu_flags = self.pop_stack()?;
-
-                self.flag_carry = cpu_flags[0];
-                self.flag_zero = cpu_flags[1];
-                self.flag_interrupt_disable = cpu_flags[2];
-                self.flag_decimal_mode = cpu_flags[3];
-                self.flag_break = cpu_flags[4];
-                // false = cpu_flags[5];
-                self.flag_overflow = cpu_flags[6];
-                self.flag_negative = cpu_flags[7];
+                self.status = StatusRegister::from(self.pop_stack()?);
 
                 // Pull PC from stack
                 let least_significant = self.pop_stack()?;
@@ -1849,9 +2170,9 @@ impl Cpu {
 
                 let result = self.reg_a - new_value;
                 
-                self.flag_zero = self.reg_a == new_value;
-                self.flag_carry = self.reg_a >= new_value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == new_value);
+                self.status.set_carry(self.reg_a >= new_value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1866,9 +2187,9 @@ impl Cpu {
 
                 let result = self.reg_a - new_value;
                 
-                self.flag_zero = self.reg_a == new_value;
-                self.flag_carry = self.reg_a >= new_value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == new_value);
+                self.status.set_carry(self.reg_a >= new_value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1883,9 +2204,9 @@ impl Cpu {
 
                 let result = self.reg_a - new_value;
                 
-                self.flag_zero = self.reg_a == new_value;
-                self.flag_carry = self.reg_a >= new_value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == new_value);
+                self.status.set_carry(self.reg_a >= new_value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 3;
             },
@@ -1900,9 +2221,9 @@ impl Cpu {
 
                 let result = self.reg_a - new_value;
                 
-                self.flag_zero = self.reg_a == new_value;
-                self.flag_carry = self.reg_a >= new_value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == new_value);
+                self.status.set_carry(self.reg_a >= new_value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1917,9 +2238,9 @@ impl Cpu {
 
                 let result = self.reg_a - new_value;
                 
-                self.flag_zero = self.reg_a == new_value;
-                self.flag_carry = self.reg_a >= new_value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == new_value);
+                self.status.set_carry(self.reg_a >= new_value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 2;
             },
@@ -1934,9 +2255,9 @@ impl Cpu {
 
                 let result = self.reg_a - new_value;
                 
-                self.flag_zero = self.reg_a == new_value;
-                self.flag_carry = self.reg_a >= new_value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == new_value);
+                self.status.set_carry(self.reg_a >= new_value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 3;
             },
@@ -1951,16 +2272,16 @@ impl Cpu {
 
                 let result = self.reg_a - new_value;
                 
-                self.flag_zero = self.reg_a == new_value;
-                self.flag_carry = self.reg_a >= new_value;
-                self.flag_negative = result[7];
+                self.status.set_zero(self.reg_a == new_value);
+                self.status.set_carry(self.reg_a >= new_value);
+                self.status.set_negative(result[7]);
 
                 self.program_counter += 3;
             },
             0x43 => { //UNOFFICIAL-SRE-Indirect,X
                 //LSR
                 let target_memory_addr = Double::from(self.get_indexed_indirect_x_addr());
-                self.flag_carry = self.get_memory_addr(target_memory_addr)[0];
+                self.status.set_carry(self.get_memory_addr(target_memory_addr)[0]);
 
                 self.set_memory_addr(target_memory_addr, self.get_memory_addr(target_memory_addr) >> 1);
 
@@ -1979,7 +2300,7 @@ impl Cpu {
             0x47 => { //UNOFFICIAL-SRE-ZeroPage
                 //LSR
                 let target_memory_addr = Double::from(self.get_zero_page_addr());
-                self.flag_carry = self.get_memory_addr(target_memory_addr)[0];
+                self.status.set_carry(self.get_memory_addr(target_memory_addr)[0]);
 
                 self.set_memory_addr(target_memory_addr, self.get_memory_addr(target_memory_addr) >> 1);
 
@@ -1998,7 +2319,7 @@ impl Cpu {
             0x4F => { //UNOFFICIAL-SRE-Absolute
                 //LSR
                 let target_memory_addr = self.get_absolute_addr();
-                self.flag_carry = self.get_memory_addr(target_memory_addr)[0];
+                self.status.set_carry(self.get_memory_addr(target_memory_addr)[0]);
 
                 self.set_memory_addr(target_memory_addr, self.get_memory_addr(target_memory_addr) >> 1);
 
@@ -2017,7 +2338,7 @@ impl Cpu {
             0x53 => { //UNOFFICIAL-SRE-Indirect,Y
                 //LSR
                 let target_memory_addr = Double::from(self.get_indirect_indexed_y_addr());
-                self.flag_carry = self.get_memory_addr(target_memory_addr)[0];
+                self.status.set_carry(self.get_memory_addr(target_memory_addr)[0]);
 
                 self.set_memory_addr(target_memory_addr, self.get_memory_addr(target_memory_addr) >> 1);
 
@@ -2036,7 +2357,7 @@ impl Cpu {
             0x57 => { //UNOFFICIAL-SRE-ZeroPage,X
                 //LSR
                 let target_memory_addr = Double::from(self.get_zero_page_x_addr());
-                self.flag_carry = self.get_memory_addr(target_memory_addr)[0];
+                self.status.set_carry(self.get_memory_addr(target_memory_addr)[0]);
 
                 self.set_memory_addr(target_memory_addr, self.get_memory_addr(target_memory_addr) >> 1);
 
@@ -2055,7 +2376,7 @@ impl Cpu {
             0x5B => { //UNOFFICIAL-SRE-Absolute,Y
                 //LSR
                 let target_memory_addr = self.get_absolute_addr_y();
-                self.flag_carry = self.get_memory_addr(target_memory_addr)[0];
+                self.status.set_carry(self.get_memory_addr(target_memory_addr)[0]);
 
                 self.set_memory_addr(target_memory_addr, self.get_memory_addr(target_memory_addr) >> 1);
 
@@ -2074,7 +2395,7 @@ impl Cpu {
             0x5F => { //UNOFFICIAL-SRE-Absolute,X
                 //LSR
                 let target_memory_addr = self.get_absolute_addr_x();
-                self.flag_carry = self.get_memory_addr(target_memory_addr)[0];
+                self.status.set_carry(self.get_memory_addr(target_memory_addr)[0]);
 
                 self.set_memory_addr(target_memory_addr, self.get_memory_addr(target_memory_addr) >> 1);
 
@@ -2344,6 +2665,167 @@ impl Cpu {
             }
         }
 
+        self.bus.tick(self.cycle_counter - start_cycle);
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+fn get_test_cpu(variant: CpuVariant, program: &[u8]) -> Cpu<crate::mapper::DebugMapper> {
+    let mut mapper = crate::mapper::DebugMapper::new();
+    mapper.load(0x0200, program);
+
+    Cpu::new_with_variant(mapper, Double::from(0x0200u16), variant)
+}
+
+#[test]
+fn decimal_mode_adc() {
+    // SED ; CLC ; LDA #$58 ; ADC #$46 ; ADC #$01
+    let program = [0xF8, 0x18, 0xA9, 0x58, 0x69, 0x46, 0x69, 0x01];
+    let mut cpu = get_test_cpu(CpuVariant::Nmos6502, &program);
+    for _ in 0..4 {
+        cpu.execute_instruction().unwrap();
+    }
+
+    assert_eq!(cpu.get_reg_a(), Byte::new(0x04));
+    assert!(cpu.status.get_carry());
+
+    cpu.execute_instruction().unwrap();
+    assert_eq!(cpu.get_reg_a(), Byte::new(0x06));
+    assert!(!cpu.status.get_carry());
+
+    // 99 + 01 wraps to 00 but Z and N come from the binary sum and the unadjusted result
+    let mut cpu = get_test_cpu(CpuVariant::Nmos6502, &[0xF8, 0x18, 0xA9, 0x99, 0x69, 0x01]);
+    for _ in 0..4 {
+        cpu.execute_instruction().unwrap();
+    }
+
+    assert_eq!(cpu.get_reg_a(), Byte::new(0x00));
+    assert!(cpu.status.get_carry());
+    assert!(!cpu.status.get_zero());
+    assert!(cpu.status.get_negative());
+}
+
+#[test]
+fn decimal_mode_sbc() {
+    // SED ; SEC ; LDA #$40 ; SBC #$13 ; SBC #$30
+    let program = [0xF8, 0x38, 0xA9, 0x40, 0xE9, 0x13, 0xE9, 0x30];
+    let mut cpu = get_test_cpu(CpuVariant::Nmos6502, &program);
+    for _ in 0..4 {
+        cpu.execute_instruction().unwrap();
+    }
+
+    assert_eq!(cpu.get_reg_a(), Byte::new(0x27));
+    assert!(cpu.status.get_carry());
+
+    cpu.execute_instruction().unwrap();
+    assert_eq!(cpu.get_reg_a(), Byte::new(0x97));
+    assert!(!cpu.status.get_carry());
+    assert!(cpu.status.get_negative());
+}
+
+#[test]
+fn decimal_mode_ignored_on_2a03() {
+    // SED ; CLC ; LDA #$58 ; ADC #$46
+    let mut cpu = get_test_cpu(CpuVariant::Ricoh2A03, &[0xF8, 0x18, 0xA9, 0x58, 0x69, 0x46]);
+    for _ in 0..4 {
+        cpu.execute_instruction().unwrap();
+    }
+
+    assert_eq!(cpu.get_reg_a(), Byte::new(0x9E));
+    assert!(cpu.status.get_decimal_mode());
+}
+
+#[test]
+fn brk_and_rti() {
+    for variant in [CpuVariant::Nmos6502, CpuVariant::Cmos65C02] {
+        // SED ; BRK with its skipped byte ; NOP, the handler at $0300 is a lone RTI
+        let mut cpu = get_test_cpu(variant, &[0xF8, 0x00, 0xFF, 0xEA]);
+        cpu.set_memory_addr(Double::from(0x0300u16), Byte::new(0x40));
+        cpu.set_memory_addr(Double::from(consts::IRQ_VECTOR_ADDR), Byte::new(0x00));
+        cpu.set_memory_addr(Double::from(consts::IRQ_VECTOR_ADDR + 1), Byte::new(0x03));
+
+        cpu.execute_instruction().unwrap();
+        cpu.execute_instruction().unwrap();
+        assert_eq!(cpu.get_program_counter(), Double::from(0x0300u16));
+        assert_eq!(cpu.get_stack_pointer(), Byte::new(0xFA));
+        assert_eq!(cpu.get_cycle_counter(), 16);
+        assert_eq!(cpu.get_memory_addr(Double::from(0x01FDu16)), Byte::new(0x02));
+        assert_eq!(cpu.get_memory_addr(Double::from(0x01FCu16)), Byte::new(0x03));
+        // Pushed with B set
+        assert_eq!(cpu.get_memory_addr(Double::from(0x01FBu16)), Byte::new(0x3C));
+        assert!(cpu.status.get_interrupt_disable());
+        // Only the 65C02 clears the decimal flag
+        assert_eq!(cpu.status.get_decimal_mode(), variant == CpuVariant::Nmos6502);
+
+        cpu.execute_instruction().unwrap();
+        assert_eq!(cpu.get_program_counter(), Double::from(0x0203u16));
+        assert_eq!(cpu.get_stack_pointer(), Byte::new(0xFD));
+        assert!(cpu.status.get_decimal_mode());
+    }
+}
+
+#[test]
+fn cmos_instructions() {
+    let program = [
+        0xA9, 0x0F,       // LDA #$0F
+        0x85, 0x10,       // STA $10
+        0x64, 0x10,       // STZ $10
+        0xA9, 0x30,       // LDA #$30
+        0x85, 0x11,       // STA $11
+        0xA9, 0x03,       // LDA #$03
+        0x85, 0x12,       // STA $12
+        0xA9, 0x05,       // LDA #$05
+        0x04, 0x11,       // TSB $11
+        0x14, 0x12,       // TRB $12
+        0x1A,             // INC A
+        0xA2, 0x07,       // LDX #$07
+        0xDA,             // PHX
+        0x7A,             // PLY
+        0xA2, 0x11,       // LDX #$11
+        0x86, 0x20,       // STX $20
+        0x64, 0x21,       // STZ $21
+        0xB2, 0x20,       // LDA ($20)
+        0x03,             // undefined, 1 byte NOP
+        0xDC, 0x00, 0x00, // undefined, 3 bytes NOP
+        0x80, 0x02,       // BRA +2
+        0xA9, 0xFF,       // LDA #$FF, skipped
+        0x80, 0x03,       // BRA +3
+        0x6C, 0xFF, 0x02, // JMP ($02FF)
+        0x80, 0xFB,       // BRA -5, back to the JMP
+    ];
+
+    let mut cpu = get_test_cpu(CpuVariant::Cmos65C02, &program);
+    cpu.get_bus_mut().load(0x02FF, &[0x00, 0x04]);
+
+    // Counted so a branch going astray can't run forever
+    for _ in 0..24 {
+        cpu.execute_instruction().unwrap();
+    }
+
+    assert_eq!(cpu.get_program_counter(), Double::from(0x0400u16));
+    assert_eq!(cpu.get_memory_addr(0x10u16.into()), Byte::new(0x00));
+    assert_eq!(cpu.get_memory_addr(0x11u16.into()), Byte::new(0x35));
+    assert_eq!(cpu.get_memory_addr(0x12u16.into()), Byte::new(0x02));
+    assert_eq!(cpu.get_reg_y(), Byte::new(0x07));
+    assert_eq!(cpu.get_reg_a(), Byte::new(0x35));
+}
+
+#[test]
+fn cmos_decimal_mode_flags() {
+    // SED ; CLC ; LDA #$99 ; ADC #$01
+    let mut cpu = get_test_cpu(CpuVariant::Cmos65C02, &[0xF8, 0x18, 0xA9, 0x99, 0x69, 0x01]);
+    for _ in 0..3 {
+        cpu.execute_instruction().unwrap();
+    }
+
+    let start_cycle = cpu.get_cycle_counter();
+    cpu.execute_instruction().unwrap();
+
+    assert_eq!(cpu.get_reg_a(), Byte::new(0x00));
+    assert!(cpu.status.get_carry());
+    assert!(cpu.status.get_zero());
+    assert!(!cpu.status.get_negative());
+    assert_eq!(cpu.get_cycle_counter() - start_cycle, 3);
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use crate::core::consts;
+
 #[derive(Clone)]
 pub struct Instruction {
     pub opcode: u8,
@@ -9,8 +11,14 @@ pub struct Instruction {
     pub cycles: u8,
 }
 
-pub fn get_unknown_instruction() -> Instruction {
-    Instruction{opcode:0xFF, name:"Unknown".to_string(), bytes: 1, cycles: 0, mode:"Unknown".to_string()}
+// The cycles of every opcode indexed by the opcode, unknown opcodes take none
+pub fn get_cycle_table(instruction_set: &HashMap<u8, Instruction>) -> [u8; 0x100] {
+    let mut table = [0u8; 0x100];
+    for (opcode, instruction) in instruction_set.iter() {
+        table[*opcode as usize] = instruction.cycles;
+    }
+
+    table
 }
 
 pub fn get_instruction_set() -> HashMap<u8, Instruction> {
@@ -170,12 +178,12 @@ pub fn get_instruction_set() -> HashMap<u8, Instruction> {
 
     // Unofficial
     // Docs : https://wiki.nesdev.com/w/index.php/Programming_with_unofficial_opcodes
-    map.insert(0x1A, Instruction{opcode: 0x1A, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Unofficial".to_string()});
-    map.insert(0x3A, Instruction{opcode: 0x3A, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Unofficial".to_string()});
-    map.insert(0x5A, Instruction{opcode: 0x5A, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Unofficial".to_string()});
-    map.insert(0x7A, Instruction{opcode: 0x7A, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Unofficial".to_string()});
-    map.insert(0xDA, Instruction{opcode: 0xDA, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Unofficial".to_string()});
-    map.insert(0xFA, Instruction{opcode: 0xFA, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Unofficial".to_string()});
+    map.insert(0x1A, Instruction{opcode: 0x1A, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Implied".to_string()});
+    map.insert(0x3A, Instruction{opcode: 0x3A, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Implied".to_string()});
+    map.insert(0x5A, Instruction{opcode: 0x5A, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Implied".to_string()});
+    map.insert(0x7A, Instruction{opcode: 0x7A, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Implied".to_string()});
+    map.insert(0xDA, Instruction{opcode: 0xDA, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Implied".to_string()});
+    map.insert(0xFA, Instruction{opcode: 0xFA, name: "NOP".to_string(), bytes:1, cycles:2, mode:"Implied".to_string()});
     
     map.insert(0x80, Instruction{opcode: 0x80, name: "SKB".to_string(), bytes:2, cycles:2, mode:"Immediate".to_string()});
     map.insert(0x82, Instruction{opcode: 0x82, name: "SKB".to_string(), bytes:2, cycles:2, mode:"Immediate".to_string()});
@@ -235,7 +243,7 @@ pub fn get_instruction_set() -> HashMap<u8, Instruction> {
 
     map.insert(0xE3, Instruction{opcode: 0xE3, name: "ISC".to_string(), bytes:2, cycles:8, mode:"(Indirect,X)".to_string()});
     map.insert(0xE7, Instruction{opcode: 0xE7, name: "ISC".to_string(), bytes:2, cycles:5, mode:"ZeroPage".to_string()});
-    map.insert(0xEF, Instruction{opcode: 0xEE, name: "ISC".to_string(), bytes:3, cycles:6, mode:"Absolute".to_string()});
+    map.insert(0xEF, Instruction{opcode: 0xEF, name: "ISC".to_string(), bytes:3, cycles:6, mode:"Absolute".to_string()});
     map.insert(0xF3, Instruction{opcode: 0xF3, name: "ISC".to_string(), bytes:2, cycles:8, mode:"(Indirect),Y".to_string()});
     map.insert(0xF7, Instruction{opcode: 0xF7, name: "ISC".to_string(), bytes:2, cycles:6, mode:"ZeroPage,X".to_string()});
     map.insert(0xFB, Instruction{opcode: 0xFB, name: "ISC".to_string(), bytes:3, cycles:7, mode:"Absolute,Y".to_string()});
@@ -266,4 +274,60 @@ pub fn get_instruction_set() -> HashMap<u8, Instruction> {
     map.insert(0x7F, Instruction{opcode: 0x7F, name: "RRA".to_string(), bytes:3, cycles:7, mode:"Absolute,X".to_string()});
 
     return map;
-}
\ No newline at end of file
+}
+
+// The 65C02 drops the unofficial opcodes, adds its own and turns every other undefined opcode into a NOP
+pub fn get_cmos_instruction_set() -> HashMap<u8, Instruction> {
+    let mut map = get_instruction_set();
+    map.retain(|opcode, _| !consts::UNOFFICIAL_OPCODES.contains(opcode));
+
+    map.insert(0x80, Instruction{opcode: 0x80, name: "BRA".to_string(), bytes:2, cycles:2, mode:"Relative".to_string()});
+    map.insert(0xDA, Instruction{opcode: 0xDA, name: "PHX".to_string(), bytes:1, cycles:3, mode:"Implied".to_string()});
+    map.insert(0x5A, Instruction{opcode: 0x5A, name: "PHY".to_string(), bytes:1, cycles:3, mode:"Implied".to_string()});
+    map.insert(0xFA, Instruction{opcode: 0xFA, name: "PLX".to_string(), bytes:1, cycles:4, mode:"Implied".to_string()});
+    map.insert(0x7A, Instruction{opcode: 0x7A, name: "PLY".to_string(), bytes:1, cycles:4, mode:"Implied".to_string()});
+    map.insert(0x64, Instruction{opcode: 0x64, name: "STZ".to_string(), bytes:2, cycles:3, mode:"ZeroPage".to_string()});
+    map.insert(0x74, Instruction{opcode: 0x74, name: "STZ".to_string(), bytes:2, cycles:4, mode:"ZeroPage,X".to_string()});
+    map.insert(0x9C, Instruction{opcode: 0x9C, name: "STZ".to_string(), bytes:3, cycles:4, mode:"Absolute".to_string()});
+    map.insert(0x9E, Instruction{opcode: 0x9E, name: "STZ".to_string(), bytes:3, cycles:5, mode:"Absolute,X".to_string()});
+    map.insert(0x04, Instruction{opcode: 0x04, name: "TSB".to_string(), bytes:2, cycles:5, mode:"ZeroPage".to_string()});
+    map.insert(0x0C, Instruction{opcode: 0x0C, name: "TSB".to_string(), bytes:3, cycles:6, mode:"Absolute".to_string()});
+    map.insert(0x14, Instruction{opcode: 0x14, name: "TRB".to_string(), bytes:2, cycles:5, mode:"ZeroPage".to_string()});
+    map.insert(0x1C, Instruction{opcode: 0x1C, name: "TRB".to_string(), bytes:3, cycles:6, mode:"Absolute".to_string()});
+    map.insert(0x89, Instruction{opcode: 0x89, name: "BIT".to_string(), bytes:2, cycles:2, mode:"Immediate".to_string()});
+    map.insert(0x34, Instruction{opcode: 0x34, name: "BIT".to_string(), bytes:2, cycles:4, mode:"ZeroPage,X".to_string()});
+    map.insert(0x3C, Instruction{opcode: 0x3C, name: "BIT".to_string(), bytes:3, cycles:4, mode:"Absolute,X".to_string()});
+    map.insert(0x1A, Instruction{opcode: 0x1A, name: "INC".to_string(), bytes:1, cycles:2, mode:"Accumulator".to_string()});
+    map.insert(0x3A, Instruction{opcode: 0x3A, name: "DEC".to_string(), bytes:1, cycles:2, mode:"Accumulator".to_string()});
+    map.insert(0x6C, Instruction{opcode: 0x6C, name: "JMP".to_string(), bytes:3, cycles:6, mode:"Indirect".to_string()});
+    map.insert(0x7C, Instruction{opcode: 0x7C, name: "JMP".to_string(), bytes:3, cycles:6, mode:"(Absolute,X)".to_string()});
+
+    map.insert(0x12, Instruction{opcode: 0x12, name: "ORA".to_string(), bytes:2, cycles:5, mode:"(Indirect)".to_string()});
+    map.insert(0x32, Instruction{opcode: 0x32, name: "AND".to_string(), bytes:2, cycles:5, mode:"(Indirect)".to_string()});
+    map.insert(0x52, Instruction{opcode: 0x52, name: "EOR".to_string(), bytes:2, cycles:5, mode:"(Indirect)".to_string()});
+    map.insert(0x72, Instruction{opcode: 0x72, name: "ADC".to_string(), bytes:2, cycles:5, mode:"(Indirect)".to_string()});
+    map.insert(0x92, Instruction{opcode: 0x92, name: "STA".to_string(), bytes:2, cycles:5, mode:"(Indirect)".to_string()});
+    map.insert(0xB2, Instruction{opcode: 0xB2, name: "LDA".to_string(), bytes:2, cycles:5, mode:"(Indirect)".to_string()});
+    map.insert(0xD2, Instruction{opcode: 0xD2, name: "CMP".to_string(), bytes:2, cycles:5, mode:"(Indirect)".to_string()});
+    map.insert(0xF2, Instruction{opcode: 0xF2, name: "SBC".to_string(), bytes:2, cycles:5, mode:"(Indirect)".to_string()});
+
+    for opcode in 0x00..=0xFFu8 {
+        map.entry(opcode).or_insert_with(|| get_cmos_nop_instruction(opcode));
+    }
+
+    map
+}
+
+// Undefined 65C02 opcodes are NOPs, most take one byte and one cycle
+fn get_cmos_nop_instruction(opcode: u8) -> Instruction {
+    let (bytes, cycles, mode) = match opcode {
+        0x02 | 0x22 | 0x42 | 0x62 | 0x82 | 0xC2 | 0xE2 => (2, 2, "Immediate"),
+        0x44 => (2, 3, "ZeroPage"),
+        0x54 | 0xD4 | 0xF4 => (2, 4, "ZeroPage,X"),
+        0x5C => (3, 8, "Absolute"),
+        0xDC | 0xFC => (3, 4, "Absolute"),
+        _ => (1, 1, "Implied"),
+    };
+
+    Instruction{opcode, name: "NOP".to_string(), bytes, cycles, mode: mode.to_string()}
+}
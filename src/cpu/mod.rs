@@ -1,14 +1,26 @@
 pub mod instructions;
 pub mod cpu;
+mod bus;
+mod status;
 // mod cpu_tests;
 
 use crate::core::Byte;
 
+pub use bus::CpuBus;
+pub use status::StatusRegister;
+
+// The 6502 flavours the core can emulate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CpuVariant {
+    // The nes cpu, a 6502 with the decimal mode circuitry disconnected
+    Ricoh2A03,
+    // A stock NMOS 6502, ADC and SBC honor the decimal flag
+    Nmos6502,
+    // The CMOS 65C02, with its extra opcodes and the NMOS bugs fixed
+    Cmos65C02,
+}
+
 #[derive(Debug)]
 pub enum CpuError {
-    BreakError,
     UnknownOpcodeError(Byte),
-    StackOverflow,
-    StackEmpty,
-    FailedParsingEntryPoint
 }
\ No newline at end of file
@@ -0,0 +1,150 @@
+use crate::core::Byte;
+use crate::core::consts;
+
+use std::fmt;
+
+// The P register, implemented by the docs at : https://wiki.nesdev.com/w/index.php/Status_flags
+// Only the six real flags are stored, B and the unused bit are added when the register is pushed.
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct StatusRegister(u8);
+
+impl StatusRegister {
+    // The state after power on, only interrupts are disabled
+    pub const fn new() -> StatusRegister {
+        StatusRegister(consts::STATUS_INTERRUPT_DISABLE)
+    }
+
+    #[inline]
+    fn get_flag(&self, mask: u8) -> bool {
+        self.0 & mask != 0
+    }
+
+    #[inline]
+    fn set_flag(&mut self, mask: u8, value: bool) {
+        if value {
+            self.0 |= mask;
+        } else {
+            self.0 &= !mask;
+        }
+    }
+
+    #[inline]
+    pub fn get_carry(&self) -> bool {
+        self.get_flag(consts::STATUS_CARRY)
+    }
+
+    #[inline]
+    pub fn set_carry(&mut self, value: bool) {
+        self.set_flag(consts::STATUS_CARRY, value);
+    }
+
+    #[inline]
+    pub fn get_zero(&self) -> bool {
+        self.get_flag(consts::STATUS_ZERO)
+    }
+
+    #[inline]
+    pub fn set_zero(&mut self, value: bool) {
+        self.set_flag(consts::STATUS_ZERO, value);
+    }
+
+    #[inline]
+    pub fn get_interrupt_disable(&self) -> bool {
+        self.get_flag(consts::STATUS_INTERRUPT_DISABLE)
+    }
+
+    #[inline]
+    pub fn set_interrupt_disable(&mut self, value: bool) {
+        self.set_flag(consts::STATUS_INTERRUPT_DISABLE, value);
+    }
+
+    #[inline]
+    pub fn get_decimal_mode(&self) -> bool {
+        self.get_flag(consts::STATUS_DECIMAL_MODE)
+    }
+
+    #[inline]
+    pub fn set_decimal_mode(&mut self, value: bool) {
+        self.set_flag(consts::STATUS_DECIMAL_MODE, value);
+    }
+
+    #[inline]
+    pub fn get_overflow(&self) -> bool {
+        self.get_flag(consts::STATUS_OVERFLOW)
+    }
+
+    #[inline]
+    pub fn set_overflow(&mut self, value: bool) {
+        self.set_flag(consts::STATUS_OVERFLOW, value);
+    }
+
+    #[inline]
+    pub fn get_negative(&self) -> bool {
+        self.get_flag(consts::STATUS_NEGATIVE)
+    }
+
+    #[inline]
+    pub fn set_negative(&mut self, value: bool) {
+        self.set_flag(consts::STATUS_NEGATIVE, value);
+    }
+
+    // The copy pushed to the stack, B is set by PHP and BRK and clear for IRQ and NMI
+    #[inline]
+    pub fn get_pushed_byte(&self, is_break: bool) -> Byte {
+        Byte::new(self.0 | consts::STATUS_UNUSED | if is_break { consts::STATUS_BREAK } else { 0 })
+    }
+}
+
+// Pulling the register with PLP or RTI drops B and the unused bit
+impl From<Byte> for StatusRegister {
+    #[inline]
+    fn from(value: Byte) -> StatusRegister {
+        StatusRegister(value.get_value() & !(consts::STATUS_BREAK | consts::STATUS_UNUSED))
+    }
+}
+
+// The register as debuggers and traces show it, the unused bit reads as set
+impl From<StatusRegister> for Byte {
+    #[inline]
+    fn from(status: StatusRegister) -> Byte {
+        Byte::new(status.0 | consts::STATUS_UNUSED)
+    }
+}
+
+impl fmt::Debug for StatusRegister {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:02X}", Byte::from(*self).get_value())
+    }
+}
+
+#[test]
+fn status_register_flags() {
+    let mut status = StatusRegister::new();
+    assert!(status.get_interrupt_disable());
+    assert_eq!(Byte::from(status), Byte::new(0x24));
+
+    status.set_carry(true);
+    status.set_negative(true);
+    status.set_interrupt_disable(false);
+    assert!(status.get_carry() && status.get_negative());
+    assert!(!status.get_zero() && !status.get_overflow() && !status.get_decimal_mode());
+    assert_eq!(Byte::from(status), Byte::new(0xA1));
+
+    status.set_carry(false);
+    assert_eq!(Byte::from(status), Byte::new(0xA0));
+}
+
+#[test]
+fn status_register_push_pull() {
+    let mut status = StatusRegister::from(Byte::new(0x00));
+    status.set_overflow(true);
+
+    assert_eq!(status.get_pushed_byte(true), Byte::new(0x70));
+    assert_eq!(status.get_pushed_byte(false), Byte::new(0x60));
+
+    // B and the unused bit don't survive a pull
+    let pulled = StatusRegister::from(Byte::new(0xFF));
+    assert_eq!(Byte::from(pulled), Byte::new(0xEF));
+    assert_eq!(StatusRegister::from(pulled.get_pushed_byte(true)), pulled);
+}
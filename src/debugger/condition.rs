@@ -0,0 +1,202 @@
+use std::fmt;
+
+use super::DebuggerError;
+
+use crate::core::Byte;
+use crate::core::Double;
+use crate::cpu::CpuBus;
+use crate::cpu::cpu::Cpu;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Register {
+    A,
+    X,
+    Y,
+    SP,
+    P,
+    PC,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    Register(Register),
+    // A byte of memory, written [addr]
+    Memory(u16),
+    Value(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub left: Operand,
+    pub operator: Operator,
+    pub right: Operand,
+}
+
+// Comparisons over registers and memory joined with && and ||, like A == $40 && [$00FF] > 3.
+// && binds tighter than ||, numbers are hex like everywhere in the monitor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    text: String,
+    // Any of the groups must hold, with all the comparisons of the group
+    any_of: Vec<Vec<Comparison>>,
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::<String>::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_alphanumeric() || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '$') {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else if i + 1 < chars.len() && ["==", "!=", "<=", ">=", "&&", "||"].contains(&&*format!("{}{}", c, chars[i + 1])) {
+            tokens.push(format!("{}{}", c, chars[i + 1]));
+            i += 2;
+        } else {
+            tokens.push(c.to_string());
+            i += 1;
+        }
+    }
+
+    tokens
+}
+
+fn parse_value(token: &str) -> Result<u16, DebuggerError> {
+    let digits = token.trim_start_matches('$').trim_start_matches("0x");
+    u16::from_str_radix(digits, 16).map_err(|_| DebuggerError::InvalidArgument(token.to_string()))
+}
+
+fn parse_operand(tokens: &[String], index: &mut usize) -> Result<Operand, DebuggerError> {
+    let token = tokens.get(*index).ok_or(DebuggerError::MissingArgument("operand"))?;
+    *index += 1;
+
+    let operand = match token.to_uppercase().as_str() {
+        "A" => Operand::Register(Register::A),
+        "X" => Operand::Register(Register::X),
+        "Y" => Operand::Register(Register::Y),
+        "SP" => Operand::Register(Register::SP),
+        "P" => Operand::Register(Register::P),
+        "PC" => Operand::Register(Register::PC),
+        "[" => {
+            let addr = parse_value(tokens.get(*index).ok_or(DebuggerError::MissingArgument("address"))?)?;
+            if tokens.get(*index + 1).map(|token| token.as_str()) != Some("]") {
+                return Err(DebuggerError::InvalidArgument(String::from("missing ]")));
+            }
+
+            *index += 2;
+            Operand::Memory(addr)
+        },
+        _ => Operand::Value(parse_value(token)?),
+    };
+
+    Ok(operand)
+}
+
+fn parse_operator(token: Option<&String>) -> Result<Operator, DebuggerError> {
+    match token.map(|token| token.as_str()) {
+        Some("==") => Ok(Operator::Equal),
+        Some("!=") => Ok(Operator::NotEqual),
+        Some("<") => Ok(Operator::Less),
+        Some("<=") => Ok(Operator::LessOrEqual),
+        Some(">") => Ok(Operator::Greater),
+        Some(">=") => Ok(Operator::GreaterOrEqual),
+        Some(token) => Err(DebuggerError::InvalidArgument(token.to_string())),
+        None => Err(DebuggerError::MissingArgument("operator")),
+    }
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Condition, DebuggerError> {
+        let tokens = tokenize(text);
+        let mut any_of = vec![Vec::<Comparison>::new()];
+        let mut index = 0;
+
+        loop {
+            let left = parse_operand(&tokens, &mut index)?;
+            let operator = parse_operator(tokens.get(index))?;
+            index += 1;
+            let right = parse_operand(&tokens, &mut index)?;
+            any_of.last_mut().unwrap().push(Comparison{left, operator, right});
+
+            match tokens.get(index).map(|token| token.as_str()) {
+                Some("&&") => {},
+                Some("||") => any_of.push(Vec::new()),
+                Some(token) => return Err(DebuggerError::InvalidArgument(token.to_string())),
+                None => break,
+            }
+            index += 1;
+        }
+
+        Ok(Condition{text: text.trim().to_string(), any_of})
+    }
+
+    fn get_operand_value<B: CpuBus>(operand: Operand, cpu: &Cpu<B>) -> u16 {
+        match operand {
+            Operand::Register(Register::A) => cpu.get_reg_a().get_value() as u16,
+            Operand::Register(Register::X) => cpu.get_reg_x().get_value() as u16,
+            Operand::Register(Register::Y) => cpu.get_reg_y().get_value() as u16,
+            Operand::Register(Register::SP) => cpu.get_stack_pointer().get_value() as u16,
+            Operand::Register(Register::P) => Byte::from(cpu.get_status()).get_value() as u16,
+            Operand::Register(Register::PC) => cpu.get_program_counter().get_value(),
+            Operand::Memory(addr) => cpu.peek_memory_addr(Double::from(addr)).get_value() as u16,
+            Operand::Value(value) => value,
+        }
+    }
+
+    pub fn evaluate<B: CpuBus>(&self, cpu: &Cpu<B>) -> bool {
+        self.any_of.iter().any(|comparisons| comparisons.iter().all(|comparison| {
+            let left = Condition::get_operand_value(comparison.left, cpu);
+            let right = Condition::get_operand_value(comparison.right, cpu);
+
+            match comparison.operator {
+                Operator::Equal => left == right,
+                Operator::NotEqual => left != right,
+                Operator::Less => left < right,
+                Operator::LessOrEqual => left <= right,
+                Operator::Greater => left > right,
+                Operator::GreaterOrEqual => left >= right,
+            }
+        }))
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[test]
+fn condition_parsing() {
+    let condition = Condition::parse("A == $40 && [$00FF] > 3").unwrap();
+    assert_eq!(condition.any_of, vec![vec![
+        Comparison{left: Operand::Register(Register::A), operator: Operator::Equal, right: Operand::Value(0x40)},
+        Comparison{left: Operand::Memory(0x00FF), operator: Operator::Greater, right: Operand::Value(0x03)},
+    ]]);
+
+    let condition = Condition::parse("x!=0||pc>=C000").unwrap();
+    assert_eq!(condition.any_of.len(), 2);
+    assert_eq!(condition.any_of[1][0].operator, Operator::GreaterOrEqual);
+
+    assert!(Condition::parse("A ==").is_err());
+    assert!(Condition::parse("A = 4").is_err());
+    assert!(Condition::parse("[10 == 4").is_err());
+    assert!(Condition::parse("A == 4 B").is_err());
+}
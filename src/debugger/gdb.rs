@@ -0,0 +1,417 @@
+// A GDB remote serial protocol stub, so any RSP client can debug programs running on the cpu
+// Protocol docs : https://sourceware.org/gdb/onlinedocs/gdb/Remote-Protocol.html
+//
+// There's no 6502 target description in GDB, registers are sent in this order :
+// A, X, Y, P, SP as one byte each, then PC as two bytes, little endian like every register in the protocol.
+
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::core::Byte;
+use crate::core::Double;
+use crate::cpu::{CpuBus, CpuError, StatusRegister};
+use crate::cpu::cpu::Cpu;
+
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+
+const INTERRUPT_BYTE: u8 = 0x03;
+const PACKET_SIZE: usize = 0x4000;
+
+// How many instructions run between checks for an interrupt from the client
+const INTERRUPT_POLL_INSTRUCTIONS: usize = 0x1000;
+
+const REGISTER_COUNT: usize = 6;
+const REGISTER_PC: usize = 5;
+
+fn get_checksum(payload: &[u8]) -> u8 {
+    payload.iter().fold(0u8, |checksum, b| checksum.wrapping_add(*b))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn parse_hex_number(text: &str) -> Option<usize> {
+    usize::from_str_radix(text, 16).ok()
+}
+
+// Parses "addr,length" and the optional ":data" that follows it
+fn parse_memory_args(args: &str) -> Option<(u16, usize, Option<&str>)> {
+    let (range, data) = match args.split_once(':') {
+        Some((range, data)) => (range, Some(data)),
+        None => (args, None),
+    };
+    let (addr, length) = range.split_once(',')?;
+
+    Some((parse_hex_number(addr)? as u16, parse_hex_number(length)?, data))
+}
+
+// Takes the byte an interrupt check read ahead first
+fn read_byte(stream: &mut TcpStream, pending: &mut Option<u8>) -> io::Result<Option<u8>> {
+    if let Some(b) = pending.take() {
+        return Ok(Some(b));
+    }
+
+    let mut buffer = [0u8; 1];
+    match stream.read(&mut buffer)? {
+        0 => Ok(None),
+        _ => Ok(Some(buffer[0])),
+    }
+}
+
+// Reads the next packet payload and acknowledges it, an interrupt is returned as its single byte.
+// Returns None once the client disconnects, a payload longer than the advertised PacketSize is an error.
+fn read_packet(stream: &mut TcpStream, pending: &mut Option<u8>) -> io::Result<Option<Vec<u8>>> {
+    loop {
+        match read_byte(stream, pending)? {
+            None => return Ok(None),
+            Some(INTERRUPT_BYTE) => return Ok(Some(vec![INTERRUPT_BYTE])),
+            Some(b'$') => {},
+            // Acks and noise between packets
+            Some(_) => continue,
+        }
+
+        let mut payload = Vec::<u8>::new();
+        loop {
+            match read_byte(stream, pending)? {
+                None => return Ok(None),
+                Some(b'#') => break,
+                Some(_) if payload.len() == PACKET_SIZE => {
+                    stream.write_all(b"-")?;
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "GDB packet longer than PacketSize"));
+                },
+                Some(b) => payload.push(b),
+            }
+        }
+
+        let mut checksum = [0u8; 2];
+        stream.read_exact(&mut checksum)?;
+        let expected = std::str::from_utf8(&checksum).ok().and_then(|text| u8::from_str_radix(text, 16).ok());
+
+        if expected == Some(get_checksum(&payload)) {
+            stream.write_all(b"+")?;
+            return Ok(Some(payload));
+        }
+
+        log::warn!("GDB packet with a bad checksum, asking for it again");
+        stream.write_all(b"-")?;
+    }
+}
+
+fn write_packet(stream: &mut TcpStream, payload: &str) -> io::Result<()> {
+    log::trace!("GDB reply : {}", payload);
+    write!(stream, "${}#{:02x}", payload, get_checksum(payload.as_bytes()))?;
+    stream.flush()
+}
+
+// Checks for an interrupt without blocking, the client sends it while the cpu runs.
+// Any other byte starts the next packet, it's kept for read_packet.
+fn is_interrupt_pending(stream: &mut TcpStream, pending: &mut Option<u8>) -> io::Result<bool> {
+    if pending.is_some() {
+        return Ok(*pending == Some(INTERRUPT_BYTE));
+    }
+
+    stream.set_nonblocking(true)?;
+    let mut buffer = [0u8; 1];
+    let result = stream.read(&mut buffer);
+    stream.set_nonblocking(false)?;
+
+    match result {
+        Ok(1) => {
+            *pending = Some(buffer[0]);
+            Ok(buffer[0] == INTERRUPT_BYTE)
+        },
+        Ok(_) => Ok(false),
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+pub struct GdbStub<B: CpuBus> {
+    cpu: Cpu<B>,
+    breakpoints: HashSet<u16>,
+    // A byte read ahead while checking for an interrupt
+    pending_byte: Option<u8>,
+}
+
+impl<B: CpuBus> GdbStub<B> {
+    pub fn new(cpu: Cpu<B>) -> GdbStub<B> {
+        GdbStub{cpu, breakpoints: HashSet::new(), pending_byte: None}
+    }
+
+    pub fn get_cpu(&self) -> &Cpu<B> {
+        &self.cpu
+    }
+
+    fn get_register(&self, index: usize) -> Option<Vec<u8>> {
+        let value = match index {
+            0 => self.cpu.get_reg_a().get_value(),
+            1 => self.cpu.get_reg_x().get_value(),
+            2 => self.cpu.get_reg_y().get_value(),
+            3 => Byte::from(self.cpu.get_status()).get_value(),
+            4 => self.cpu.get_stack_pointer().get_value(),
+            REGISTER_PC => return Some(self.cpu.get_program_counter().get_value().to_le_bytes().to_vec()),
+            _ => return None,
+        };
+
+        Some(vec![value])
+    }
+
+    // Takes the little endian bytes of the register, returns false for unknown registers
+    fn set_register(&mut self, index: usize, value: &[u8]) -> bool {
+        match (index, value) {
+            (0, [value]) => self.cpu.set_reg_a(Byte::new(*value)),
+            (1, [value]) => self.cpu.set_reg_x(Byte::new(*value)),
+            (2, [value]) => self.cpu.set_reg_y(Byte::new(*value)),
+            (3, [value]) => self.cpu.set_status(StatusRegister::from(Byte::new(*value))),
+            (4, [value]) => self.cpu.set_stack_pointer(Byte::new(*value)),
+            (REGISTER_PC, [least, most]) => self.cpu.set_program_counter(Double::from(u16::from_le_bytes([*least, *most]))),
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn get_stop_reply(result: Result<(), CpuError>) -> String {
+        let signal = match result {
+            Ok(()) => SIGTRAP,
+            Err(CpuError::UnknownOpcodeError(_)) => SIGILL,
+        };
+
+        format!("S{:02x}", signal)
+    }
+
+    // Runs until a breakpoint, a cpu error or an interrupt from the client
+    fn continue_execution(&mut self, stream: &mut TcpStream) -> io::Result<String> {
+        let mut executed: usize = 0;
+
+        loop {
+            if let Err(err) = self.cpu.execute_instruction() {
+                return Ok(GdbStub::<B>::get_stop_reply(Err(err)));
+            }
+
+            if self.breakpoints.contains(&self.cpu.get_program_counter().get_value()) {
+                return Ok(GdbStub::<B>::get_stop_reply(Ok(())));
+            }
+
+            executed += 1;
+            if executed.is_multiple_of(INTERRUPT_POLL_INSTRUCTIONS) && is_interrupt_pending(stream, &mut self.pending_byte)? {
+                return Ok(format!("S{:02x}", SIGINT));
+            }
+        }
+    }
+
+    // Handles one packet, returns the reply or None when the session is over
+    fn handle_packet(&mut self, packet: &str, stream: &mut TcpStream) -> io::Result<Option<String>> {
+        log::trace!("GDB packet : {}", packet);
+        let (command, args) = packet.split_at(packet.chars().next().map_or(0, |c| c.len_utf8()));
+
+        let reply = match command {
+            "\u{3}" => format!("S{:02x}", SIGINT),
+            "?" => format!("S{:02x}", SIGTRAP),
+            "g" => {
+                let registers: Vec<u8> = (0..REGISTER_COUNT).filter_map(|index| self.get_register(index)).flatten().collect();
+                encode_hex(&registers)
+            },
+            "G" => match decode_hex(args) {
+                Some(registers) if registers.len() == REGISTER_COUNT + 1 => {
+                    for index in 0..REGISTER_PC {
+                        self.set_register(index, &registers[index..index + 1]);
+                    }
+                    self.set_register(REGISTER_PC, &registers[REGISTER_PC..]);
+                    String::from("OK")
+                },
+                _ => String::from("E01"),
+            },
+            "p" => match parse_hex_number(args).and_then(|index| self.get_register(index)) {
+                Some(value) => encode_hex(&value),
+                None => String::from("E01"),
+            },
+            "P" => {
+                let register = args.split_once('=')
+                    .and_then(|(index, value)| Some((parse_hex_number(index)?, decode_hex(value)?)));
+                match register {
+                    Some((index, value)) if self.set_register(index, &value) => String::from("OK"),
+                    _ => String::from("E01"),
+                }
+            },
+            "m" => match parse_memory_args(args) {
+                // Every byte takes two hex digits in the reply
+                Some((_, length, None)) if length > PACKET_SIZE / 2 => String::from("E01"),
+                Some((addr, length, None)) => {
+                    let values: Vec<u8> = (0..length)
+                        .map(|offset| self.cpu.peek_memory_addr(Double::from(addr.wrapping_add(offset as u16))).get_value())
+                        .collect();
+                    encode_hex(&values)
+                },
+                _ => String::from("E01"),
+            },
+            "M" => match parse_memory_args(args).and_then(|(addr, length, data)| Some((addr, length, decode_hex(data?)?))) {
+                Some((addr, length, values)) if values.len() == length => {
+                    for (offset, value) in values.iter().enumerate() {
+                        self.cpu.set_memory_addr(Double::from(addr.wrapping_add(offset as u16)), Byte::new(*value));
+                    }
+                    String::from("OK")
+                },
+                _ => String::from("E01"),
+            },
+            "Z" | "z" => {
+                let breakpoint = args.split(',').collect::<Vec<&str>>();
+                match (breakpoint.first(), breakpoint.get(1).and_then(|addr| parse_hex_number(addr))) {
+                    // Software breakpoints only
+                    (Some(&"0"), Some(addr)) => {
+                        if command == "Z" {
+                            self.breakpoints.insert(addr as u16);
+                        } else {
+                            self.breakpoints.remove(&(addr as u16));
+                        }
+                        String::from("OK")
+                    },
+                    (Some(_), Some(_)) => String::new(),
+                    _ => String::from("E01"),
+                }
+            },
+            "s" | "c" => {
+                if let Some(addr) = parse_hex_number(args) {
+                    self.cpu.set_program_counter(Double::from(addr as u16));
+                }
+
+                if command == "s" {
+                    GdbStub::<B>::get_stop_reply(self.cpu.execute_instruction())
+                } else {
+                    self.continue_execution(stream)?
+                }
+            },
+            "H" => String::from("OK"),
+            "q" if args.starts_with("Supported") => format!("PacketSize={:x}", PACKET_SIZE),
+            "q" if args == "Attached" => String::from("1"),
+            "D" => {
+                write_packet(stream, "OK")?;
+                return Ok(None);
+            },
+            "k" => return Ok(None),
+            // Empty replies tell the client the packet isn't supported
+            _ => String::new(),
+        };
+
+        Ok(Some(reply))
+    }
+
+    // Serves a single client until it detaches, kills the session or disconnects
+    pub fn serve(&mut self, stream: &mut TcpStream) -> io::Result<()> {
+        while let Some(packet) = read_packet(stream, &mut self.pending_byte)? {
+            let packet = String::from_utf8_lossy(&packet).to_string();
+
+            match self.handle_packet(&packet, stream)? {
+                Some(reply) => write_packet(stream, &reply)?,
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn accept(&mut self, listener: &TcpListener) -> io::Result<()> {
+        let (mut stream, client_addr) = listener.accept()?;
+        log::info!("GDB client connected from {}", client_addr);
+        stream.set_nodelay(true)?;
+
+        self.serve(&mut stream)
+    }
+}
+
+#[cfg(test)]
+fn send_test_packet(stream: &mut TcpStream, payload: &str) -> String {
+    write_packet(stream, payload).unwrap();
+
+    // Skip the ack, then read the reply
+    let mut ack = [0u8; 1];
+    stream.read_exact(&mut ack).unwrap();
+    assert_eq!(ack[0], b'+');
+
+    String::from_utf8(read_packet(stream, &mut None).unwrap().unwrap()).unwrap()
+}
+
+#[test]
+fn gdb_stub_loopback() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stub_addr = listener.local_addr().unwrap();
+
+    let client = std::thread::spawn(move || {
+        let mut stream = TcpStream::connect(stub_addr).unwrap();
+        let mut replies = Vec::<String>::new();
+
+        for packet in ["qSupported:multiprocess+", "?", "g", "m200,3", "m0,ffffffff", "M10,2:beef", "m10,2", "Z0,205,1", "c", "g",
+            "s", "p5", "P0=7f", "p0", "z0,205,1", "Z1,205,1", "Z0,300,1", "c"].iter() {
+            replies.push(send_test_packet(&mut stream, packet));
+        }
+
+        write_packet(&mut stream, "k").unwrap();
+        replies
+    });
+
+    // LDX #$00 ; INX ; CPX #$05 ; BNE $0202 ; BRK, with the BRK handler at $0300
+    let mut mapper = crate::mapper::DebugMapper::new();
+    mapper.load(0x0200, &[0xA2, 0x00, 0xE8, 0xE0, 0x05, 0xD0, 0xFB, 0x00]);
+    mapper.load(0xFFFE, &[0x00, 0x03]);
+    let mut stub = GdbStub::new(Cpu::new_with_entry_point(mapper, Double::from(0x0200u16)));
+    stub.accept(&listener).unwrap();
+
+    let replies = client.join().unwrap();
+    assert_eq!(replies, vec![
+        "PacketSize=4000", "S05", "00000024fd0002", "a200e8", "E01", "OK", "beef", "OK",
+        "S05", "000100a4fd0502", "S05", "0202", "OK", "7f", "OK", "", "OK", "S05",
+    ].iter().map(|reply| reply.to_string()).collect::<Vec<String>>());
+
+    // Continued through the BRK to its handler after the loop breakpoint was removed
+    assert_eq!(stub.get_cpu().get_reg_x().get_value(), 0x05);
+    assert_eq!(stub.get_cpu().get_program_counter().get_value(), 0x0300);
+}
+
+#[test]
+fn gdb_interrupt_check_keeps_packets() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+
+    // A packet sent while the cpu runs isn't an interrupt, and its first byte isn't lost
+    write_packet(&mut client, "?").unwrap();
+    let mut pending = None;
+    while pending.is_none() {
+        assert!(!is_interrupt_pending(&mut stream, &mut pending).unwrap());
+    }
+    assert_eq!(read_packet(&mut stream, &mut pending).unwrap(), Some(b"?".to_vec()));
+
+    client.write_all(&[INTERRUPT_BYTE]).unwrap();
+    while !is_interrupt_pending(&mut stream, &mut pending).unwrap() {}
+    assert_eq!(read_packet(&mut stream, &mut pending).unwrap(), Some(vec![INTERRUPT_BYTE]));
+}
+
+#[test]
+fn gdb_oversized_packet() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+
+    write_packet(&mut client, &"0".repeat(PACKET_SIZE)).unwrap();
+    assert_eq!(read_packet(&mut stream, &mut None).unwrap().unwrap().len(), PACKET_SIZE);
+
+    // The payload isn't buffered past PacketSize, the packet is refused
+    client.write_all(format!("${}", "0".repeat(PACKET_SIZE + 1)).as_bytes()).unwrap();
+    assert_eq!(read_packet(&mut stream, &mut None).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let mut replies = [0u8; 2];
+    client.read_exact(&mut replies).unwrap();
+    assert_eq!(&replies, b"+-");
+}
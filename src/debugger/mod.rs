@@ -0,0 +1,89 @@
+pub mod condition;
+pub mod gdb;
+pub mod monitor;
+pub mod watch;
+
+use std::fmt;
+
+use crate::savestate::SaveStateError;
+
+pub use condition::Condition;
+pub use gdb::GdbStub;
+pub use monitor::Monitor;
+pub use watch::WatchedBus;
+
+#[derive(Debug)]
+pub enum DebuggerError {
+    UnknownCommand(String),
+    MissingArgument(&'static str),
+    InvalidArgument(String),
+    SaveStateFailed(SaveStateError),
+}
+
+impl fmt::Display for DebuggerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DebuggerError::UnknownCommand(command) => write!(f, "Unknown command {}, type help for the command list", command),
+            DebuggerError::MissingArgument(name) => write!(f, "Missing argument : {}", name),
+            DebuggerError::InvalidArgument(argument) => write!(f, "Invalid argument : {}", argument),
+            DebuggerError::SaveStateFailed(err) => write!(f, "Save state failed : {}", err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+// Where execution stops, watchpoints are checked after every instruction and the others before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Breakpoint {
+    ProgramCounter(u16),
+    Opcode(u8),
+    // Any instruction executed inside the inclusive address range
+    Execute(u16, u16),
+    // Cpu accesses inside the inclusive address range, optionally only when the value matches
+    Watch{kind: WatchKind, start_addr: u16, end_addr: u16, value: Option<u8>},
+}
+
+// A breakpoint only stops execution while its condition holds
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalBreakpoint {
+    pub breakpoint: Breakpoint,
+    pub condition: Option<Condition>,
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Breakpoint::ProgramCounter(addr) => write!(f, "pc ${:04X}", addr),
+            Breakpoint::Opcode(opcode) => write!(f, "opcode ${:02X}", opcode),
+            Breakpoint::Execute(start_addr, end_addr) => write!(f, "exec ${:04X}-${:04X}", start_addr, end_addr),
+            Breakpoint::Watch{kind, start_addr, end_addr, value} => {
+                let kind = match kind {
+                    WatchKind::Read => "read",
+                    WatchKind::Write => "write",
+                    WatchKind::ReadWrite => "access",
+                };
+                write!(f, "watch {} ${:04X}-${:04X}", kind, start_addr, end_addr)?;
+
+                match value {
+                    Some(value) => write!(f, " value ${:02X}", value),
+                    None => Ok(()),
+                }
+            },
+        }
+    }
+}
+
+impl fmt::Display for ConditionalBreakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.condition {
+            Some(condition) => write!(f, "{} if {}", self.breakpoint, condition),
+            None => write!(f, "{}", self.breakpoint),
+        }
+    }
+}
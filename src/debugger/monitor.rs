@@ -0,0 +1,653 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+use super::{Breakpoint, Condition, ConditionalBreakpoint, DebuggerError, WatchKind, WatchedBus};
+
+use crate::core::Byte;
+use crate::core::Double;
+use crate::core::consts;
+use crate::cpu::{CpuBus, CpuError};
+use crate::cpu::cpu::Cpu;
+use crate::mapper::{BusAccess, BusAccessKind};
+use crate::nes::Region;
+use crate::savestate::{self, RewindBuffer};
+
+const JSR_OPCODE: u8 = 0x20;
+const RTS_OPCODE: u8 = 0x60;
+
+// Executed addresses kept to disassemble the code before the program counter
+const HISTORY_SIZE: usize = 16;
+const DISASM_HISTORY_LINES: usize = 3;
+const DISASM_DEFAULT_LINES: usize = 10;
+const MEMORY_DUMP_DEFAULT_LENGTH: usize = 0x40;
+const MEMORY_DUMP_LINE_LENGTH: usize = 0x10;
+const REWIND_DEFAULT_INTERVAL_FRAMES: usize = 1;
+const REWIND_DEFAULT_BUDGET_KB: usize = 0x4000;
+
+const HELP_TEXT: &str = "\
+step (s) [count]            Execute count instructions, 1 by default
+next (n)                    Step over a JSR
+finish (f)                  Run until the current subroutine returns
+continue (c)                Run until a breakpoint or a cpu error
+break (b) <addr>            Break when the program counter reaches addr
+break (b) op <opcode>       Break before executing an opcode
+break (b) exec <start> <end> Break on any instruction executed between start and end
+watch <r|w|rw> <start> [end] [value <value>]
+                            Break after the cpu reads or writes memory between start and end, in the cpu
+                            address space only since there's no ppu address space yet
+                            Breakpoints and watchpoints take an optional condition : b C000 if A == 40 && [FF] > 3
+breakpoints (bl)            List the breakpoints
+delete (d) <index>          Delete a breakpoint
+mem (m) <addr> [length]     Dump memory
+write (w) <addr> <value>..  Write bytes to memory
+regs (r)                    Dump the registers
+stack                       Dump the stack
+pc <addr>                   Set the program counter
+disasm (u) [addr] [count]   Disassemble around the program counter, or from addr
+save <file>                 Save the machine state to file
+load <file>                 Load a machine state saved from the same rom
+rewind on [frames] [kb]     Snapshot every few frames while running, keeping at most kb of snapshots
+rewind off                  Stop snapshotting and drop the snapshots
+rewind [count]              Go back count snapshots, 1 by default
+help (h)                    Show this text
+quit (q)                    Leave the monitor
+Numbers are hex, with an optional $ or 0x prefix. An empty line repeats the last command.";
+
+enum StopReason {
+    Condition,
+    Breakpoint(usize),
+    Watchpoint(usize, BusAccess),
+    CpuError(CpuError),
+}
+
+// Hex with an optional $ or 0x prefix, like the addresses the monitor prints
+fn parse_number(argument: Option<&str>, name: &'static str) -> Result<u16, DebuggerError> {
+    let argument = argument.ok_or(DebuggerError::MissingArgument(name))?;
+    let digits = argument.trim_start_matches('$').trim_start_matches("0x");
+
+    u16::from_str_radix(digits, 16).map_err(|_| DebuggerError::InvalidArgument(argument.to_string()))
+}
+
+fn parse_byte(argument: Option<&str>, name: &'static str) -> Result<u8, DebuggerError> {
+    let value = parse_number(argument, name)?;
+    if value > 0xFF {
+        return Err(DebuggerError::InvalidArgument(format!("{:X}", value)));
+    }
+
+    Ok(value as u8)
+}
+
+fn is_condition_met<B: CpuBus>(breakpoint: &ConditionalBreakpoint, cpu: &Cpu<B>) -> bool {
+    breakpoint.condition.as_ref().is_none_or(|condition| condition.evaluate(cpu))
+}
+
+// A machine language monitor, reads commands line by line so it can be scripted.
+// The cpu runs on a WatchedBus so watchpoints can see its memory accesses.
+pub struct Monitor<B: CpuBus> {
+    cpu: Cpu<WatchedBus<B>>,
+    breakpoints: Vec<ConditionalBreakpoint>,
+    history: VecDeque<u16>,
+    last_command: String,
+    // Identifies the rom in the save states
+    rom_hash: u64,
+    // Sets the frame length of the rewind snapshots
+    region: Region,
+    rewind: Option<RewindBuffer>,
+}
+
+impl<B: CpuBus> Monitor<B> {
+    pub fn new(cpu: Cpu<WatchedBus<B>>) -> Monitor<B> {
+        Monitor{cpu, breakpoints: Vec::new(), history: VecDeque::with_capacity(HISTORY_SIZE), last_command: String::new(),
+            rom_hash: 0, region: Region::Ntsc, rewind: None}
+    }
+
+    pub fn set_rom_hash(&mut self, rom_hash: u64) {
+        self.rom_hash = rom_hash;
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    pub fn get_cpu(&self) -> &Cpu<WatchedBus<B>> {
+        &self.cpu
+    }
+
+    pub fn get_cpu_mut(&mut self) -> &mut Cpu<WatchedBus<B>> {
+        &mut self.cpu
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint, condition: Option<Condition>) {
+        self.breakpoints.push(ConditionalBreakpoint{breakpoint, condition});
+        self.update_recording();
+    }
+
+    pub fn remove_breakpoint(&mut self, index: usize) -> ConditionalBreakpoint {
+        let breakpoint = self.breakpoints.remove(index);
+        self.update_recording();
+
+        breakpoint
+    }
+
+    // Accesses are only recorded while there are watchpoints
+    fn update_recording(&mut self) {
+        let has_watchpoints = self.breakpoints.iter()
+            .any(|breakpoint| matches!(breakpoint.breakpoint, Breakpoint::Watch{..}));
+        self.cpu.get_bus_mut().set_recording(has_watchpoints);
+    }
+
+    fn get_hit_breakpoint(&self) -> Option<usize> {
+        let program_counter = self.cpu.get_program_counter().get_value();
+        let opcode = self.cpu.peek_memory_addr(self.cpu.get_program_counter()).get_value();
+
+        self.breakpoints.iter().position(|breakpoint| {
+            let is_hit = match breakpoint.breakpoint {
+                Breakpoint::ProgramCounter(addr) => addr == program_counter,
+                Breakpoint::Opcode(breakpoint_opcode) => breakpoint_opcode == opcode,
+                Breakpoint::Execute(start_addr, end_addr) => (start_addr..=end_addr).contains(&program_counter),
+                Breakpoint::Watch{..} => false,
+            };
+
+            is_hit && is_condition_met(breakpoint, &self.cpu)
+        })
+    }
+
+    fn get_hit_watchpoint(&self, accesses: &[BusAccess]) -> Option<(usize, BusAccess)> {
+        for (index, breakpoint) in self.breakpoints.iter().enumerate() {
+            if let Breakpoint::Watch{kind, start_addr, end_addr, value} = breakpoint.breakpoint {
+                let hit_access = accesses.iter().find(|access| {
+                    let is_kind = match kind {
+                        WatchKind::Read => access.kind == BusAccessKind::Read,
+                        WatchKind::Write => access.kind == BusAccessKind::Write,
+                        WatchKind::ReadWrite => true,
+                    };
+
+                    is_kind && (start_addr..=end_addr).contains(&access.addr.get_value())
+                        && value.is_none_or(|value| value == access.value.get_value())
+                });
+
+                if let Some(access) = hit_access {
+                    if is_condition_met(breakpoint, &self.cpu) {
+                        return Some((index, *access));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn step(&mut self) -> Result<(), CpuError> {
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(self.cpu.get_program_counter().get_value());
+
+        // Drop the accesses the monitor itself made
+        self.cpu.get_bus().take_accesses();
+        let result = self.cpu.execute_instruction();
+
+        if let Some(rewind) = self.rewind.as_mut() {
+            rewind.capture_if_due(&self.cpu);
+        }
+
+        result
+    }
+
+    // Runs until should_stop returns true after an instruction, gets the executed opcode and the stack pointer before it.
+    // Breakpoints are checked before every instruction but the first, so a run can leave a breakpoint.
+    fn run_until<F: FnMut(u8, u8, &Cpu<WatchedBus<B>>) -> bool>(&mut self, mut should_stop: F) -> StopReason {
+        let mut first_instruction = true;
+
+        loop {
+            if !first_instruction {
+                if let Some(index) = self.get_hit_breakpoint() {
+                    return StopReason::Breakpoint(index);
+                }
+            }
+            first_instruction = false;
+
+            let opcode = self.cpu.peek_memory_addr(self.cpu.get_program_counter()).get_value();
+            let stack_pointer = self.cpu.get_stack_pointer().get_value();
+            if let Err(err) = self.step() {
+                return StopReason::CpuError(err);
+            }
+
+            if let Some((index, access)) = self.get_hit_watchpoint(&self.cpu.get_bus().take_accesses()) {
+                return StopReason::Watchpoint(index, access);
+            }
+
+            if should_stop(opcode, stack_pointer, &self.cpu) {
+                return StopReason::Condition;
+            }
+        }
+    }
+
+    fn format_instruction(&self, addr: u16) -> String {
+        let instruction = self.cpu.disassemble_at(addr);
+        let bytes: Vec<String> = instruction.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+
+        format!("{:04X}  {:<8}  {}", addr, bytes.join(" "), instruction)
+    }
+
+    fn format_registers(&self) -> String {
+        let status = Byte::from(self.cpu.get_status());
+        let flags: String = "NV-BDIZC".chars().enumerate()
+            .map(|(i, flag)| if status[7 - i] { flag } else { '.' }).collect();
+
+        format!("PC:{:04X} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} [{}] SP:{:02X} CYC:{}",
+            self.cpu.get_program_counter().get_value(), self.cpu.get_reg_a().get_value(), self.cpu.get_reg_x().get_value(),
+            self.cpu.get_reg_y().get_value(), status.get_value(), flags, self.cpu.get_stack_pointer().get_value(),
+            self.cpu.get_cycle_counter())
+    }
+
+    fn report_stop<W: Write>(&self, reason: StopReason, output: &mut W) -> io::Result<()> {
+        match reason {
+            StopReason::Condition => {},
+            StopReason::Breakpoint(index) => writeln!(output, "Breakpoint #{} ({}) hit", index, self.breakpoints[index])?,
+            StopReason::Watchpoint(index, access) => {
+                let kind = if access.kind == BusAccessKind::Read { "Read" } else { "Write" };
+                writeln!(output, "Watchpoint #{} ({}) hit, {} ${:04X} = {:02X}", index, self.breakpoints[index], kind,
+                    access.addr.get_value(), access.value.get_value())?;
+            },
+            StopReason::CpuError(err) => writeln!(output, "Stopped on cpu error {:?}", err)?,
+        }
+
+        writeln!(output, "{}", self.format_instruction(self.cpu.get_program_counter().get_value()))
+    }
+
+    fn write_memory_dump<W: Write>(&self, start_addr: u16, length: usize, output: &mut W) -> io::Result<()> {
+        let addrs: Vec<u16> = (0..length).map(|offset| start_addr.wrapping_add(offset as u16)).collect();
+
+        for line_addrs in addrs.chunks(MEMORY_DUMP_LINE_LENGTH) {
+            let values: Vec<String> = line_addrs.iter()
+                .map(|addr| format!("{:02X}", self.cpu.peek_memory_addr(Double::from(*addr)).get_value())).collect();
+            writeln!(output, "{:04X}: {}", line_addrs[0], values.join(" "))?;
+        }
+
+        Ok(())
+    }
+
+    fn write_disassembly<W: Write>(&self, start_addr: Option<u16>, count: usize, output: &mut W) -> io::Result<()> {
+        let program_counter = self.cpu.get_program_counter().get_value();
+        let mut addr = start_addr.unwrap_or(program_counter);
+
+        if start_addr.is_none() {
+            let history_start = self.history.len().saturating_sub(DISASM_HISTORY_LINES);
+            for history_addr in self.history.iter().skip(history_start) {
+                writeln!(output, "  {}", self.format_instruction(*history_addr))?;
+            }
+        }
+
+        for _ in 0..count {
+            let marker = if addr == program_counter { '>' } else { ' ' };
+            writeln!(output, "{} {}", marker, self.format_instruction(addr))?;
+            addr = self.cpu.disassemble_at(addr).get_next_addr();
+        }
+
+        Ok(())
+    }
+
+    // Runs a single command, returns false once the monitor should exit
+    pub fn execute_command<W: Write>(&mut self, line: &str, output: &mut W) -> Result<bool, DebuggerError> {
+        let (line, condition) = match line.split_once(" if ") {
+            Some((line, condition)) => (line, Some(Condition::parse(condition)?)),
+            None => (line, None),
+        };
+
+        let mut arguments = line.split_whitespace();
+        let command = match arguments.next() {
+            Some(command) => command,
+            None => return Ok(true),
+        };
+
+        if condition.is_some() && !["b", "break", "watch"].contains(&command) {
+            return Err(DebuggerError::InvalidArgument(String::from("only breakpoints take a condition")));
+        }
+
+        let io_result = match command {
+            "s" | "step" => {
+                let count = match arguments.next() {
+                    Some(count) => std::cmp::max(parse_number(Some(count), "count")? as usize, 1),
+                    None => 1,
+                };
+
+                let mut executed = 0;
+                let reason = self.run_until(|_, _, _| {
+                    executed += 1;
+                    executed == count
+                });
+                self.report_stop(reason, output)
+            },
+            "n" | "next" => {
+                let program_counter = self.cpu.get_program_counter().get_value();
+                let stack_pointer = self.cpu.get_stack_pointer().get_value();
+                let is_jsr = self.cpu.peek_memory_addr(Double::from(program_counter)).get_value() == JSR_OPCODE;
+                let return_addr = program_counter.wrapping_add(3);
+
+                let reason = self.run_until(|_, _, cpu| {
+                    !is_jsr || (cpu.get_program_counter().get_value() == return_addr
+                        && cpu.get_stack_pointer().get_value() == stack_pointer)
+                });
+                self.report_stop(reason, output)
+            },
+            "f" | "finish" => {
+                // Values pushed before the subroutine returns are popped by then, so its RTS runs with this stack pointer
+                let start_stack_pointer = self.cpu.get_stack_pointer().get_value();
+
+                let reason = self.run_until(|opcode, stack_pointer, _| opcode == RTS_OPCODE && stack_pointer >= start_stack_pointer);
+                self.report_stop(reason, output)
+            },
+            "c" | "continue" => {
+                let reason = self.run_until(|_, _, _| false);
+                self.report_stop(reason, output)
+            },
+            "b" | "break" => {
+                let breakpoint = match arguments.next() {
+                    Some("op") => Breakpoint::Opcode(parse_byte(arguments.next(), "opcode")?),
+                    Some("exec") => {
+                        let start_addr = parse_number(arguments.next(), "start")?;
+                        Breakpoint::Execute(start_addr, parse_number(arguments.next(), "end")?)
+                    },
+                    addr => Breakpoint::ProgramCounter(parse_number(addr, "addr")?),
+                };
+
+                self.add_breakpoint(breakpoint, condition);
+                writeln!(output, "Breakpoint #{} ({})", self.breakpoints.len() - 1, self.breakpoints.last().unwrap())
+            },
+            "watch" => {
+                let kind = match arguments.next() {
+                    Some("ppu") => return Err(DebuggerError::InvalidArgument(String::from("no ppu address space yet"))),
+                    Some("r") => WatchKind::Read,
+                    Some("w") => WatchKind::Write,
+                    Some("rw") => WatchKind::ReadWrite,
+                    Some(kind) => return Err(DebuggerError::InvalidArgument(kind.to_string())),
+                    None => return Err(DebuggerError::MissingArgument("r, w or rw")),
+                };
+                let start_addr = parse_number(arguments.next(), "start")?;
+
+                let mut end_addr = start_addr;
+                let mut value = None;
+                while let Some(argument) = arguments.next() {
+                    match argument {
+                        "value" => value = Some(parse_byte(arguments.next(), "value")?),
+                        end => end_addr = parse_number(Some(end), "end")?,
+                    }
+                }
+
+                self.add_breakpoint(Breakpoint::Watch{kind, start_addr, end_addr, value}, condition);
+                writeln!(output, "Watchpoint #{} ({})", self.breakpoints.len() - 1, self.breakpoints.last().unwrap())
+            },
+            "bl" | "breakpoints" => {
+                self.breakpoints.iter().enumerate()
+                    .try_for_each(|(index, breakpoint)| writeln!(output, "#{} {}", index, breakpoint))
+            },
+            "d" | "delete" => {
+                let index = parse_number(arguments.next(), "index")? as usize;
+                if index >= self.breakpoints.len() {
+                    return Err(DebuggerError::InvalidArgument(index.to_string()));
+                }
+
+                let breakpoint = self.remove_breakpoint(index);
+                writeln!(output, "Deleted breakpoint ({})", breakpoint)
+            },
+            "m" | "mem" => {
+                let start_addr = parse_number(arguments.next(), "addr")?;
+                let length = match arguments.next() {
+                    Some(length) => parse_number(Some(length), "length")? as usize,
+                    None => MEMORY_DUMP_DEFAULT_LENGTH,
+                };
+
+                self.write_memory_dump(start_addr, length, output)
+            },
+            "w" | "write" => {
+                let start_addr = parse_number(arguments.next(), "addr")?;
+                let values = arguments.map(|value| parse_byte(Some(value), "value")).collect::<Result<Vec<u8>, DebuggerError>>()?;
+                if values.is_empty() {
+                    return Err(DebuggerError::MissingArgument("value"));
+                }
+
+                for (offset, value) in values.iter().enumerate() {
+                    self.cpu.set_memory_addr(Double::from(start_addr.wrapping_add(offset as u16)), Byte::new(*value));
+                }
+                self.write_memory_dump(start_addr, values.len(), output)
+            },
+            "r" | "regs" => writeln!(output, "{}", self.format_registers()),
+            "stack" => {
+                let stack_pointer = self.cpu.get_stack_pointer().get_value();
+                let values: Vec<String> = (stack_pointer as u16 + 1..=0xFF)
+                    .map(|offset| format!("{:02X}", self.cpu.peek_memory_addr(Double::from(consts::STACK_ADDR + offset)).get_value()))
+                    .collect();
+
+                writeln!(output, "SP:{:02X} [{}]", stack_pointer, values.join(" "))
+            },
+            "pc" => {
+                let addr = parse_number(arguments.next(), "addr")?;
+                self.cpu.set_program_counter(Double::from(addr));
+
+                writeln!(output, "{}", self.format_instruction(addr))
+            },
+            "u" | "disasm" => {
+                let start_addr = match arguments.next() {
+                    Some(addr) => Some(parse_number(Some(addr), "addr")?),
+                    None => None,
+                };
+                let count = match arguments.next() {
+                    Some(count) => parse_number(Some(count), "count")? as usize,
+                    None => DISASM_DEFAULT_LINES,
+                };
+
+                self.write_disassembly(start_addr, count, output)
+            },
+            "save" => {
+                let path = arguments.next().ok_or(DebuggerError::MissingArgument("file"))?;
+                savestate::save_to_file(&self.cpu, self.rom_hash, path).map_err(DebuggerError::SaveStateFailed)?;
+
+                writeln!(output, "Saved state to {}", path)
+            },
+            "load" => {
+                let path = arguments.next().ok_or(DebuggerError::MissingArgument("file"))?;
+                savestate::load_from_file(&mut self.cpu, self.rom_hash, path).map_err(DebuggerError::SaveStateFailed)?;
+                self.history.clear();
+
+                writeln!(output, "{}", self.format_registers())
+            },
+            "rewind" => match arguments.next() {
+                Some("on") => {
+                    let interval_frames = match arguments.next() {
+                        Some(frames) => parse_number(Some(frames), "frames")? as usize,
+                        None => REWIND_DEFAULT_INTERVAL_FRAMES,
+                    };
+                    let budget_kb = match arguments.next() {
+                        Some(budget) => parse_number(Some(budget), "kb")? as usize,
+                        None => REWIND_DEFAULT_BUDGET_KB,
+                    };
+
+                    let mut rewind = RewindBuffer::new(self.region, interval_frames, budget_kb * 0x400);
+                    rewind.capture(&self.cpu);
+                    self.rewind = Some(rewind);
+                    writeln!(output, "Rewind on, every {} frames", interval_frames)
+                },
+                Some("off") => {
+                    self.rewind = None;
+                    writeln!(output, "Rewind off")
+                },
+                count => {
+                    let count = match count {
+                        Some(count) => std::cmp::max(parse_number(Some(count), "count")? as usize, 1),
+                        None => 1,
+                    };
+                    let rewind = self.rewind.as_mut()
+                        .ok_or_else(|| DebuggerError::InvalidArgument(String::from("rewind is off, enable it with rewind on")))?;
+
+                    let mut frame = None;
+                    for _ in 0..count {
+                        match rewind.rewind(&mut self.cpu).map_err(DebuggerError::SaveStateFailed)? {
+                            Some(rewound_frame) => frame = Some(rewound_frame),
+                            None => break,
+                        }
+                    }
+                    self.history.clear();
+
+                    match frame {
+                        Some(frame) => writeln!(output, "Rewound to frame {}\n{}", frame, self.format_registers()),
+                        None => writeln!(output, "Nothing to rewind"),
+                    }
+                },
+            },
+            "h" | "help" => writeln!(output, "{}", HELP_TEXT),
+            "q" | "quit" => return Ok(false),
+            command => return Err(DebuggerError::UnknownCommand(command.to_string())),
+        };
+
+        if let Err(err) = io_result {
+            log::error!("Failed writing monitor output : {}", err);
+        }
+
+        Ok(true)
+    }
+
+    // Reads commands until quit or the end of the input
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        writeln!(output, "{}", self.format_instruction(self.cpu.get_program_counter().get_value()))?;
+        write!(output, "> ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            let line = line?;
+            let command = if line.trim().is_empty() { self.last_command.clone() } else { line };
+
+            match self.execute_command(&command, &mut output) {
+                Ok(true) => {},
+                Ok(false) => return Ok(()),
+                Err(err) => writeln!(output, "{}", err)?,
+            }
+
+            self.last_command = command;
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn get_test_monitor(program: &[u8]) -> Monitor<crate::mapper::DebugMapper> {
+    let mut mapper = crate::mapper::DebugMapper::new();
+    mapper.load(0x0200, program);
+
+    Monitor::new(Cpu::new_with_entry_point(WatchedBus::new(mapper), Double::from(0x0200u16)))
+}
+
+#[cfg(test)]
+fn run_test_script(monitor: &mut Monitor<crate::mapper::DebugMapper>, script: &str) -> String {
+    let mut output = Vec::<u8>::new();
+    monitor.run(script.as_bytes(), &mut output).unwrap();
+
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn monitor_stepping() {
+    let program = [
+        0x20, 0x0A, 0x02, // $0200 JSR $020A
+        0xE8,             // $0203 INX
+        0x4C, 0x04, 0x02, // $0204 JMP $0204
+        0x00, 0x00, 0x00,
+        0xA9, 0x01,       // $020A LDA #$01
+        0x20, 0x10, 0x02, // $020C JSR $0210
+        0x60,             // $020F RTS
+        0xA0, 0x02,       // $0210 LDY #$02
+        0x60,             // $0212 RTS
+    ];
+
+    let mut monitor = get_test_monitor(&program);
+    let output = run_test_script(&mut monitor, "next\nregs\n");
+    assert!(output.contains("0203  E8        INX"), "{}", output);
+    assert!(output.contains("A:01 X:00 Y:02"), "{}", output);
+
+    let mut monitor = get_test_monitor(&program);
+    run_test_script(&mut monitor, "s 2\nfinish\n");
+    assert_eq!(monitor.get_cpu().get_program_counter().get_value(), 0x0203);
+    assert_eq!(monitor.get_cpu().get_reg_y().get_value(), 0x02);
+
+    // An empty line repeats the step
+    let mut monitor = get_test_monitor(&program);
+    run_test_script(&mut monitor, "s\n\n\nquit\nstep\n");
+    assert_eq!(monitor.get_cpu().get_program_counter().get_value(), 0x0210);
+}
+
+#[test]
+fn monitor_breakpoints() {
+    // LDX #$00 ; INX ; CPX #$05 ; BNE $0202 ; JAM
+    let program = [0xA2, 0x00, 0xE8, 0xE0, 0x05, 0xD0, 0xFB, 0x02];
+
+    let mut monitor = get_test_monitor(&program);
+    let output = run_test_script(&mut monitor, "b $0205\nc\nc\nr\nbl\nd 0\nc\n");
+    assert!(output.contains("Breakpoint #0 (pc $0205) hit"), "{}", output);
+    assert!(output.contains("X:02"), "{}", output);
+    assert!(output.contains("Stopped on cpu error UnknownOpcodeError(0x02)"), "{}", output);
+
+    let mut monitor = get_test_monitor(&program);
+    run_test_script(&mut monitor, "b op e0\nc\nc\nc\n");
+    assert_eq!(monitor.get_cpu().get_reg_x().get_value(), 0x03);
+
+    let mut monitor = get_test_monitor(&program);
+    let output = run_test_script(&mut monitor, "b exec 0206 0300\nc\n");
+    assert!(output.contains("Breakpoint #0 (exec $0206-$0300) hit"), "{}", output);
+    assert_eq!(monitor.get_cpu().get_program_counter().get_value(), 0x0207);
+}
+
+#[test]
+fn monitor_memory() {
+    let mut monitor = get_test_monitor(&[0xEA, 0xEA, 0xEA]);
+    let output = run_test_script(&mut monitor, "w 10 de ad\nm 0x10 4\nu 200 2\nstack\npc zz\nfoo\n");
+
+    assert!(output.contains("0010: DE AD 00 00"), "{}", output);
+    assert!(output.contains("> 0200  EA        NOP\n  0201  EA        NOP"), "{}", output);
+    assert!(output.contains("SP:FD [00 00]"), "{}", output);
+    assert!(output.contains("Invalid argument : zz"), "{}", output);
+    assert!(output.contains("Unknown command foo"), "{}", output);
+}
+
+#[test]
+fn monitor_watchpoints() {
+    // LDX #$00 ; INX ; STX $10 ; LDA $10 ; CPX #$05 ; BNE $0202 ; BRK
+    let program = [0xA2, 0x00, 0xE8, 0x86, 0x10, 0xA5, 0x10, 0xE0, 0x05, 0xD0, 0xF7, 0x00];
+
+    let mut monitor = get_test_monitor(&program);
+    let output = run_test_script(&mut monitor, "watch w 10 value 3\nc\nwatch r 10 if X == 4\nd 0\nc\n");
+    assert!(output.contains("Watchpoint #0 (watch write $0010-$0010 value $03) hit, Write $0010 = 03"), "{}", output);
+    assert!(output.contains("Watchpoint #0 (watch read $0010-$0010 if X == 4) hit, Read $0010 = 04"), "{}", output);
+    assert_eq!(monitor.get_cpu().get_program_counter().get_value(), 0x0207);
+
+    let mut monitor = get_test_monitor(&program);
+    let output = run_test_script(&mut monitor, "b 0207 if [10] >= 2 && A == 2\nc\nr\n");
+    assert!(output.contains("Breakpoint #0 (pc $0207 if [10] >= 2 && A == 2) hit"), "{}", output);
+    assert!(output.contains("A:02 X:02"), "{}", output);
+
+    // Peeking from the monitor doesn't trigger watchpoints
+    let mut monitor = get_test_monitor(&program);
+    let output = run_test_script(&mut monitor, "watch rw 0 ff\nm 10 1\ns\nr if A == 1\n");
+    assert!(!output.contains("Watchpoint #0 (watch access $0000-$00FF) hit"), "{}", output);
+    assert!(output.contains("Invalid argument : only breakpoints take a condition"), "{}", output);
+
+    let output = run_test_script(&mut monitor, "watch ppu w 2000 23ff\nbl\n");
+    assert!(output.contains("Invalid argument : no ppu address space yet"), "{}", output);
+    assert!(!output.contains("#1"), "{}", output);
+}
+
+#[test]
+fn monitor_rewind() {
+    // INC $10 ; JMP $0200
+    let program = [0xE6, 0x10, 0x4C, 0x00, 0x02];
+
+    let mut monitor = get_test_monitor(&program);
+    let output = run_test_script(&mut monitor, "rewind\nrewind on\ns 4000\nrewind 2\nrewind 10\nrewind\n");
+    assert!(output.contains("rewind is off"), "{}", output);
+    assert!(output.contains("Rewound to frame 1"), "{}", output);
+    assert!(output.contains("Rewound to frame 0\nPC:0200"), "{}", output);
+    assert!(output.contains("Nothing to rewind"), "{}", output);
+    assert_eq!(monitor.get_cpu().get_cycle_counter(), 7);
+}
@@ -0,0 +1,79 @@
+use std::cell::RefCell;
+
+use crate::core::Byte;
+use crate::core::Double;
+use crate::cpu::CpuBus;
+use crate::mapper::{BusAccess, BusAccessKind};
+use crate::savestate::{SaveStateError, StateReader, StateWriter};
+
+// Wraps the bus of a debugged cpu to report the accesses the cpu makes, peeks are not reported.
+// Only the monitor uses it, a cpu running on the bare bus doesn't pay for the bookkeeping.
+pub struct WatchedBus<B: CpuBus> {
+    bus: B,
+    recording: bool,
+    accesses: RefCell<Vec<BusAccess>>,
+}
+
+impl<B: CpuBus> WatchedBus<B> {
+    pub fn new(bus: B) -> WatchedBus<B> {
+        WatchedBus{bus, recording: false, accesses: RefCell::new(Vec::new())}
+    }
+
+    pub fn get_bus(&self) -> &B {
+        &self.bus
+    }
+
+    pub fn get_bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
+
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
+        self.accesses.borrow_mut().clear();
+    }
+
+    // Returns the accesses recorded since the last call
+    pub fn take_accesses(&self) -> Vec<BusAccess> {
+        self.accesses.replace(Vec::new())
+    }
+
+    fn record(&self, addr: Double, value: Byte, kind: BusAccessKind) {
+        if self.recording {
+            self.accesses.borrow_mut().push(BusAccess{addr, value, kind});
+        }
+    }
+}
+
+impl<B: CpuBus> CpuBus for WatchedBus<B> {
+    fn read(&self, addr: Double) -> Byte {
+        let value = self.bus.read(addr);
+        self.record(addr, value, BusAccessKind::Read);
+
+        value
+    }
+
+    fn write(&mut self, addr: Double, value: Byte) {
+        self.bus.write(addr, value);
+        self.record(addr, value, BusAccessKind::Write);
+    }
+
+    fn peek(&self, addr: Double) -> Byte {
+        self.bus.peek(addr)
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        self.bus.tick(cycles);
+    }
+
+    fn get_prg_bank(&self, addr: Double) -> Option<usize> {
+        self.bus.get_prg_bank(addr)
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.bus.save_state(state);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.bus.load_state(state)
+    }
+}
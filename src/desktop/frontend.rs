@@ -0,0 +1,239 @@
+use super::{DesktopError, Hotkey, KeyMap, Pacer};
+use super::screenshot;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use minifb::{KeyRepeat, Window, WindowOptions};
+
+use crate::core::consts;
+use crate::nes::Nes;
+
+// A window showing the framebuffer at an integer scale, fed by the keyboard.
+// Save states and screenshots are written next to the rom.
+pub struct Frontend {
+    nes: Nes,
+    window: Window,
+    keymap: KeyMap,
+    pacer: Pacer,
+    scale: usize,
+    // The framebuffer scaled up, as 0RGB pixels
+    pixels: Vec<u32>,
+    rom_path: PathBuf,
+    paused: bool,
+    frame_advance: bool,
+    quit: bool,
+}
+
+impl Frontend {
+    pub fn new(nes: Nes, rom_path: &Path, scale: usize) -> Result<Frontend, DesktopError> {
+        let scale = scale.clamp(1, consts::DESKTOP_MAX_SCALE);
+        let mut window = Window::new(&get_title(rom_path, false), consts::FRAME_WIDTH * scale,
+            consts::FRAME_HEIGHT * scale, WindowOptions::default()).map_err(DesktopError::WindowFailed)?;
+        // Frames are paced by the audio, not by the window
+        window.set_target_fps(0);
+
+        let mut frontend = Frontend{nes, window, keymap: KeyMap::new(), pacer: Pacer::new(consts::AUDIO_SAMPLE_RATE), scale,
+            pixels: vec![0; consts::FRAME_WIDTH * consts::FRAME_HEIGHT * scale * scale], rom_path: rom_path.to_path_buf(),
+            paused: false, frame_advance: false, quit: false};
+        frontend.scale_frame();
+
+        Ok(frontend)
+    }
+
+    pub fn get_nes(&self) -> &Nes {
+        &self.nes
+    }
+
+    pub fn get_keymap_mut(&mut self) -> &mut KeyMap {
+        &mut self.keymap
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.window.is_open() && !self.quit
+    }
+
+    // Runs until the window is closed or quit
+    pub fn run(&mut self) -> Result<(), DesktopError> {
+        while self.is_open() {
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    // Handles the keys, runs a frame unless paused and shows it
+    pub fn step(&mut self) -> Result<(), DesktopError> {
+        for key in self.window.get_keys_pressed(KeyRepeat::No) {
+            if let Some(hotkey) = self.keymap.get_hotkey(key) {
+                // A missing state file shouldn't close the window
+                if let Err(err) = self.handle_hotkey(hotkey) {
+                    log::error!("{:?} failed : {}", hotkey, err);
+                }
+            }
+        }
+
+        let run_frame = !self.paused || std::mem::take(&mut self.frame_advance);
+        if run_frame {
+            let keys = self.window.get_keys();
+            for port in 0..2 {
+                self.nes.set_buttons(port, self.keymap.get_buttons(&keys, port));
+            }
+
+            self.nes.run_frame().map_err(DesktopError::NesFailed)?;
+            self.scale_frame();
+        }
+
+        self.window.update_with_buffer(&self.pixels, consts::FRAME_WIDTH * self.scale, consts::FRAME_HEIGHT * self.scale)
+            .map_err(DesktopError::WindowFailed)?;
+
+        if self.paused {
+            // The window still needs updates to see the keys
+            std::thread::sleep(Duration::from_secs_f64(1.0 / self.nes.get_region().get_frames_per_second()));
+        } else {
+            self.pacer.wait(self.nes.get_audio_samples().len());
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_hotkey(&mut self, hotkey: Hotkey) -> Result<(), DesktopError> {
+        match hotkey {
+            Hotkey::Pause => self.set_paused(!self.paused),
+            Hotkey::Reset => self.nes.reset(),
+            Hotkey::FrameAdvance => {
+                self.set_paused(true);
+                self.frame_advance = true;
+            },
+            Hotkey::Screenshot => {
+                let path = self.get_screenshot_path();
+                std::fs::write(&path, screenshot::encode_png(self.nes.get_framebuffer(), consts::FRAME_WIDTH, consts::FRAME_HEIGHT))?;
+                log::info!("Saved a screenshot to {}", path.display());
+            },
+            Hotkey::SaveState => {
+                std::fs::write(self.get_state_path(), self.nes.save_state())?;
+                log::info!("Saved the state to {}", self.get_state_path().display());
+            },
+            Hotkey::LoadState => {
+                let state = std::fs::read(self.get_state_path())?;
+                self.nes.load_state(&state).map_err(DesktopError::NesFailed)?;
+                self.scale_frame();
+                self.pacer.restart();
+            },
+            Hotkey::Quit => self.quit = true,
+        }
+
+        Ok(())
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        if self.paused != paused {
+            self.paused = paused;
+            self.pacer.restart();
+            self.window.set_title(&get_title(&self.rom_path, paused));
+        }
+    }
+
+    fn get_state_path(&self) -> PathBuf {
+        self.rom_path.with_extension("state")
+    }
+
+    // Named by the frame so screenshots don't overwrite each other
+    fn get_screenshot_path(&self) -> PathBuf {
+        let rom_name = self.rom_path.file_stem().map_or(String::from("nessy"), |stem| stem.to_string_lossy().to_string());
+        self.rom_path.with_file_name(format!("{}-{}.png", rom_name, self.nes.get_frame_counter()))
+    }
+
+    fn scale_frame(&mut self) {
+        scale_frame(self.nes.get_framebuffer(), self.scale, &mut self.pixels);
+    }
+}
+
+fn get_title(rom_path: &Path, paused: bool) -> String {
+    let rom_name = rom_path.file_name().map_or(String::new(), |name| name.to_string_lossy().to_string());
+    format!("Nessy - {}{}", rom_name, if paused { " (paused)" } else { "" })
+}
+
+// Nearest neighbour, every RGBA pixel becomes a scale * scale square of 0RGB pixels
+fn scale_frame(rgba: &[u8], scale: usize, pixels: &mut [u32]) {
+    let width = consts::FRAME_WIDTH * scale;
+    for (y, row) in rgba.chunks_exact(consts::FRAME_WIDTH * consts::FRAME_BYTES_PER_PIXEL).enumerate() {
+        let line_start = y * scale * width;
+        for (x, pixel) in row.chunks_exact(consts::FRAME_BYTES_PER_PIXEL).enumerate() {
+            let value = (pixel[0] as u32) << 16 | (pixel[1] as u32) << 8 | pixel[2] as u32;
+            pixels[line_start + x * scale..line_start + (x + 1) * scale].fill(value);
+        }
+
+        for line in 1..scale {
+            pixels.copy_within(line_start..line_start + width, line_start + line * width);
+        }
+    }
+}
+
+#[test]
+fn scale_frame_by_integer() {
+    let mut rgba = vec![0u8; consts::FRAME_WIDTH * consts::FRAME_HEIGHT * 4];
+    rgba[..4].copy_from_slice(&[0x12, 0x34, 0x56, 0xFF]);
+    let last_pixel = rgba.len() - 4;
+    rgba[last_pixel..].copy_from_slice(&[0xAB, 0xCD, 0xEF, 0xFF]);
+
+    let mut pixels = vec![0u32; consts::FRAME_WIDTH * consts::FRAME_HEIGHT * 9];
+    scale_frame(&rgba, 3, &mut pixels);
+
+    let width = consts::FRAME_WIDTH * 3;
+    for (x, y) in [(0, 0), (2, 0), (0, 2), (2, 2)] {
+        assert_eq!(pixels[y * width + x], 0x123456);
+    }
+    assert_eq!(pixels[3], 0);
+    assert_eq!(pixels[3 * width], 0);
+    assert_eq!(pixels[pixels.len() - 1], 0xABCDEF);
+    assert_eq!(pixels[pixels.len() - 1 - 2 * width - 2], 0xABCDEF);
+    assert_eq!(pixels[pixels.len() - 1 - 3 * width], 0);
+}
+
+// Needs an X server, run it under a virtual one with xvfb-run cargo test --features desktop
+#[test]
+fn desktop_window() {
+    if std::env::var_os("DISPLAY").is_none() {
+        println!("Skipping desktop_window, no DISPLAY (run it under xvfb-run)");
+        return;
+    }
+
+    let work_dir = std::env::temp_dir().join(format!("nessy-desktop-{}", std::process::id()));
+    std::fs::create_dir_all(&work_dir).unwrap();
+    let rom_path = work_dir.join("test.nes");
+    let rom = crate::nes::console::get_test_rom();
+    std::fs::write(&rom_path, &rom).unwrap();
+
+    let mut frontend = Frontend::new(Nes::new(&rom).unwrap(), &rom_path, 2).unwrap();
+    assert!(frontend.is_open());
+    for _ in 0..3 {
+        frontend.step().unwrap();
+    }
+    assert_eq!(frontend.get_nes().get_frame_counter(), 3);
+
+    frontend.handle_hotkey(Hotkey::Pause).unwrap();
+    frontend.step().unwrap();
+    assert_eq!(frontend.get_nes().get_frame_counter(), 3);
+    frontend.handle_hotkey(Hotkey::FrameAdvance).unwrap();
+    frontend.step().unwrap();
+    frontend.step().unwrap();
+    assert_eq!(frontend.get_nes().get_frame_counter(), 4);
+    assert!(frontend.is_paused());
+
+    frontend.handle_hotkey(Hotkey::SaveState).unwrap();
+    frontend.handle_hotkey(Hotkey::Screenshot).unwrap();
+    assert!(work_dir.join("test-4.png").is_file());
+    frontend.handle_hotkey(Hotkey::Pause).unwrap();
+    frontend.step().unwrap();
+    frontend.handle_hotkey(Hotkey::LoadState).unwrap();
+    assert_eq!(frontend.get_nes().get_frame_counter(), 4);
+
+    frontend.handle_hotkey(Hotkey::Quit).unwrap();
+    assert!(!frontend.is_open());
+    std::fs::remove_dir_all(&work_dir).unwrap();
+}
@@ -0,0 +1,100 @@
+use minifb::Key;
+
+use super::Hotkey;
+
+use crate::core::consts;
+
+// Which keys press which controller buttons, and which run the hotkeys
+pub struct KeyMap {
+    // Key, port and button
+    buttons: Vec<(Key, usize, u8)>,
+    hotkeys: Vec<(Key, Hotkey)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> KeyMap {
+        KeyMap::new()
+    }
+}
+
+impl KeyMap {
+    // The first controller on the arrows with Z and X, the second on WASD with Q and E
+    pub fn new() -> KeyMap {
+        let buttons = vec![
+            (Key::X, 0, consts::BUTTON_A),
+            (Key::Z, 0, consts::BUTTON_B),
+            (Key::RightShift, 0, consts::BUTTON_SELECT),
+            (Key::Enter, 0, consts::BUTTON_START),
+            (Key::Up, 0, consts::BUTTON_UP),
+            (Key::Down, 0, consts::BUTTON_DOWN),
+            (Key::Left, 0, consts::BUTTON_LEFT),
+            (Key::Right, 0, consts::BUTTON_RIGHT),
+            (Key::E, 1, consts::BUTTON_A),
+            (Key::Q, 1, consts::BUTTON_B),
+            (Key::Key1, 1, consts::BUTTON_SELECT),
+            (Key::Key2, 1, consts::BUTTON_START),
+            (Key::W, 1, consts::BUTTON_UP),
+            (Key::S, 1, consts::BUTTON_DOWN),
+            (Key::A, 1, consts::BUTTON_LEFT),
+            (Key::D, 1, consts::BUTTON_RIGHT),
+        ];
+
+        let hotkeys = vec![
+            (Key::P, Hotkey::Pause),
+            (Key::F1, Hotkey::Reset),
+            (Key::Backslash, Hotkey::FrameAdvance),
+            (Key::F12, Hotkey::Screenshot),
+            (Key::F5, Hotkey::SaveState),
+            (Key::F7, Hotkey::LoadState),
+            (Key::Escape, Hotkey::Quit),
+        ];
+
+        KeyMap{buttons, hotkeys}
+    }
+
+    // A key does one thing, binding it drops what it did before
+    pub fn set_button(&mut self, key: Key, port: usize, button: u8) {
+        self.unbind(key);
+        self.buttons.push((key, port, button));
+    }
+
+    pub fn set_hotkey(&mut self, key: Key, hotkey: Hotkey) {
+        self.unbind(key);
+        self.hotkeys.push((key, hotkey));
+    }
+
+    fn unbind(&mut self, key: Key) {
+        self.buttons.retain(|(bound_key, _, _)| *bound_key != key);
+        self.hotkeys.retain(|(bound_key, _)| *bound_key != key);
+    }
+
+    // The buttons held on a port given the keys held
+    pub fn get_buttons(&self, keys: &[Key], port: usize) -> u8 {
+        self.buttons.iter()
+            .filter(|(key, button_port, _)| *button_port == port && keys.contains(key))
+            .fold(0, |buttons, (_, _, button)| buttons | button)
+    }
+
+    pub fn get_hotkey(&self, key: Key) -> Option<Hotkey> {
+        self.hotkeys.iter().find(|(bound_key, _)| *bound_key == key).map(|(_, hotkey)| *hotkey)
+    }
+}
+
+#[test]
+fn keymap_bindings() {
+    let mut keymap = KeyMap::new();
+    let keys = [Key::X, Key::Up, Key::W, Key::P];
+    assert_eq!(keymap.get_buttons(&keys, 0), consts::BUTTON_A | consts::BUTTON_UP);
+    assert_eq!(keymap.get_buttons(&keys, 1), consts::BUTTON_UP);
+    assert_eq!(keymap.get_hotkey(Key::P), Some(Hotkey::Pause));
+    assert_eq!(keymap.get_hotkey(Key::X), None);
+
+    // Rebinding a hotkey key to a button replaces the hotkey
+    keymap.set_button(Key::P, 0, consts::BUTTON_START);
+    assert_eq!(keymap.get_hotkey(Key::P), None);
+    assert_eq!(keymap.get_buttons(&keys, 0), consts::BUTTON_A | consts::BUTTON_UP | consts::BUTTON_START);
+
+    keymap.set_hotkey(Key::X, Hotkey::Quit);
+    assert_eq!(keymap.get_buttons(&keys, 0), consts::BUTTON_UP | consts::BUTTON_START);
+    assert_eq!(keymap.get_hotkey(Key::X), Some(Hotkey::Quit));
+}
@@ -0,0 +1,49 @@
+// A windowed frontend over Nes, built with the desktop feature so the core doesn't need a window library
+pub mod frontend;
+pub mod keymap;
+pub mod pacer;
+pub mod screenshot;
+
+use std::fmt;
+use std::io;
+
+use crate::nes::NesError;
+
+pub use frontend::Frontend;
+pub use keymap::KeyMap;
+pub use pacer::Pacer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hotkey {
+    Pause,
+    Reset,
+    // Runs one frame and pauses
+    FrameAdvance,
+    Screenshot,
+    SaveState,
+    LoadState,
+    Quit,
+}
+
+#[derive(Debug)]
+pub enum DesktopError {
+    WindowFailed(minifb::Error),
+    NesFailed(NesError),
+    Io(io::Error),
+}
+
+impl fmt::Display for DesktopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DesktopError::WindowFailed(err) => write!(f, "Window failed : {}", err),
+            DesktopError::NesFailed(err) => write!(f, "{}", err),
+            DesktopError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for DesktopError {
+    fn from(err: io::Error) -> DesktopError {
+        DesktopError::Io(err)
+    }
+}
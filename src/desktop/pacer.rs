@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant};
+
+use crate::core::consts;
+
+// Paces the emulation by the audio it makes, every frame waits until the samples queued so far would have played.
+// The frame rate follows the emulated clock, NTSC or PAL, instead of the display's refresh rate.
+pub struct Pacer {
+    sample_rate: usize,
+    start: Instant,
+    samples: usize,
+}
+
+impl Pacer {
+    pub fn new(sample_rate: usize) -> Pacer {
+        Pacer{sample_rate, start: Instant::now(), samples: 0}
+    }
+
+    // After a pause, the time spent paused isn't caught up on
+    pub fn restart(&mut self) {
+        self.start = Instant::now();
+        self.samples = 0;
+    }
+
+    // Queues a frame's samples and returns how long until they played
+    pub fn get_delay(&mut self, samples: usize, now: Instant) -> Duration {
+        self.samples += samples;
+        let played = self.start + Duration::from_secs_f64(self.samples as f64 / self.sample_rate as f64);
+
+        if now > played + Duration::from_millis(consts::DESKTOP_RESYNC_THRESHOLD_MS) {
+            self.start = now;
+            self.samples = 0;
+            return Duration::ZERO;
+        }
+
+        played.saturating_duration_since(now)
+    }
+
+    pub fn wait(&mut self, samples: usize) {
+        let delay = self.get_delay(samples, Instant::now());
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+}
+
+#[test]
+fn pacer_delay() {
+    let mut pacer = Pacer::new(1000);
+    let start = pacer.start;
+
+    assert_eq!(pacer.get_delay(20, start), Duration::from_millis(20));
+    assert_eq!(pacer.get_delay(20, start + Duration::from_millis(15)), Duration::from_millis(25));
+    // Running late plays the next frame at once
+    assert_eq!(pacer.get_delay(20, start + Duration::from_millis(90)), Duration::ZERO);
+
+    // Far behind, the late frames are dropped from the schedule
+    let late = start + Duration::from_millis(500);
+    assert_eq!(pacer.get_delay(20, late), Duration::ZERO);
+    assert_eq!(pacer.get_delay(20, late), Duration::from_millis(20));
+}
@@ -0,0 +1,82 @@
+// Screenshots are PNG files with uncompressed deflate blocks, big but they need no compression library
+
+// RGBA pixels, row by row
+pub fn encode_png(rgba: &[u8], width: usize, height: usize) -> Vec<u8> {
+    // Every row starts with its filter type, none
+    let mut image = Vec::with_capacity((width * 4 + 1) * height);
+    for row in rgba.chunks_exact(width * 4).take(height) {
+        image.push(0);
+        image.extend_from_slice(row);
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bit RGBA, deflate, no interlacing
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1A\n".to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &get_zlib_stream(&image));
+    write_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = get_crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Stored blocks of up to 0xFFFF bytes
+fn get_zlib_stream(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let block_count = data.chunks(0xFFFF).len();
+    for (i, block) in data.chunks(0xFFFF).enumerate() {
+        stream.push((i + 1 == block_count) as u8);
+        stream.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        stream.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&get_adler32(data).to_be_bytes());
+
+    stream
+}
+
+fn get_crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, b| {
+        (0..8).fold(crc ^ *b as u32, |crc, _| if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 })
+    })
+}
+
+fn get_adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+
+    b << 16 | a
+}
+
+#[test]
+fn png_encoding() {
+    assert_eq!(get_crc32(b"IEND"), 0xAE426082);
+    assert_eq!(get_adler32(b"Wikipedia"), 0x11E60398);
+
+    let rgba: Vec<u8> = (0..300 * 250 * 4).map(|i| i as u8).collect();
+    let png = encode_png(&rgba, 300, 250);
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1A\n");
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(&png[16..24], &[0, 0, 0x01, 0x2C, 0, 0, 0, 0xFA]);
+    assert_eq!(&png[png.len() - 12..], &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]);
+
+    // The image is bigger than a stored block, only the last one is marked final
+    let stream = &png[41..png.len() - 16];
+    let image_size: usize = (300 * 4 + 1) * 250;
+    assert_eq!(&stream[2..7], &[0x00, 0xFF, 0xFF, 0x00, 0x00]);
+    assert_eq!(stream.len(), 2 + image_size.div_ceil(0xFFFF) * 5 + image_size + 4);
+}
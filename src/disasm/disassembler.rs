@@ -0,0 +1,311 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::core::consts;
+use crate::cpu::CpuVariant;
+use crate::cpu::instructions::{Instruction, get_instruction_set, get_cmos_instruction_set};
+
+const JSR_OPCODE: u8 = 0x20;
+const JMP_ABSOLUTE_OPCODE: u8 = 0x4C;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisassembledInstruction {
+    pub addr: u16,
+    pub bytes: Vec<u8>,
+    pub name: String,
+    pub mode: String,
+    pub operand: String,
+}
+
+impl DisassembledInstruction {
+    pub fn get_opcode(&self) -> u8 {
+        self.bytes[0]
+    }
+
+    pub fn get_next_addr(&self) -> u16 {
+        self.addr.wrapping_add(self.bytes.len() as u16)
+    }
+
+    fn get_first_arg(&self) -> u8 {
+        self.bytes.get(1).copied().unwrap_or(0)
+    }
+
+    fn get_absolute_arg(&self) -> u16 {
+        self.get_first_arg() as u16 + self.bytes.get(2).copied().unwrap_or(0) as u16 * 0x100
+    }
+
+    // Where a branch, JMP or JSR goes, this doesn't need any cpu state
+    pub fn get_target_addr(&self) -> Option<u16> {
+        match self.mode.as_str() {
+            "Relative" => Some(get_relative_target(self.addr, self.get_first_arg())),
+            "Absolute" if self.get_opcode() == JMP_ABSOLUTE_OPCODE || self.get_opcode() == JSR_OPCODE => {
+                Some(self.get_absolute_arg())
+            },
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DisassembledInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.operand.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{} {}", self.name, self.operand)
+        }
+    }
+}
+
+fn get_relative_target(addr: u16, offset: u8) -> u16 {
+    addr.wrapping_add(2).wrapping_add(offset as i8 as u16)
+}
+
+fn get_label(addr: u16) -> String {
+    format!("L_{:04X}", addr)
+}
+
+fn get_data_instruction(addr: u16, value: u8) -> DisassembledInstruction {
+    DisassembledInstruction{addr, bytes: vec![value], name: String::from(".db"), mode: String::from("Data"),
+        operand: format!("${:02X}", value)}
+}
+
+// Some unofficial opcodes go by several names, use the most common one
+pub fn get_mnemonic(name: &str) -> &str {
+    match name {
+        "IGN" | "SKB" => "NOP",
+        "ISC" => "ISB",
+        name => name,
+    }
+}
+
+// Renders an operand in the usual assembler syntax, addr is the address of the opcode
+pub fn format_operand(mode: &str, addr: u16, args: &[u8]) -> String {
+    let first_arg = args.first().copied().unwrap_or(0);
+    let absolute_addr = first_arg as u16 + args.get(1).copied().unwrap_or(0) as u16 * 0x100;
+
+    match mode {
+        "Accumulator" => String::from("A"),
+        "Immediate" => format!("#${:02X}", first_arg),
+        "ZeroPage" => format!("${:02X}", first_arg),
+        "ZeroPage,X" => format!("${:02X},X", first_arg),
+        "ZeroPage,Y" => format!("${:02X},Y", first_arg),
+        "Absolute" => format!("${:04X}", absolute_addr),
+        "Absolute,X" => format!("${:04X},X", absolute_addr),
+        "Absolute,Y" => format!("${:04X},Y", absolute_addr),
+        "Indirect" => format!("(${:04X})", absolute_addr),
+        "(Absolute,X)" => format!("(${:04X},X)", absolute_addr),
+        "(Indirect)" => format!("(${:02X})", first_arg),
+        "(Indirect,X)" => format!("(${:02X},X)", first_arg),
+        "(Indirect),Y" => format!("(${:02X}),Y", first_arg),
+        "Relative" => format!("${:04X}", get_relative_target(addr, first_arg)),
+        _ => String::new(),
+    }
+}
+
+// Decodes the instruction at addr, opcodes missing from the instruction set are shown as data bytes
+pub fn disassemble_instruction<F: Fn(u16) -> u8>(instruction_set: &HashMap<u8, Instruction>, addr: u16, read: F)
+    -> DisassembledInstruction {
+    let opcode = read(addr);
+
+    match instruction_set.get(&opcode) {
+        Some(instruction) => {
+            let bytes: Vec<u8> = (0..instruction.bytes as u16).map(|offset| read(addr.wrapping_add(offset))).collect();
+            let operand = format_operand(&instruction.mode, addr, &bytes[1..]);
+
+            DisassembledInstruction{addr, bytes, name: get_mnemonic(&instruction.name).to_string(),
+                mode: instruction.mode.clone(), operand}
+        },
+        None => get_data_instruction(addr, opcode),
+    }
+}
+
+// Resolves the address an instruction accesses (or jumps to) with the given index registers and memory
+pub fn get_effective_addr<F: Fn(u16) -> u8>(instruction: &DisassembledInstruction, variant: CpuVariant,
+    reg_x: u8, reg_y: u8, read: F) -> Option<u16> {
+    let first_arg = instruction.get_first_arg();
+    let absolute_addr = instruction.get_absolute_arg();
+    let read_pointer = |least_addr: u16, most_addr: u16| read(least_addr) as u16 + read(most_addr) as u16 * 0x100;
+    let read_zero_page_pointer = |addr: u8| read_pointer(addr as u16, addr.wrapping_add(1) as u16);
+
+    match instruction.mode.as_str() {
+        "ZeroPage" => Some(first_arg as u16),
+        "ZeroPage,X" => Some(first_arg.wrapping_add(reg_x) as u16),
+        "ZeroPage,Y" => Some(first_arg.wrapping_add(reg_y) as u16),
+        "Absolute" => Some(absolute_addr),
+        "Absolute,X" => Some(absolute_addr.wrapping_add(reg_x as u16)),
+        "Absolute,Y" => Some(absolute_addr.wrapping_add(reg_y as u16)),
+        "Indirect" => {
+            // Only the 65C02 carries into the next page when reading the pointer
+            let most_addr = match variant {
+                CpuVariant::Cmos65C02 => absolute_addr.wrapping_add(1),
+                _ => (absolute_addr & 0xFF00) + (absolute_addr as u8).wrapping_add(1) as u16,
+            };
+            Some(read_pointer(absolute_addr, most_addr))
+        },
+        "(Absolute,X)" => {
+            let pointer_addr = absolute_addr.wrapping_add(reg_x as u16);
+            Some(read_pointer(pointer_addr, pointer_addr.wrapping_add(1)))
+        },
+        "(Indirect)" => Some(read_zero_page_pointer(first_arg)),
+        "(Indirect,X)" => Some(read_zero_page_pointer(first_arg.wrapping_add(reg_x))),
+        "(Indirect),Y" => Some(read_zero_page_pointer(first_arg).wrapping_add(reg_y as u16)),
+        "Relative" => instruction.get_target_addr(),
+        _ => None,
+    }
+}
+
+pub struct Disassembler {
+    variant: CpuVariant,
+    instruction_set: HashMap<u8, Instruction>,
+}
+
+impl Disassembler {
+    pub fn new(variant: CpuVariant) -> Disassembler {
+        let instruction_set = match variant {
+            CpuVariant::Cmos65C02 => get_cmos_instruction_set(),
+            _ => get_instruction_set(),
+        };
+
+        Disassembler{variant, instruction_set}
+    }
+
+    pub fn disassemble<F: Fn(u16) -> u8>(&self, addr: u16, read: F) -> DisassembledInstruction {
+        disassemble_instruction(&self.instruction_set, addr, read)
+    }
+
+    pub fn get_effective_addr<F: Fn(u16) -> u8>(&self, instruction: &DisassembledInstruction, reg_x: u8, reg_y: u8,
+        read: F) -> Option<u16> {
+        get_effective_addr(instruction, self.variant, reg_x, reg_y, read)
+    }
+
+    // Linear sweep of a whole prg bank mapped at base_addr
+    pub fn disassemble_bank(&self, content: &[u8], base_addr: u16) -> Vec<DisassembledInstruction> {
+        let read = |addr: u16| content.get((addr as usize).wrapping_sub(base_addr as usize)).copied().unwrap_or(0);
+
+        let mut instructions = Vec::<DisassembledInstruction>::new();
+        let mut offset = 0;
+        while offset < content.len() {
+            let addr = base_addr.wrapping_add(offset as u16);
+            let mut instruction = self.disassemble(addr, read);
+
+            // The operand would go past the end of the bank
+            if offset + instruction.bytes.len() > content.len() {
+                instruction = get_data_instruction(addr, content[offset]);
+            }
+
+            offset += instruction.bytes.len();
+            instructions.push(instruction);
+        }
+
+        instructions
+    }
+
+    // Disassembles a prg bank to text, jump and branch targets inside the bank get labels
+    pub fn disassemble_bank_to_text(&self, content: &[u8], base_addr: u16) -> String {
+        let instructions = self.disassemble_bank(content, base_addr);
+        let instruction_addrs: HashSet<u16> = instructions.iter().map(|instruction| instruction.addr).collect();
+        let bank_end = base_addr as usize + content.len();
+
+        let mut labels = BTreeMap::<u16, String>::new();
+        for target_addr in instructions.iter().filter_map(|instruction| instruction.get_target_addr()) {
+            if instruction_addrs.contains(&target_addr) {
+                labels.insert(target_addr, get_label(target_addr));
+            }
+        }
+
+        // The last bank holds the interrupt vectors
+        if bank_end == consts::MEMORY_SIZE {
+            // A bank smaller than the vectors can start after the first ones
+            let read_vector = |addr: u16| {
+                let offset = addr.checked_sub(base_addr)? as usize;
+                Some(content[offset] as u16 + content[offset + 1] as u16 * 0x100)
+            };
+
+            let vectors = [("NMI", consts::NMI_VECTOR_ADDR), ("RESET", consts::RESET_VECTOR_ADDR), ("IRQ", consts::IRQ_VECTOR_ADDR)];
+            for (name, vector_addr) in vectors.iter() {
+                if let Some(target_addr) = read_vector(*vector_addr).filter(|target_addr| instruction_addrs.contains(target_addr)) {
+                    labels.insert(target_addr, name.to_string());
+                }
+            }
+        }
+
+        let mut text = String::new();
+        for instruction in &instructions {
+            if let Some(label) = labels.get(&instruction.addr) {
+                text += &format!("{}:\n", label);
+            }
+
+            let instruction_text = match instruction.get_target_addr().and_then(|target_addr| labels.get(&target_addr)) {
+                Some(label) => format!("{} {}", instruction.name, label),
+                None => instruction.to_string(),
+            };
+
+            let bytes: Vec<String> = instruction.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+            text += &format!("    {:04X}  {:<8}  {}\n", instruction.addr, bytes.join(" "), instruction_text);
+        }
+
+        text
+    }
+}
+
+#[test]
+fn disassemble_operands() {
+    let disassembler = Disassembler::new(CpuVariant::Ricoh2A03);
+    let memory = [0xB1, 0x44, 0xD0, 0xFE, 0x6C, 0xFF, 0x02, 0x0A, 0x04, 0x10, 0x02];
+    let read = |addr: u16| memory.get((addr - 0xC000) as usize).copied().unwrap_or(0);
+
+    let lines: Vec<String> = [0xC000, 0xC002, 0xC004, 0xC007, 0xC008, 0xC00A].iter()
+        .map(|addr| disassembler.disassemble(*addr, read).to_string()).collect();
+    assert_eq!(lines, vec!["LDA ($44),Y", "BNE $C002", "JMP ($02FF)", "ASL A", "NOP $10", ".db $02"]);
+}
+
+#[test]
+fn disassemble_effective_addr() {
+    let mut memory = vec![0x00u8; consts::MEMORY_SIZE];
+    memory[0x44] = 0x00;
+    memory[0x45] = 0x06;
+    memory[0x02FF] = 0x34;
+    memory[0x0200] = 0x12;
+    memory[0x0300] = 0x56;
+    let read = |addr: u16| memory[addr as usize];
+
+    let indirect_y = DisassembledInstruction{addr: 0x8000, bytes: vec![0xB1, 0x44], name: String::from("LDA"),
+        mode: String::from("(Indirect),Y"), operand: String::from("($44),Y")};
+    assert_eq!(get_effective_addr(&indirect_y, CpuVariant::Ricoh2A03, 0x00, 0x34, read), Some(0x0634));
+
+    let jmp_indirect = DisassembledInstruction{addr: 0x8000, bytes: vec![0x6C, 0xFF, 0x02], name: String::from("JMP"),
+        mode: String::from("Indirect"), operand: String::from("($02FF)")};
+    assert_eq!(get_effective_addr(&jmp_indirect, CpuVariant::Ricoh2A03, 0x00, 0x00, read), Some(0x1234));
+    assert_eq!(get_effective_addr(&jmp_indirect, CpuVariant::Cmos65C02, 0x00, 0x00, read), Some(0x5634));
+}
+
+#[test]
+fn disassemble_bank_labels() {
+    // SEI ; JSR $C005 ; LDX #$00 ; DEX ; BNE $C007 ; RTS, with the reset vector pointing at $C000
+    let mut bank = vec![0x78, 0x20, 0x05, 0xC0, 0xEA, 0xA2, 0x00, 0xCA, 0xD0, 0xFD, 0x60];
+    bank.resize(0x4000, 0xEA);
+    bank[0x3FFC] = 0x00;
+    bank[0x3FFD] = 0xC0;
+
+    let text = Disassembler::new(CpuVariant::Ricoh2A03).disassemble_bank_to_text(&bank, 0xC000);
+    let lines: Vec<&str> = text.lines().take(9).collect();
+    assert_eq!(lines, vec![
+        "RESET:",
+        "    C000  78        SEI",
+        "    C001  20 05 C0  JSR L_C005",
+        "    C004  EA        NOP",
+        "L_C005:",
+        "    C005  A2 00     LDX #$00",
+        "L_C007:",
+        "    C007  CA        DEX",
+        "    C008  D0 FD     BNE L_C007",
+    ]);
+}
+
+#[test]
+fn disassemble_bank_after_vectors() {
+    // Only the reset and irq vectors, both pointing at the start of the bank, the last label wins
+    let bank = [0xFC, 0xFF, 0xFC, 0xFF];
+    let text = Disassembler::new(CpuVariant::Ricoh2A03).disassemble_bank_to_text(&bank, 0xFFFC);
+    assert!(text.starts_with("IRQ:\n    FFFC"), "{}", text);
+}
@@ -0,0 +1,3 @@
+pub mod disassembler;
+
+pub use disassembler::{Disassembler, DisassembledInstruction, disassemble_instruction};
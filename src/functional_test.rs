@@ -0,0 +1,91 @@
+// Runs Klaus Dormann's 6502 functional test on a flat 64KB memory, without any nes hardware
+// Test source : https://github.com/Klaus2m5/6502_65C02_functional_tests
+//
+// The binary is not shipped with the repo, place 6502_functional_test.bin (assembled with the default
+// configuration) in samples or point NESSY_FUNCTIONAL_TEST_BIN to it, then run `cargo test -- --ignored`.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+use crate::core::Double;
+use crate::cpu::CpuVariant;
+use crate::cpu::cpu::Cpu;
+use crate::mapper::DebugMapper;
+
+const FUNCTIONAL_TEST_BIN_ENV: &str = "NESSY_FUNCTIONAL_TEST_BIN";
+
+const FUNCTIONAL_TEST_START_ADDR: u16 = 0x0400;
+const FUNCTIONAL_TEST_SUCCESS_ADDR: u16 = 0x3469;
+
+// The test keeps the number of the running test here
+const FUNCTIONAL_TEST_CASE_ADDR: u16 = 0x0200;
+
+const FUNCTIONAL_TEST_MAX_INSTRUCTIONS: usize = 100_000_000;
+
+#[derive(Debug, PartialEq)]
+pub enum FunctionalTestResult {
+    Success,
+    // The program counter looped on itself somewhere other than the success address
+    Trap{program_counter: u16, test_case: u8},
+    CpuError{program_counter: u16, test_case: u8, error: String},
+    Timeout{program_counter: u16, test_case: u8},
+}
+
+pub fn run_functional_test(image: &[u8], start_addr: u16, success_addr: u16) -> FunctionalTestResult {
+    let mut mapper = DebugMapper::new();
+    mapper.load(0x0000, image);
+
+    // The default configuration of the test checks decimal mode too
+    let mut cpu = Cpu::new_with_variant(mapper, Double::from(start_addr), CpuVariant::Nmos6502);
+    let get_test_case = |cpu: &Cpu<DebugMapper>| cpu.get_memory_addr(Double::from(FUNCTIONAL_TEST_CASE_ADDR)).get_value();
+
+    for _ in 0..FUNCTIONAL_TEST_MAX_INSTRUCTIONS {
+        let program_counter = cpu.get_program_counter().get_value();
+
+        if let Err(err) = cpu.execute_instruction() {
+            return FunctionalTestResult::CpuError{program_counter, test_case: get_test_case(&cpu), error: format!("{:?}", err)};
+        }
+
+        if cpu.get_program_counter().get_value() == program_counter {
+            if program_counter == success_addr {
+                return FunctionalTestResult::Success;
+            }
+
+            return FunctionalTestResult::Trap{program_counter, test_case: get_test_case(&cpu)};
+        }
+    }
+
+    FunctionalTestResult::Timeout{program_counter: cpu.get_program_counter().get_value(), test_case: get_test_case(&cpu)}
+}
+
+#[test]
+fn functional_test_trap_detection() {
+    // LDA #$2A ; STA $0200 ; JMP $0405
+    let program = [0xA9, 0x2A, 0x8D, 0x00, 0x02, 0x4C, 0x05, 0x04];
+    let mut image = vec![0x00u8; 0x10000];
+    image[0x0400..0x0400 + program.len()].copy_from_slice(&program);
+
+    assert_eq!(run_functional_test(&image, 0x0400, 0x0405), FunctionalTestResult::Success);
+    assert_eq!(run_functional_test(&image, 0x0400, FUNCTIONAL_TEST_SUCCESS_ADDR),
+        FunctionalTestResult::Trap{program_counter: 0x0405, test_case: 0x2A});
+}
+
+#[test]
+#[ignore = "needs 6502_functional_test.bin"]
+fn functional_test() {
+    let bin_path = match std::env::var(FUNCTIONAL_TEST_BIN_ENV) {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => PathBuf::from("samples").join("6502_functional_test.bin"),
+    };
+
+    if !bin_path.is_file() {
+        panic!("Functional test not found at {:?} (set {})", bin_path, FUNCTIONAL_TEST_BIN_ENV);
+    }
+
+    let mut image = Vec::<u8>::new();
+    File::open(bin_path).unwrap().read_to_end(&mut image).unwrap();
+
+    let result = run_functional_test(&image, FUNCTIONAL_TEST_START_ADDR, FUNCTIONAL_TEST_SUCCESS_ADDR);
+    assert_eq!(result, FunctionalTestResult::Success);
+}
@@ -0,0 +1,170 @@
+use super::{EnvironmentState, Observation, ObservationMode, StepInfo};
+
+use crate::core::consts;
+use crate::movie;
+use crate::nes::{Nes, NesError};
+
+// A headless reinforcement learning environment, actions are the buttons of the first controller.
+// Nothing in the emulator is random, the same actions from the same state always give the same observations.
+pub struct Environment {
+    nes: Nes,
+    // The state at power on, reset goes back to it
+    initial_state: Vec<u8>,
+    frame: usize,
+    frame_skip: usize,
+    downsample: usize,
+    observation_mode: ObservationMode,
+}
+
+impl Environment {
+    pub fn new(rom: &[u8]) -> Result<Environment, NesError> {
+        let nes = Nes::new(rom)?;
+        let initial_state = nes.save_state();
+
+        Ok(Environment{nes, initial_state, frame: 0, frame_skip: 1, downsample: 1, observation_mode: ObservationMode::Rgb})
+    }
+
+    pub fn get_nes(&self) -> &Nes {
+        &self.nes
+    }
+
+    // Frames each step runs with the same action, at least 1
+    pub fn set_frame_skip(&mut self, frame_skip: usize) {
+        self.frame_skip = frame_skip.max(1);
+    }
+
+    // Screen observations average blocks of downsample x downsample pixels, at least 1
+    pub fn set_downsample(&mut self, downsample: usize) {
+        self.downsample = downsample.max(1);
+    }
+
+    pub fn set_observation_mode(&mut self, observation_mode: ObservationMode) {
+        self.observation_mode = observation_mode;
+    }
+
+    pub fn reset(&mut self) -> Result<Observation, NesError> {
+        self.nes.load_state(&self.initial_state)?;
+        self.frame = 0;
+
+        Ok(self.get_observation())
+    }
+
+    // Holds the buttons, a mask of the consts::BUTTON_ values, for frame_skip frames
+    pub fn step(&mut self, action: u8) -> Result<(Observation, StepInfo), NesError> {
+        self.nes.set_buttons(0, action);
+        for _ in 0..self.frame_skip {
+            self.nes.run_frame()?;
+            self.frame += 1;
+        }
+
+        let info = StepInfo{frame: self.frame, ram_hash: movie::get_ram_hash(self.nes.get_cpu())};
+        Ok((self.get_observation(), info))
+    }
+
+    pub fn clone_state(&self) -> EnvironmentState {
+        EnvironmentState{state: self.nes.save_state(), frame: self.frame}
+    }
+
+    pub fn restore_state(&mut self, state: &EnvironmentState) -> Result<(), NesError> {
+        self.nes.load_state(&state.state)?;
+        self.frame = state.frame;
+
+        Ok(())
+    }
+
+    pub fn get_observation(&self) -> Observation {
+        match self.observation_mode {
+            ObservationMode::Ram => {
+                let data: Vec<u8> = (0..consts::INTERNAL_RAM_SIZE).map(|addr| self.nes.peek_memory(addr)).collect();
+                Observation{width: data.len(), height: 1, channels: 1, data}
+            },
+            ObservationMode::Rgb => self.get_screen_observation(3),
+            ObservationMode::Greyscale => self.get_screen_observation(1),
+        }
+    }
+
+    fn get_screen_observation(&self, channels: usize) -> Observation {
+        let framebuffer = self.nes.get_framebuffer();
+        let width = consts::FRAME_WIDTH / self.downsample;
+        let height = consts::FRAME_HEIGHT / self.downsample;
+        let block_size = (self.downsample * self.downsample) as u32;
+
+        let mut data = Vec::<u8>::with_capacity(width * height * channels);
+        for y in 0..height {
+            for x in 0..width {
+                // Sums the RGB of the block
+                let mut sum = [0u32; 3];
+                for block_y in y * self.downsample..(y + 1) * self.downsample {
+                    for block_x in x * self.downsample..(x + 1) * self.downsample {
+                        let pixel = (block_y * consts::FRAME_WIDTH + block_x) * consts::FRAME_BYTES_PER_PIXEL;
+                        for (channel, value) in sum.iter_mut().enumerate() {
+                            *value += framebuffer[pixel + channel] as u32;
+                        }
+                    }
+                }
+
+                let [red, green, blue] = sum.map(|value| value / block_size);
+                if channels == 1 {
+                    // ITU-R 601 luma in fixed point
+                    data.push(((red * 299 + green * 587 + blue * 114) / 1000) as u8);
+                } else {
+                    data.extend_from_slice(&[red as u8, green as u8, blue as u8]);
+                }
+            }
+        }
+
+        Observation{width, height, channels, data}
+    }
+}
+
+#[test]
+fn environment_step_and_reset() {
+    let mut env = Environment::new(&crate::nes::console::get_test_rom()).unwrap();
+    env.set_frame_skip(4);
+    env.set_downsample(2);
+
+    let observation = env.reset().unwrap();
+    assert_eq!((observation.width, observation.height, observation.channels), (128, 120, 3));
+    assert_eq!(observation.data.len(), 128 * 120 * 3);
+
+    let (_, info) = env.step(consts::BUTTON_A).unwrap();
+    assert_eq!(info.frame, 4);
+    assert_eq!(env.get_nes().get_frame_counter(), 4);
+
+    env.set_observation_mode(ObservationMode::Greyscale);
+    let observation = env.get_observation();
+    assert_eq!((observation.width, observation.height, observation.channels), (128, 120, 1));
+
+    env.set_observation_mode(ObservationMode::Ram);
+    let observation = env.get_observation();
+    assert_eq!(observation.data.len(), consts::INTERNAL_RAM_SIZE as usize);
+    assert_eq!(observation.data[0x10], env.get_nes().peek_memory(0x10));
+
+    let observation = env.reset().unwrap();
+    assert_eq!(observation.data, vec![0u8; consts::INTERNAL_RAM_SIZE as usize]);
+}
+
+#[test]
+fn environment_is_deterministic() {
+    let rom = crate::nes::console::get_test_rom();
+    let mut env = Environment::new(&rom).unwrap();
+    env.set_observation_mode(ObservationMode::Ram);
+    env.reset().unwrap();
+
+    let actions = [0, consts::BUTTON_A, consts::BUTTON_RIGHT, 0, consts::BUTTON_START];
+    let run = |env: &mut Environment| -> Vec<(Observation, StepInfo)> {
+        actions.iter().map(|action| env.step(*action).unwrap()).collect()
+    };
+
+    let snapshot = env.clone_state();
+    let first_run = run(&mut env);
+
+    // From the snapshot, then in a new environment
+    env.restore_state(&snapshot).unwrap();
+    assert_eq!(run(&mut env), first_run);
+
+    let mut other = Environment::new(&rom).unwrap();
+    other.set_observation_mode(ObservationMode::Ram);
+    other.reset().unwrap();
+    assert_eq!(run(&mut other), first_run);
+}
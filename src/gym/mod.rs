@@ -0,0 +1,37 @@
+pub mod environment;
+
+pub use environment::Environment;
+
+// What step and reset return
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObservationMode {
+    // The framebuffer as RGB, 3 bytes per pixel
+    Rgb,
+    // One luminance byte per pixel
+    Greyscale,
+    // The 2KB of internal ram, not downsampled
+    Ram,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    // Row by row, channels interleaved
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepInfo {
+    // Frames since reset
+    pub frame: usize,
+    pub ram_hash: u64,
+}
+
+// A snapshot of the environment for search, restoring it replays the same way
+#[derive(Debug, Clone)]
+pub struct EnvironmentState {
+    state: Vec<u8>,
+    frame: usize,
+}
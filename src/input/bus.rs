@@ -0,0 +1,97 @@
+use super::Controller;
+
+use crate::core::Byte;
+use crate::core::Double;
+use crate::core::consts;
+use crate::cpu::CpuBus;
+use crate::savestate::{SaveStateError, StateReader, StateWriter};
+
+// Wraps the cartridge bus with the two controller ports, the rest of the memory map goes to the wrapped bus.
+// $4017 writes belong to the apu frame counter and are passed on.
+pub struct InputBus<B: CpuBus> {
+    bus: B,
+    controllers: [Controller; 2],
+}
+
+impl<B: CpuBus> InputBus<B> {
+    pub fn new(bus: B) -> InputBus<B> {
+        InputBus{bus, controllers: [Controller::new(), Controller::new()]}
+    }
+
+    pub fn get_bus(&self) -> &B {
+        &self.bus
+    }
+
+    pub fn get_bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
+
+    // Port 0 is read at $4016, port 1 at $4017
+    pub fn get_controller(&self, port: usize) -> &Controller {
+        &self.controllers[port]
+    }
+
+    pub fn get_controller_mut(&mut self, port: usize) -> &mut Controller {
+        &mut self.controllers[port]
+    }
+}
+
+impl<B: CpuBus> CpuBus for InputBus<B> {
+    fn read(&self, addr: Double) -> Byte {
+        match addr.get_value() {
+            consts::CONTROLLER_1_ADDR => Byte::new(consts::CONTROLLER_OPEN_BUS | self.controllers[0].read()),
+            consts::CONTROLLER_2_ADDR => Byte::new(consts::CONTROLLER_OPEN_BUS | self.controllers[1].read()),
+            _ => self.bus.read(addr),
+        }
+    }
+
+    fn write(&mut self, addr: Double, value: Byte) {
+        match addr.get_value() {
+            // The strobe goes to both ports
+            consts::CONTROLLER_1_ADDR => {
+                for controller in self.controllers.iter_mut() {
+                    controller.write_strobe(value.get_value());
+                }
+            },
+            _ => self.bus.write(addr, value),
+        }
+    }
+
+    fn peek(&self, addr: Double) -> Byte {
+        match addr.get_value() {
+            consts::CONTROLLER_1_ADDR => Byte::new(consts::CONTROLLER_OPEN_BUS | self.controllers[0].peek()),
+            consts::CONTROLLER_2_ADDR => Byte::new(consts::CONTROLLER_OPEN_BUS | self.controllers[1].peek()),
+            _ => self.bus.peek(addr),
+        }
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        self.bus.tick(cycles);
+    }
+
+    fn get_prg_bank(&self, addr: Double) -> Option<usize> {
+        self.bus.get_prg_bank(addr)
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.bus.save_state(state);
+
+        for controller in self.controllers.iter() {
+            let (buttons, strobe, shift_register) = controller.get_state();
+            state.write_u8(buttons);
+            state.write_u8(strobe as u8);
+            state.write_u8(shift_register);
+        }
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.bus.load_state(state)?;
+
+        for controller in self.controllers.iter_mut() {
+            let (buttons, strobe, shift_register) = (state.read_u8()?, state.read_u8()?, state.read_u8()?);
+            controller.set_state(buttons, strobe != 0, shift_register);
+        }
+
+        Ok(())
+    }
+}
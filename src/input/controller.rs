@@ -0,0 +1,90 @@
+use std::cell::Cell;
+
+// The standard controller, implemented by the docs at : https://wiki.nesdev.com/w/index.php/Standard_controller
+// Writing 1 to the strobe reloads the shift register with the buttons, each read then shifts one button out.
+pub struct Controller {
+    buttons: u8,
+    strobe: bool,
+    // Reads shift it from behind a shared reference, like the bus reads
+    shift_register: Cell<u8>,
+}
+
+impl Default for Controller {
+    fn default() -> Controller {
+        Controller::new()
+    }
+}
+
+impl Controller {
+    pub fn new() -> Controller {
+        Controller{buttons: 0, strobe: false, shift_register: Cell::new(0)}
+    }
+
+    // The pressed buttons, a mask of the consts::BUTTON_ values
+    pub fn get_buttons(&self) -> u8 {
+        self.buttons
+    }
+
+    pub fn set_buttons(&mut self, buttons: u8) {
+        self.buttons = buttons;
+        if self.strobe {
+            self.shift_register.set(buttons);
+        }
+    }
+
+    pub fn write_strobe(&mut self, value: u8) {
+        self.strobe = value & 0x01 != 0;
+        self.shift_register.set(self.buttons);
+    }
+
+    // The next button bit, 1 once all 8 were read
+    pub fn read(&self) -> u8 {
+        let bit = self.peek();
+        if !self.strobe {
+            self.shift_register.set((self.shift_register.get() >> 1) | 0x80);
+        }
+
+        bit
+    }
+
+    pub fn peek(&self) -> u8 {
+        if self.strobe {
+            self.buttons & 0x01
+        } else {
+            self.shift_register.get() & 0x01
+        }
+    }
+
+    pub fn get_state(&self) -> (u8, bool, u8) {
+        (self.buttons, self.strobe, self.shift_register.get())
+    }
+
+    pub fn set_state(&mut self, buttons: u8, strobe: bool, shift_register: u8) {
+        self.buttons = buttons;
+        self.strobe = strobe;
+        self.shift_register.set(shift_register);
+    }
+}
+
+#[test]
+fn controller_shifts_buttons() {
+    use crate::core::consts;
+
+    let mut controller = Controller::new();
+    controller.set_buttons(consts::BUTTON_A | consts::BUTTON_START | consts::BUTTON_RIGHT);
+
+    // Reads while strobing keep returning A
+    controller.write_strobe(1);
+    assert_eq!([controller.read(), controller.read()], [1, 1]);
+
+    controller.write_strobe(0);
+    let bits: Vec<u8> = (0..10).map(|_| controller.read()).collect();
+    assert_eq!(bits, vec![1, 0, 0, 1, 0, 0, 0, 1, 1, 1]);
+
+    // Buttons changed after the strobe wait for the next one
+    controller.set_buttons(consts::BUTTON_B);
+    assert_eq!(controller.peek(), 1);
+    controller.write_strobe(1);
+    controller.write_strobe(0);
+    assert_eq!([controller.read(), controller.read()], [0, 1]);
+}
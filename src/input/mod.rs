@@ -0,0 +1,5 @@
+pub mod controller;
+pub mod bus;
+
+pub use controller::Controller;
+pub use bus::InputBus;
@@ -0,0 +1,26 @@
+pub mod core;
+pub mod rom_parser;
+pub mod cpu;
+pub mod mapper;
+pub mod disasm;
+// The gdb stub needs sockets and threads and the benchmark a clock, neither exists in the browser
+#[cfg(not(target_arch = "wasm32"))] pub mod debugger;
+pub mod tracer;
+pub mod savestate;
+pub mod input;
+pub mod movie;
+#[cfg(not(target_arch = "wasm32"))] pub mod benchmark;
+pub mod nes;
+pub mod gym;
+pub mod capi;
+#[cfg(any(target_arch = "wasm32", test))] pub mod wasm;
+#[cfg(feature = "libretro")] pub mod libretro;
+#[cfg(feature = "desktop")] pub mod desktop;
+#[cfg(test)] mod nestest;
+#[cfg(test)] mod processor_tests;
+#[cfg(test)] mod blargg;
+#[cfg(test)] mod functional_test;
+
+#[macro_use] extern crate log;
+
+pub use nes::{Nes, NesError};
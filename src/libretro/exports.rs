@@ -0,0 +1,590 @@
+// Libretro gives no instance handle, the loaded game and the callbacks live in a per thread core.
+// Frontends call every retro_ function from the same thread.
+
+use super::*;
+
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+use std::slice;
+
+use crate::core::consts;
+use crate::nes::{Nes, Region};
+
+const OPTION_REGION: &[u8] = b"nessy_region\0";
+const OPTION_PALETTE: &[u8] = b"nessy_palette\0";
+
+// Libretro pad ids to nes buttons
+const BUTTON_MAPPING: [(u32, u8); 8] = [
+    (RETRO_DEVICE_ID_JOYPAD_A, consts::BUTTON_A),
+    (RETRO_DEVICE_ID_JOYPAD_B, consts::BUTTON_B),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, consts::BUTTON_SELECT),
+    (RETRO_DEVICE_ID_JOYPAD_START, consts::BUTTON_START),
+    (RETRO_DEVICE_ID_JOYPAD_UP, consts::BUTTON_UP),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, consts::BUTTON_DOWN),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, consts::BUTTON_LEFT),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, consts::BUTTON_RIGHT),
+];
+
+struct Core {
+    environment: Option<RetroEnvironment>,
+    video_refresh: Option<RetroVideoRefresh>,
+    audio_sample_batch: Option<RetroAudioSampleBatch>,
+    input_poll: Option<RetroInputPoll>,
+    input_state: Option<RetroInputState>,
+    nes: Option<Nes>,
+    // From the rom header, used when the region option is Auto
+    rom_region: Region,
+    greyscale: bool,
+    video: Vec<u32>,
+    audio: Vec<i16>,
+}
+
+impl Core {
+    fn new() -> Core {
+        Core{environment: None, video_refresh: None, audio_sample_batch: None, input_poll: None, input_state: None,
+            nes: None, rom_region: Region::Ntsc, greyscale: false,
+            video: vec![0; consts::FRAME_WIDTH * consts::FRAME_HEIGHT], audio: Vec::new()}
+    }
+
+    // RGBA to XRGB8888, the buffer is taken out of the core while the frontend has it
+    fn take_video(&mut self) -> Option<(RetroVideoRefresh, Vec<u32>)> {
+        let (video_refresh, nes) = match (self.video_refresh, &self.nes) {
+            (Some(video_refresh), Some(nes)) => (video_refresh, nes),
+            _ => return None,
+        };
+
+        let greyscale = self.greyscale;
+        for (pixel, rgba) in self.video.iter_mut().zip(nes.get_framebuffer().chunks_exact(consts::FRAME_BYTES_PER_PIXEL)) {
+            let (mut r, mut g, mut b) = (rgba[0] as u32, rgba[1] as u32, rgba[2] as u32);
+            if greyscale {
+                let luma = (r * 299 + g * 587 + b * 114) / 1000;
+                r = luma;
+                g = luma;
+                b = luma;
+            }
+            *pixel = r << 16 | g << 8 | b;
+        }
+
+        Some((video_refresh, std::mem::take(&mut self.video)))
+    }
+
+    // The mono samples are duplicated to both channels
+    fn take_audio(&mut self) -> Option<(RetroAudioSampleBatch, Vec<i16>)> {
+        let (audio_sample_batch, nes) = match (self.audio_sample_batch, &self.nes) {
+            (Some(audio_sample_batch), Some(nes)) => (audio_sample_batch, nes),
+            _ => return None,
+        };
+
+        self.audio.clear();
+        for sample in nes.get_audio_samples() {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.audio.extend_from_slice(&[sample, sample]);
+        }
+
+        Some((audio_sample_batch, std::mem::take(&mut self.audio)))
+    }
+}
+
+thread_local! {
+    static CORE: RefCell<Core> = RefCell::new(Core::new());
+}
+
+fn with_core<T>(f: impl FnOnce(&mut Core) -> T) -> T {
+    CORE.with(|core| f(&mut core.borrow_mut()))
+}
+
+// The frontend may call back into any retro_ function from its callbacks, so they're only called once the core
+// isn't borrowed anymore
+fn call_environment<T>(cmd: u32, data: &mut T) -> bool {
+    match with_core(|core| core.environment) {
+        Some(environment) => environment(cmd, data as *mut T as *mut c_void),
+        None => false,
+    }
+}
+
+fn get_variable(key: &'static [u8]) -> Option<String> {
+    let mut variable = RetroVariable{key: key.as_ptr() as *const c_char, value: std::ptr::null()};
+    if !call_environment(RETRO_ENVIRONMENT_GET_VARIABLE, &mut variable) || variable.value.is_null() {
+        return None;
+    }
+
+    unsafe { CStr::from_ptr(variable.value) }.to_str().ok().map(String::from)
+}
+
+// Reads the core options, a region change is announced to the frontend since the frame rate changes with it
+fn apply_options() {
+    let region_option = get_variable(OPTION_REGION);
+    let greyscale = get_variable(OPTION_PALETTE).as_deref() == Some("Greyscale");
+
+    let changed_region = with_core(|core| {
+        let region = match region_option.as_deref() {
+            Some("NTSC") => Region::Ntsc,
+            Some("PAL") => Region::Pal,
+            _ => core.rom_region,
+        };
+        core.greyscale = greyscale;
+
+        match &mut core.nes {
+            Some(nes) if nes.get_region() != region => {
+                nes.set_region(region);
+                Some(region)
+            },
+            _ => None,
+        }
+    });
+    if let Some(region) = changed_region {
+        call_environment(RETRO_ENVIRONMENT_SET_SYSTEM_AV_INFO, &mut get_av_info(region));
+    }
+}
+
+// Hands the frontend direct pointers to the ram, they stay valid while the game is loaded
+fn set_memory_maps() {
+    let descriptors = with_core(|core| {
+        let nes = core.nes.as_mut()?;
+
+        let mut descriptors = Vec::new();
+        if let Some(ram) = nes.get_system_ram_mut() {
+            descriptors.push(get_memory_descriptor(RETRO_MEMDESC_SYSTEM_RAM, ram, 0x0000));
+        }
+        if let Some(ram) = nes.get_save_ram_mut() {
+            descriptors.push(get_memory_descriptor(RETRO_MEMDESC_SAVE_RAM, ram, consts::NROM_PRG_RAM_RANGE_START as usize));
+        }
+
+        Some(descriptors)
+    });
+
+    if let Some(descriptors) = descriptors {
+        let mut memory_map = RetroMemoryMap{descriptors: descriptors.as_ptr(), num_descriptors: descriptors.len() as u32};
+        call_environment(RETRO_ENVIRONMENT_SET_MEMORY_MAPS, &mut memory_map);
+    }
+}
+
+fn set_buttons() {
+    let (input_poll, input_state) = match with_core(|core| (core.input_poll, core.input_state, core.nes.is_some())) {
+        (Some(input_poll), Some(input_state), true) => (input_poll, input_state),
+        _ => return,
+    };
+
+    input_poll();
+    let buttons: Vec<u8> = (0..2).map(|port| BUTTON_MAPPING.iter()
+        .filter(|(id, _)| input_state(port, RETRO_DEVICE_JOYPAD, 0, *id) != 0)
+        .fold(0, |buttons, (_, button)| buttons | button)).collect();
+
+    with_core(|core| {
+        if let Some(nes) = &mut core.nes {
+            for (port, buttons) in buttons.into_iter().enumerate() {
+                nes.set_buttons(port, buttons);
+            }
+        }
+    });
+}
+
+fn refresh_video() {
+    if let Some((video_refresh, video)) = with_core(Core::take_video) {
+        video_refresh(video.as_ptr() as *const c_void, consts::FRAME_WIDTH as u32, consts::FRAME_HEIGHT as u32,
+            consts::FRAME_WIDTH * std::mem::size_of::<u32>());
+        with_core(|core| core.video = video);
+    }
+}
+
+fn send_audio() {
+    if let Some((audio_sample_batch, audio)) = with_core(Core::take_audio) {
+        audio_sample_batch(audio.as_ptr(), audio.len() / 2);
+        with_core(|core| core.audio = audio);
+    }
+}
+
+fn get_av_info(region: Region) -> RetroSystemAvInfo {
+    let geometry = RetroGameGeometry{base_width: consts::FRAME_WIDTH as u32, base_height: consts::FRAME_HEIGHT as u32,
+        max_width: consts::FRAME_WIDTH as u32, max_height: consts::FRAME_HEIGHT as u32, aspect_ratio: 4.0 / 3.0};
+    let timing = RetroSystemTiming{fps: region.get_frames_per_second(), sample_rate: consts::AUDIO_SAMPLE_RATE as f64};
+
+    RetroSystemAvInfo{geometry, timing}
+}
+
+fn get_memory_descriptor(flags: u64, ram: &mut [u8], start: usize) -> RetroMemoryDescriptor {
+    RetroMemoryDescriptor{flags, ptr: ram.as_mut_ptr() as *mut c_void, offset: 0, start, select: 0, disconnect: 0,
+        len: ram.len(), addrspace: std::ptr::null()}
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> u32 {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    if let Some(info) = info.as_mut() {
+        *info = RetroSystemInfo{library_name: b"nessy\0".as_ptr() as *const c_char,
+            library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+            valid_extensions: b"nes\0".as_ptr() as *const c_char, need_fullpath: false, block_extract: false};
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    let region = with_core(|core| core.nes.as_ref().map_or(Region::Ntsc, Nes::get_region));
+    if let Some(info) = info.as_mut() {
+        *info = get_av_info(region);
+    }
+}
+
+// The options are declared as soon as the frontend hands over the environment
+#[no_mangle]
+pub extern "C" fn retro_set_environment(environment: RetroEnvironment) {
+    with_core(|core| core.environment = Some(environment));
+
+    let mut variables = [
+        RetroVariable{key: OPTION_REGION.as_ptr() as *const c_char, value: b"Region; Auto|NTSC|PAL\0".as_ptr() as *const c_char},
+        RetroVariable{key: OPTION_PALETTE.as_ptr() as *const c_char, value: b"Palette; Default|Greyscale\0".as_ptr() as *const c_char},
+        RetroVariable{key: std::ptr::null(), value: std::ptr::null()},
+    ];
+    call_environment(RETRO_ENVIRONMENT_SET_VARIABLES, &mut variables);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(video_refresh: RetroVideoRefresh) {
+    with_core(|core| core.video_refresh = Some(video_refresh));
+}
+
+// Audio always goes through the batch callback
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_audio_sample: RetroAudioSample) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(audio_sample_batch: RetroAudioSampleBatch) {
+    with_core(|core| core.audio_sample_batch = Some(audio_sample_batch));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(input_poll: RetroInputPoll) {
+    with_core(|core| core.input_poll = Some(input_poll));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(input_state: RetroInputState) {
+    with_core(|core| core.input_state = Some(input_state));
+}
+
+// Both ports are standard controllers
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: u32, _device: u32) {}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    with_core(|core| *core = Core::new());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    let game = match game.as_ref() {
+        Some(game) if !game.data.is_null() => game,
+        _ => return false,
+    };
+
+    let nes = match Nes::new(slice::from_raw_parts(game.data as *const u8, game.size)) {
+        Ok(nes) => nes,
+        Err(err) => {
+            log::error!("Failed loading the game : {}", err);
+            return false;
+        },
+    };
+
+    let mut pixel_format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !call_environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, &mut pixel_format) {
+        log::error!("The frontend doesn't support XRGB8888");
+        return false;
+    }
+
+    with_core(|core| {
+        core.rom_region = nes.get_region();
+        core.nes = Some(nes);
+    });
+    apply_options();
+    set_memory_maps();
+
+    true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game_special(_game_type: u32, _info: *const RetroGameInfo, _num_info: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    with_core(|core| core.nes = None);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> u32 {
+    match with_core(|core| core.nes.as_ref().map_or(Region::Ntsc, Nes::get_region)) {
+        Region::Ntsc => RETRO_REGION_NTSC,
+        Region::Pal => RETRO_REGION_PAL,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    with_core(|core| {
+        if let Some(nes) = &mut core.nes {
+            nes.reset();
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let mut updated = false;
+    if call_environment(RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE, &mut updated) && updated {
+        apply_options();
+    }
+
+    set_buttons();
+    with_core(|core| {
+        if let Some(nes) = &mut core.nes {
+            // The frontend has no way to handle errors, the frame is still presented
+            if let Err(err) = nes.run_frame() {
+                log::error!("Failed running a frame : {}", err);
+            }
+        }
+    });
+
+    refresh_video();
+    send_audio();
+}
+
+// States have a fixed size for a given rom
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    with_core(|core| core.nes.as_ref().map_or(0, |nes| nes.save_state().len()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let state = match with_core(|core| core.nes.as_ref().map(Nes::save_state)) {
+        Some(state) if !data.is_null() && state.len() <= size => state,
+        _ => return false,
+    };
+
+    slice::from_raw_parts_mut(data as *mut u8, state.len()).copy_from_slice(&state);
+    true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+
+    let state = slice::from_raw_parts(data as *const u8, size);
+    with_core(|core| core.nes.as_mut().is_some_and(|nes| nes.load_state(state).is_ok()))
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_cheat_set(_index: u32, _enabled: bool, _code: *const c_char) {}
+
+// Null when the game has no such memory
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: u32) -> *mut c_void {
+    with_core(|core| get_memory(core, id).map_or(std::ptr::null_mut(), |ram| ram.as_mut_ptr() as *mut c_void))
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: u32) -> usize {
+    with_core(|core| get_memory(core, id).map_or(0, |ram| ram.len()))
+}
+
+fn get_memory(core: &mut Core, id: u32) -> Option<&mut [u8]> {
+    let nes = core.nes.as_mut()?;
+    match id {
+        RETRO_MEMORY_SAVE_RAM => nes.get_save_ram_mut(),
+        RETRO_MEMORY_SYSTEM_RAM => nes.get_system_ram_mut(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod stub_frontend {
+    use super::*;
+
+    // What the core handed to the stub, per test thread like the core itself
+    #[derive(Default)]
+    pub struct Frontend {
+        pub region_option: Option<&'static [u8]>,
+        pub variables_updated: bool,
+        pub declared_options: Vec<String>,
+        pub memory_map_flags: Vec<u64>,
+        pub announced_fps: Option<f64>,
+        pub announced_region: Option<u32>,
+        pub frames: usize,
+        pub frame_size: (u32, u32, usize),
+        pub audio_frames: usize,
+        pub audio_ram_size: usize,
+        pub pressed: u32,
+    }
+
+    thread_local! {
+        pub static FRONTEND: RefCell<Frontend> = RefCell::new(Frontend::default());
+    }
+
+    pub fn with_frontend<T>(f: impl FnOnce(&mut Frontend) -> T) -> T {
+        FRONTEND.with(|frontend| f(&mut frontend.borrow_mut()))
+    }
+
+    pub extern "C" fn environment(cmd: u32, data: *mut c_void) -> bool {
+        with_frontend(|frontend| unsafe {
+            match cmd {
+                RETRO_ENVIRONMENT_SET_PIXEL_FORMAT => *(data as *const u32) == RETRO_PIXEL_FORMAT_XRGB8888,
+                RETRO_ENVIRONMENT_SET_VARIABLES => {
+                    let mut variable = data as *const RetroVariable;
+                    while !(*variable).key.is_null() {
+                        frontend.declared_options.push(CStr::from_ptr((*variable).key).to_string_lossy().into_owned());
+                        variable = variable.add(1);
+                    }
+                    true
+                },
+                RETRO_ENVIRONMENT_GET_VARIABLE => {
+                    let variable = &mut *(data as *mut RetroVariable);
+                    match frontend.region_option {
+                        Some(value) if CStr::from_ptr(variable.key).to_bytes_with_nul() == OPTION_REGION => {
+                            variable.value = value.as_ptr() as *const c_char;
+                            true
+                        },
+                        _ => false,
+                    }
+                },
+                RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE => {
+                    *(data as *mut bool) = std::mem::take(&mut frontend.variables_updated);
+                    true
+                },
+                RETRO_ENVIRONMENT_SET_SYSTEM_AV_INFO => {
+                    frontend.announced_fps = Some((*(data as *const RetroSystemAvInfo)).timing.fps);
+                    frontend.announced_region = Some(retro_get_region());
+                    true
+                },
+                RETRO_ENVIRONMENT_SET_MEMORY_MAPS => {
+                    let memory_map = &*(data as *const RetroMemoryMap);
+                    let descriptors = slice::from_raw_parts(memory_map.descriptors, memory_map.num_descriptors as usize);
+                    frontend.memory_map_flags = descriptors.iter().map(|descriptor| descriptor.flags).collect();
+                    true
+                },
+                _ => false,
+            }
+        })
+    }
+
+    // Frontends are allowed to call back into the core from their callbacks
+    pub extern "C" fn video_refresh(data: *const c_void, width: u32, height: u32, pitch: usize) {
+        assert!(!data.is_null());
+        let state_size = retro_serialize_size();
+        with_frontend(|frontend| {
+            frontend.frames += 1;
+            frontend.frame_size = (width, height, pitch);
+        });
+        assert_ne!(state_size, 0);
+    }
+
+    pub extern "C" fn audio_sample_batch(_data: *const i16, frames: usize) -> usize {
+        let ram_size = retro_get_memory_size(RETRO_MEMORY_SYSTEM_RAM);
+        with_frontend(|frontend| {
+            frontend.audio_frames = frames;
+            frontend.audio_ram_size = ram_size;
+        });
+        frames
+    }
+
+    pub extern "C" fn input_poll() {}
+
+    pub extern "C" fn input_state(port: u32, device: u32, _index: u32, id: u32) -> i16 {
+        let pressed = with_frontend(|frontend| frontend.pressed);
+        (port == 0 && device == RETRO_DEVICE_JOYPAD && pressed & (1 << id) != 0) as i16
+    }
+}
+
+#[test]
+fn libretro_stub_frontend() {
+    use stub_frontend::*;
+
+    let rom = crate::nes::console::get_test_rom();
+    let game = RetroGameInfo{path: std::ptr::null(), data: rom.as_ptr() as *const c_void, size: rom.len(), meta: std::ptr::null()};
+
+    assert_eq!(retro_api_version(), RETRO_API_VERSION);
+    retro_set_environment(environment);
+    retro_set_video_refresh(video_refresh);
+    retro_set_audio_sample_batch(audio_sample_batch);
+    retro_set_input_poll(input_poll);
+    retro_set_input_state(input_state);
+    retro_init();
+    assert_eq!(with_frontend(|frontend| frontend.declared_options.clone()), ["nessy_region", "nessy_palette"]);
+
+    // A prg size the mapper can't bank fails the load instead of taking the frontend down
+    let mut bad_rom = rom[..0x10].to_vec();
+    bad_rom[4] = 0;
+    let bad_game = RetroGameInfo{path: std::ptr::null(), data: bad_rom.as_ptr() as *const c_void, size: bad_rom.len(), meta: std::ptr::null()};
+
+    unsafe {
+        assert!(!retro_load_game(std::ptr::null()));
+        assert!(!retro_load_game(&bad_game));
+        assert!(retro_get_memory_data(RETRO_MEMORY_SYSTEM_RAM).is_null());
+        assert!(retro_load_game(&game));
+    }
+    // The flag values from libretro.h, so a wrong constant can't agree with itself
+    assert_eq!(with_frontend(|frontend| frontend.memory_map_flags.clone()), [4, 8]);
+    assert_eq!(retro_get_region(), RETRO_REGION_NTSC);
+
+    let mut av_info = get_av_info(Region::Pal);
+    unsafe { retro_get_system_av_info(&mut av_info) };
+    assert!((av_info.timing.fps - 60.0988).abs() < 0.0001);
+
+    with_frontend(|frontend| frontend.pressed = 1 << RETRO_DEVICE_ID_JOYPAD_START | 1 << RETRO_DEVICE_ID_JOYPAD_A);
+    retro_run();
+    with_frontend(|frontend| {
+        assert_eq!(frontend.frames, 1);
+        assert_eq!(frontend.frame_size, (256, 240, 256 * 4));
+        assert!((730..=740).contains(&frontend.audio_frames));
+        assert_eq!(frontend.audio_ram_size, consts::INTERNAL_RAM_SIZE as usize);
+    });
+    with_core(|core| assert_eq!(core.nes.as_ref().unwrap().get_buttons(0), consts::BUTTON_START | consts::BUTTON_A));
+
+    // The counter in $10 is visible through the system ram pointer
+    assert_eq!(retro_get_memory_size(RETRO_MEMORY_SYSTEM_RAM), consts::INTERNAL_RAM_SIZE as usize);
+    assert_eq!(retro_get_memory_size(RETRO_MEMORY_SAVE_RAM), 0x2000);
+    let system_ram = retro_get_memory_data(RETRO_MEMORY_SYSTEM_RAM) as *const u8;
+    let counter = unsafe { *system_ram.add(0x10) };
+    assert_ne!(counter, 0);
+
+    let mut state = vec![0u8; retro_serialize_size()];
+    unsafe {
+        assert!(!retro_serialize(state.as_mut_ptr() as *mut c_void, state.len() - 1));
+        assert!(retro_serialize(state.as_mut_ptr() as *mut c_void, state.len()));
+        retro_run();
+        assert_ne!(*system_ram.add(0x10), counter);
+
+        // Loading copies into the same ram so the pointer handed out earlier still sees it
+        assert!(retro_unserialize(state.as_ptr() as *const c_void, state.len()));
+        assert_eq!(*system_ram.add(0x10), counter);
+        assert!(!retro_unserialize(state.as_ptr() as *const c_void, 4));
+    }
+
+    // Switching the region option is announced with the new frame rate
+    with_frontend(|frontend| {
+        frontend.region_option = Some(b"PAL\0");
+        frontend.variables_updated = true;
+    });
+    retro_run();
+    assert_eq!(retro_get_region(), RETRO_REGION_PAL);
+    assert!((with_frontend(|frontend| frontend.announced_fps.unwrap()) - 50.0070).abs() < 0.0001);
+    assert_eq!(with_frontend(|frontend| frontend.announced_region), Some(RETRO_REGION_PAL));
+
+    retro_unload_game();
+    assert!(retro_get_memory_data(RETRO_MEMORY_SYSTEM_RAM).is_null());
+    retro_deinit();
+}
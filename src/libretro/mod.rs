@@ -0,0 +1,108 @@
+// A libretro core, the frontend loads the cdylib and drives the emulator through the retro_ functions.
+// The types and values below mirror libretro.h, only the parts the core uses are declared.
+#[allow(clippy::missing_safety_doc)]
+pub mod exports;
+
+use std::os::raw::{c_char, c_void};
+
+pub const RETRO_API_VERSION: u32 = 1;
+
+pub const RETRO_DEVICE_JOYPAD: u32 = 1;
+
+// Joypad ids, laid out like a snes pad
+pub const RETRO_DEVICE_ID_JOYPAD_B: u32 = 0;
+pub const RETRO_DEVICE_ID_JOYPAD_SELECT: u32 = 2;
+pub const RETRO_DEVICE_ID_JOYPAD_START: u32 = 3;
+pub const RETRO_DEVICE_ID_JOYPAD_UP: u32 = 4;
+pub const RETRO_DEVICE_ID_JOYPAD_DOWN: u32 = 5;
+pub const RETRO_DEVICE_ID_JOYPAD_LEFT: u32 = 6;
+pub const RETRO_DEVICE_ID_JOYPAD_RIGHT: u32 = 7;
+pub const RETRO_DEVICE_ID_JOYPAD_A: u32 = 8;
+
+pub const RETRO_REGION_NTSC: u32 = 0;
+pub const RETRO_REGION_PAL: u32 = 1;
+
+pub const RETRO_MEMORY_SAVE_RAM: u32 = 0;
+pub const RETRO_MEMORY_SYSTEM_RAM: u32 = 2;
+
+pub const RETRO_MEMDESC_SAVE_RAM: u64 = 1 << 3;
+pub const RETRO_MEMDESC_SYSTEM_RAM: u64 = 1 << 2;
+
+pub const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: u32 = 10;
+pub const RETRO_ENVIRONMENT_GET_VARIABLE: u32 = 15;
+pub const RETRO_ENVIRONMENT_SET_VARIABLES: u32 = 16;
+pub const RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE: u32 = 17;
+pub const RETRO_ENVIRONMENT_SET_SYSTEM_AV_INFO: u32 = 32;
+pub const RETRO_ENVIRONMENT_SET_MEMORY_MAPS: u32 = 36 | 0x10000;
+
+pub const RETRO_PIXEL_FORMAT_XRGB8888: u32 = 1;
+
+pub type RetroEnvironment = extern "C" fn(cmd: u32, data: *mut c_void) -> bool;
+pub type RetroVideoRefresh = extern "C" fn(data: *const c_void, width: u32, height: u32, pitch: usize);
+pub type RetroAudioSample = extern "C" fn(left: i16, right: i16);
+pub type RetroAudioSampleBatch = extern "C" fn(data: *const i16, frames: usize) -> usize;
+pub type RetroInputPoll = extern "C" fn();
+pub type RetroInputState = extern "C" fn(port: u32, device: u32, index: u32, id: u32) -> i16;
+
+#[repr(C)]
+pub struct RetroSystemInfo {
+    pub library_name: *const c_char,
+    pub library_version: *const c_char,
+    pub valid_extensions: *const c_char,
+    pub need_fullpath: bool,
+    pub block_extract: bool,
+}
+
+#[repr(C)]
+pub struct RetroGameGeometry {
+    pub base_width: u32,
+    pub base_height: u32,
+    pub max_width: u32,
+    pub max_height: u32,
+    pub aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct RetroSystemTiming {
+    pub fps: f64,
+    pub sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct RetroSystemAvInfo {
+    pub geometry: RetroGameGeometry,
+    pub timing: RetroSystemTiming,
+}
+
+#[repr(C)]
+pub struct RetroGameInfo {
+    pub path: *const c_char,
+    pub data: *const c_void,
+    pub size: usize,
+    pub meta: *const c_char,
+}
+
+// A core option, the frontend answers GET_VARIABLE with the chosen value
+#[repr(C)]
+pub struct RetroVariable {
+    pub key: *const c_char,
+    pub value: *const c_char,
+}
+
+#[repr(C)]
+pub struct RetroMemoryDescriptor {
+    pub flags: u64,
+    pub ptr: *mut c_void,
+    pub offset: usize,
+    pub start: usize,
+    pub select: usize,
+    pub disconnect: usize,
+    pub len: usize,
+    pub addrspace: *const c_char,
+}
+
+#[repr(C)]
+pub struct RetroMemoryMap {
+    pub descriptors: *const RetroMemoryDescriptor,
+    pub num_descriptors: u32,
+}
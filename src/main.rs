@@ -1,20 +1,307 @@
-mod core;
-mod rom_parser;
-mod cpu;
-mod mapper;
-mod nestest;
-
 #[macro_use] extern crate log;
 
-use simplelog::{ConfigBuilder, Level, CombinedLogger, TermLogger, WriteLogger, LevelFilter, TerminalMode, Color};
+use simplelog::{ConfigBuilder, Level, CombinedLogger, SharedLogger, TermLogger, WriteLogger, LevelFilter, TerminalMode, Color};
 
 use std::fs::File;
 use std::io::Read;
 
-use crate::rom_parser::ines::InesRom;
-use crate::cpu::cpu::Cpu;
+use nessy::Nes;
+use nessy::benchmark::{self, BenchmarkResult};
+use nessy::core::consts;
+use nessy::cpu::CpuVariant;
+use nessy::cpu::cpu::Cpu;
+use nessy::debugger::{GdbStub, Monitor, WatchedBus};
+use nessy::disasm::Disassembler;
+use nessy::movie::{self, Movie};
+use nessy::nes::Region;
+use nessy::rom_parser::ines::InesRom;
+use nessy::tracer::{TraceFormat, TraceOutput, TraceTrigger, Tracer};
+
+// Reads a rom for the subcommands, exits with a message on failure
+fn read_rom(rom_path: &str) -> Vec<u8> {
+    let mut rom_buffer = Vec::<u8>::new();
+    if let Err(err) = File::open(rom_path).and_then(|mut file| file.read_to_end(&mut rom_buffer)) {
+        eprintln!("Failed reading {} : {}", rom_path, err);
+        std::process::exit(1);
+    }
+
+    rom_buffer
+}
+
+fn load_rom(rom_path: &str) -> InesRom {
+    match InesRom::new(read_rom(rom_path)) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("Failed parsing {} : {:?}", rom_path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn load_nes(rom_path: &str) -> Nes {
+    match Nes::new(&read_rom(rom_path)) {
+        Ok(nes) => nes,
+        Err(err) => {
+            eprintln!("Failed loading {} : {}", rom_path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// nessy disasm <rom> [bank], prints the prg banks of an ines rom
+fn run_disasm(args: &[String]) {
+    if args.is_empty() {
+        eprintln!("Usage : nessy disasm <rom> [bank]");
+        std::process::exit(1);
+    }
+
+    let rom = load_rom(&args[0]);
+
+    let prg_rom_content = rom.get_prg_rom_content();
+    let bank_count = prg_rom_content.len() / consts::INES_PRG_ROM_BANK_SIZE;
+    let banks: Vec<usize> = match args.get(1).map(|bank| bank.parse::<usize>()) {
+        Some(Ok(bank)) if bank < bank_count => vec![bank],
+        Some(_) => {
+            eprintln!("Invalid bank {}, the rom has {} banks", args[1], bank_count);
+            std::process::exit(1);
+        },
+        None => (0..bank_count).collect(),
+    };
+
+    let disassembler = Disassembler::new(CpuVariant::Ricoh2A03);
+    for bank in banks {
+        // The last bank is fixed at $C000 on power on (NROM and MMC1), the others are shown at $8000
+        let base_addr = if bank == bank_count - 1 {
+            consts::NROM_SECOND_PRG_ROM_RANGE_START
+        } else {
+            consts::NROM_FIRST_PRG_ROM_RANGE_START
+        };
+
+        let bank_content = &prg_rom_content[bank * consts::INES_PRG_ROM_BANK_SIZE..(bank + 1) * consts::INES_PRG_ROM_BANK_SIZE];
+        println!("; Bank {} at ${:04X}", bank, base_addr);
+        print!("{}", disassembler.disassemble_bank_to_text(bank_content, base_addr));
+    }
+}
+
+// nessy debug <rom>, runs the rom in the monitor, commands are read from stdin
+fn run_debugger(args: &[String]) {
+    if args.is_empty() {
+        eprintln!("Usage : nessy debug <rom>");
+        std::process::exit(1);
+    }
+
+    let rom = load_rom(&args[0]);
+    let mapper = match rom.get_mapper() {
+        Ok(mapper) => mapper,
+        Err(err) => {
+            eprintln!("Failed getting mapper from rom parser : {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let stdin = std::io::stdin();
+    let mut monitor = Monitor::new(Cpu::new(WatchedBus::new(mapper)));
+    monitor.set_rom_hash(rom.get_hash());
+    monitor.set_region(if rom.is_pal() { Region::Pal } else { Region::Ntsc });
+    if let Err(err) = monitor.run(stdin.lock(), std::io::stdout()) {
+        eprintln!("Monitor failed : {}", err);
+    }
+}
+
+fn run_gdb_stub(args: &[String]) {
+    if args.is_empty() {
+        eprintln!("Usage : nessy gdb <rom> [port]");
+        std::process::exit(1);
+    }
+
+    let port = match args.get(1).map(|port| port.parse::<u16>()) {
+        Some(Ok(port)) => port,
+        Some(Err(_)) => {
+            eprintln!("Invalid port {}", args[1]);
+            std::process::exit(1);
+        },
+        None => consts::GDB_DEFAULT_PORT,
+    };
+
+    let nes = load_nes(&args[0]);
+
+    // Only local clients, the stub gives full control over the emulator
+    let listener = match std::net::TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Failed listening on port {} : {}", port, err);
+            std::process::exit(1);
+        }
+    };
+
+    println!("Waiting for a GDB client on 127.0.0.1:{}", port);
+    let mut stub = GdbStub::new(nes.into_cpu());
+    if let Err(err) = stub.accept(&listener) {
+        eprintln!("GDB session failed : {}", err);
+    }
+}
+
+fn run_tracer(args: &[String]) {
+    let usage = "Usage : nessy trace <rom> <output> [nestest|mesen|binary] [--pc start-end] [--bank n] [--start addr|frame:N] \
+        [--stop addr|frame:N] [--frames N]";
+    if args.len() < 2 {
+        eprintln!("{}", usage);
+        std::process::exit(1);
+    }
+
+    let invalid_argument = |argument: &str| -> ! {
+        eprintln!("Invalid argument {}\n{}", argument, usage);
+        std::process::exit(1);
+    };
+
+    let mut format = TraceFormat::Nestest;
+    let mut options = &args[2..];
+    if let Some(name) = options.first().filter(|name| !name.starts_with("--")) {
+        format = TraceFormat::parse(name).unwrap_or_else(|| invalid_argument(name));
+        options = &options[1..];
+    }
+
+    let output = match TraceOutput::file(&args[1]) {
+        Ok(output) => output,
+        Err(err) => {
+            eprintln!("Failed creating {} : {}", args[1], err);
+            std::process::exit(1);
+        }
+    };
+    let mut tracer = Tracer::new(format, output);
+    let mut frame_limit = consts::TRACE_DEFAULT_FRAMES;
+
+    for option in options.chunks(2) {
+        let value = option.get(1).unwrap_or_else(|| invalid_argument(&option[0]));
+        match option[0].as_str() {
+            "--pc" => {
+                let range = value.split_once('-')
+                    .and_then(|(start, end)| Some((u16::from_str_radix(start, 16).ok()?, u16::from_str_radix(end, 16).ok()?)));
+                let (start_addr, end_addr) = range.unwrap_or_else(|| invalid_argument(value));
+                tracer.set_pc_range(start_addr, end_addr);
+            },
+            "--bank" => tracer.set_bank(value.parse::<usize>().unwrap_or_else(|_| invalid_argument(value))),
+            "--start" => tracer.set_start_trigger(TraceTrigger::parse(value).unwrap_or_else(|| invalid_argument(value))),
+            "--stop" => tracer.set_stop_trigger(TraceTrigger::parse(value).unwrap_or_else(|| invalid_argument(value))),
+            "--frames" => frame_limit = value.parse::<usize>().unwrap_or_else(|_| invalid_argument(value)),
+            _ => invalid_argument(&option[0]),
+        }
+    }
+
+    // Runs until the cpu fails, the stop trigger is reached or the frame limit
+    let mut nes = load_nes(&args[0]);
+    let (mut tracer, result) = nes.run_traced(tracer, frame_limit);
+    if let Err(err) = result {
+        eprintln!("Stopped : {}", err);
+    }
+
+    if let Err(err) = tracer.flush() {
+        eprintln!("Failed writing {} : {}", args[1], err);
+    }
+}
+
+fn run_movie(args: &[String]) {
+    let usage = "Usage : nessy movie <rom> <fm2 file> [expected ram hash] [expected framebuffer hash]";
+    if args.len() < 2 {
+        eprintln!("{}", usage);
+        std::process::exit(1);
+    }
+
+    let mut nes = load_nes(&args[0]);
+    let rom_hash = nes.get_rom_hash();
+
+    let movie = match std::fs::read_to_string(&args[1]).map(|text| Movie::from_fm2(&text, rom_hash)) {
+        Ok(Ok(movie)) => movie,
+        Ok(Err(err)) => {
+            eprintln!("Failed reading {} : {}", args[1], err);
+            std::process::exit(1);
+        },
+        Err(err) => {
+            eprintln!("Failed reading {} : {}", args[1], err);
+            std::process::exit(1);
+        }
+    };
+
+    let expected_hashes: Vec<u64> = args[2..].iter().map(|hash| u64::from_str_radix(hash, 16).unwrap_or_else(|_| {
+        eprintln!("Invalid hash {}\n{}", hash, usage);
+        std::process::exit(1);
+    })).collect();
+
+    // The movie runs frames of the region it was recorded in
+    nes.set_region(movie.get_region());
+    let cpu = nes.get_cpu_mut();
+    let result = match expected_hashes.first() {
+        Some(expected_ram_hash) => movie.play_and_verify(cpu, rom_hash, *expected_ram_hash),
+        None => movie.play(cpu, rom_hash),
+    }.and_then(|_| match expected_hashes.get(1) {
+        Some(expected_framebuffer_hash) => movie::verify_framebuffer_hash(nes.get_framebuffer(), *expected_framebuffer_hash),
+        None => Ok(()),
+    });
+
+    match result {
+        Ok(()) => println!("Played {} frames, ram hash {:016X}, framebuffer hash {:016X}", movie.get_frames().len(),
+            movie::get_ram_hash(nes.get_cpu()), movie::get_framebuffer_hash(nes.get_framebuffer())),
+        Err(err) => {
+            eprintln!("Playback failed : {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// nessy --benchmark [cycles] [nestest rom], runs the benchmark suite, nestest is skipped when the rom is missing
+fn run_benchmark(args: &[String]) {
+    let cycle_budget = match args.first().map(|cycles| cycles.parse::<usize>()) {
+        Some(Ok(cycles)) if cycles > 0 => cycles,
+        Some(_) => {
+            eprintln!("Usage : nessy --benchmark [cycles] [nestest rom]");
+            std::process::exit(1);
+        },
+        None => consts::BENCHMARK_DEFAULT_CYCLE_BUDGET,
+    };
+
+    let rom_path = args.get(1).map(std::path::PathBuf::from).unwrap_or_else(|| std::path::Path::new("samples").join("nestest.nes"));
+    let nestest_rom = if rom_path.exists() {
+        Some(load_rom(&rom_path.to_string_lossy()))
+    } else {
+        println!("{} not found, skipping nestest", rom_path.display());
+        None
+    };
+
+    println!("Running every workload for {} cycles", cycle_budget);
+    match benchmark::run_suite(nestest_rom.as_ref(), cycle_budget) {
+        Ok(results) => {
+            for result in results.iter() {
+                println!("{}", result);
+            }
+
+            let total = results.iter().fold(BenchmarkResult::new("total", 0, 0, std::time::Duration::default()), |total, result|
+                BenchmarkResult::new("total", total.get_instructions() + result.get_instructions(),
+                    total.get_cycles() + result.get_cycles(), total.get_elapsed() + result.get_elapsed()));
+            println!("{}", total);
+        },
+        Err(err) => {
+            eprintln!("Benchmark failed : {}", err);
+            std::process::exit(1);
+        }
+    }
+}
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(|command| command.as_str()) {
+        Some("disasm") => return run_disasm(&args[2..]),
+        Some("debug") => return run_debugger(&args[2..]),
+        Some("gdb") => return run_gdb_stub(&args[2..]),
+        Some("trace") => return run_tracer(&args[2..]),
+        Some("movie") => return run_movie(&args[2..]),
+        Some("--benchmark") => return run_benchmark(&args[2..]),
+        _ => {},
+    }
+
+    if args.len() < 2 {
+        eprintln!("Usage : nessy <rom> [frames] | disasm | debug | gdb | trace | movie | --benchmark");
+        std::process::exit(1);
+    }
 
     // Initialize logger
     let mut config_builder = ConfigBuilder::new();
@@ -24,39 +311,33 @@ fn main() {
 
     let config = config_builder.build();
 
-    let _ = CombinedLogger::init(
-        vec![TermLogger::new(LevelFilter::Debug, config.clone(), TerminalMode::Mixed),
-            WriteLogger::new(LevelFilter::Trace, config.clone(), File::create("nessy.log").unwrap()),]);
+    // The trace level file log is only worth it with the cpu log, which traces every instruction
+    let mut loggers: Vec<Box<dyn SharedLogger>> = vec![TermLogger::new(LevelFilter::Debug, config.clone(), TerminalMode::Mixed)];
+    if cfg!(feature = "cpu-log") {
+        loggers.push(WriteLogger::new(LevelFilter::Trace, config, File::create("nessy.log").unwrap()));
+    }
+    let _ = CombinedLogger::init(loggers);
 
     info!("Logger initialized");
     info!("Starting Nessy {}", env!("CARGO_PKG_VERSION"));
 
-    // Read sample file buffer
-    let mut file = File::open(r"samples\nestest.nes").unwrap();
-    let mut rom_buffer = Vec::<u8>::new();
-    let bytes_read = file.read_to_end(&mut rom_buffer).unwrap();
-    log::info!("Read {} from rom", bytes_read);
-
-    let parser = InesRom::new(rom_buffer).unwrap();
-    let mapper = match parser.get_mapper() {
-        Ok(m) => m,
-        Err(err) => panic!("Failed getting mapper from rom parser : {:?}", err),
+    let frame_count = match args.get(2).map(|frames| frames.parse::<usize>()) {
+        Some(Ok(frames)) => Some(frames),
+        Some(Err(_)) => {
+            eprintln!("Invalid frame count {}", args[2]);
+            std::process::exit(1);
+        },
+        None => None,
     };
-    
-    let cpu_result = Cpu::new(mapper);
-
-    if cpu_result.is_err() {
-        panic!("Failed creating cpu instance : {:?}", cpu_result.unwrap_err());
-    }
 
-    let mut cpu = cpu_result.unwrap();
-
-    loop {
-        let instruction_out = cpu.execute_instruction();
-        if instruction_out.is_err() {
-            let cpu_error = instruction_out.unwrap_err();
-            log::info!("Stopping execution due to error {:?}", cpu_error);
+    // Runs until the frame count or a cpu error
+    let mut nes = load_nes(&args[1]);
+    while frame_count.is_none_or(|frames| nes.get_frame_counter() < frames) {
+        if let Err(err) = nes.run_frame() {
+            log::info!("Stopping execution due to error {}", err);
             break;
         }
     }
-}
\ No newline at end of file
+
+    info!("Ran {} frames, ram hash {:016X}", nes.get_frame_counter(), movie::get_ram_hash(nes.get_cpu()));
+}
@@ -0,0 +1,108 @@
+use super::Mapper;
+use super::MapperError;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::core::Byte;
+use crate::core::Double;
+use crate::core::consts;
+use crate::core::memory::Memory;
+use crate::savestate::{SaveStateError, StateReader, StateWriter};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BusAccessKind {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BusAccess {
+    pub addr: Double,
+    pub value: Byte,
+    pub kind: BusAccessKind,
+}
+
+pub type BusLog = Rc<RefCell<Vec<BusAccess>>>;
+
+// A flat 64KB ram with no mirroring or rom regions, used to run the cpu outside of a cartridge.
+// Once the bus log is requested every access is recorded to it so tests can inspect what the cpu did.
+pub struct DebugMapper {
+    memory: Memory,
+    bus_log: Option<BusLog>,
+}
+
+impl Default for DebugMapper {
+    fn default() -> DebugMapper {
+        DebugMapper::new()
+    }
+}
+
+impl DebugMapper {
+    pub fn new() -> DebugMapper {
+        DebugMapper{memory: Memory::new(consts::MEMORY_SIZE), bus_log: None}
+    }
+
+    pub fn load(&mut self, start_addr: u16, content: &[u8]) {
+        for (i, b) in content.iter().enumerate() {
+            self.memory[start_addr as usize + i] = Byte::new(*b);
+        }
+    }
+
+    pub fn get_bus_log(&mut self) -> BusLog {
+        Rc::clone(self.bus_log.get_or_insert_with(|| Rc::new(RefCell::new(Vec::new()))))
+    }
+
+    fn log_access(&self, addr: Double, value: Byte, kind: BusAccessKind) {
+        if let Some(bus_log) = &self.bus_log {
+            bus_log.borrow_mut().push(BusAccess{addr, value, kind});
+        }
+    }
+}
+
+impl Mapper for DebugMapper {
+    fn get_memory_addr(&self, addr: Double) -> Result<Byte, MapperError> {
+        let value = self.memory[addr];
+        self.log_access(addr, value, BusAccessKind::Read);
+
+        Ok(value)
+    }
+
+    fn set_memory_addr(&mut self, addr: Double, value: Byte) -> Result<(), MapperError> {
+        self.memory[addr] = value;
+        self.log_access(addr, value, BusAccessKind::Write);
+
+        Ok(())
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_bytes(&self.memory[0..consts::MEMORY_SIZE]);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        for (i, b) in state.read_bytes(consts::MEMORY_SIZE, "memory")?.into_iter().enumerate() {
+            self.memory[i] = b;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn debug_mapper_records_accesses() {
+    let mut mapper = DebugMapper::new();
+    mapper.load(0x2000, &[0x01, 0x02]);
+    let bus_log = mapper.get_bus_log();
+
+    mapper.set_memory_addr(0x1234u16.into(), Byte::new(0x56)).unwrap();
+    assert_eq!(mapper.get_memory_addr(0x1234u16.into()).unwrap(), Byte::new(0x56));
+    assert_eq!(mapper.get_memory_addr(0xFFFFu16.into()).unwrap(), Byte::new(0x00));
+
+    let accesses = bus_log.borrow();
+    assert_eq!(accesses.len(), 3);
+    assert_eq!(accesses[0], BusAccess{addr: 0x1234u16.into(), value: Byte::new(0x56), kind: BusAccessKind::Write});
+    assert_eq!(accesses[1].kind, BusAccessKind::Read);
+    drop(accesses);
+
+    assert_eq!(mapper.get_memory_addr(0x2001u16.into()).unwrap(), Byte::new(0x02));
+}
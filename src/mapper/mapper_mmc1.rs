@@ -0,0 +1,204 @@
+use super::Mapper;
+use super::MapperError;
+
+use crate::core::Byte;
+use crate::core::Double;
+use crate::core::consts;
+use crate::savestate::{SaveStateError, StateReader, StateWriter};
+
+// Implemented by the docs at : https://wiki.nesdev.com/w/index.php/MMC1
+pub struct MMC1Mapper {
+    prg_ram_size: usize,
+    prg_rom_bank_count: usize,
+    prg_rom_content: Vec<Byte>,
+    prg_ram_content: Vec<Byte>,
+    general_purpose_memory: Vec<Byte>,
+
+    shift_register: u8,
+    shift_count: u8,
+    control_register: u8,
+    prg_bank_register: u8,
+}
+
+impl MMC1Mapper {
+    pub fn new(prg_rom_content: &[u8], prg_ram_size: usize) -> Result<MMC1Mapper, MapperError> {
+        let prg_rom_bank_count = prg_rom_content.len() / consts::MMC1_PRG_ROM_BANK_SIZE;
+        if prg_rom_bank_count == 0 || prg_rom_bank_count * consts::MMC1_PRG_ROM_BANK_SIZE != prg_rom_content.len() {
+            return Err(MapperError::InvalidPrgRomSize(prg_rom_content.len()));
+        }
+
+        let prg_rom_content_byte: Vec<Byte> = prg_rom_content.iter().map(|b| Byte::new(*b)).collect();
+
+        Ok(MMC1Mapper{prg_ram_size, prg_rom_bank_count,
+            prg_rom_content: prg_rom_content_byte, prg_ram_content: vec![Byte::new(0x00); prg_ram_size],
+            general_purpose_memory: vec![Byte::new(0x00); consts::MEMORY_SIZE],
+            shift_register: 0, shift_count: 0, control_register: consts::MMC1_CONTROL_POWER_ON, prg_bank_register: 0})
+    }
+
+    fn get_prg_rom_bank(&self, addr: u16) -> usize {
+        let selected_bank = (self.prg_bank_register & 0x0F) as usize;
+        let is_first_bank = addr < consts::MMC1_SECOND_PRG_ROM_RANGE_START;
+
+        let bank = match (self.control_register >> 2) & 0x03 {
+            // 32KB mode, the lowest bit of the bank number is ignored
+            0 | 1 => (selected_bank & !1) + if is_first_bank { 0 } else { 1 },
+            // First bank fixed at 0x8000
+            2 => if is_first_bank { 0 } else { selected_bank },
+            // Last bank fixed at 0xC000
+            _ => if is_first_bank { selected_bank } else { self.prg_rom_bank_count - 1 },
+        };
+
+        bank % self.prg_rom_bank_count
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        if value & 0x80 != 0 {
+            self.shift_register = 0;
+            self.shift_count = 0;
+            self.control_register |= 0x0C;
+            return;
+        }
+
+        // Bits are shifted in starting from the least significant one
+        self.shift_register |= (value & 0x01) << self.shift_count;
+        self.shift_count += 1;
+
+        if self.shift_count == 5 {
+            match addr {
+                0x8000..=0x9FFF => self.control_register = self.shift_register,
+                // Chr banks are ignored, there is no ppu to read them yet
+                0xA000..=0xDFFF => {},
+                _ => self.prg_bank_register = self.shift_register,
+            }
+
+            log::trace!("MMC1 register {:04X} set to {:02X}", addr, self.shift_register);
+
+            self.shift_register = 0;
+            self.shift_count = 0;
+        }
+    }
+}
+
+impl Mapper for MMC1Mapper {
+    fn get_memory_addr(&self, addr: Double) -> Result<Byte, MapperError> {
+        match addr.get_value() {
+            consts::MMC1_PRG_RAM_RANGE_START..=consts::MMC1_PRG_RAM_RANGE_END => {
+                Ok(self.prg_ram_content[(addr.get_value() - consts::MMC1_PRG_RAM_RANGE_START) as usize % self.prg_ram_size])
+            },
+            consts::MMC1_FIRST_PRG_ROM_RANGE_START..=consts::MMC1_SECOND_PRG_ROM_RANGE_END => {
+                let bank_offset = addr.get_value() as usize % consts::MMC1_PRG_ROM_BANK_SIZE;
+                Ok(self.prg_rom_content[self.get_prg_rom_bank(addr.get_value()) * consts::MMC1_PRG_ROM_BANK_SIZE + bank_offset])
+            },
+            _ => {
+                Ok(self.general_purpose_memory[addr.get_value() as usize])
+            }
+        }
+    }
+
+    fn set_memory_addr(&mut self, addr: Double, value: Byte) -> Result<(), MapperError> {
+        match addr.get_value() {
+            consts::MMC1_PRG_RAM_RANGE_START..=consts::MMC1_PRG_RAM_RANGE_END => {
+                self.prg_ram_content[(addr.get_value() - consts::MMC1_PRG_RAM_RANGE_START) as usize % self.prg_ram_size] = value;
+                Ok(())
+            },
+            consts::MMC1_FIRST_PRG_ROM_RANGE_START..=consts::MMC1_SECOND_PRG_ROM_RANGE_END => {
+                self.write_register(addr.get_value(), value.get_value());
+                Ok(())
+            },
+            _ => {
+                self.general_purpose_memory[addr.get_value() as usize] = value;
+                Ok(())
+            }
+        }
+    }
+
+    fn get_prg_ram_mut(&mut self) -> Option<&mut [Byte]> {
+        Some(&mut self.prg_ram_content)
+    }
+
+    fn get_internal_ram_mut(&mut self) -> Option<&mut [Byte]> {
+        Some(&mut self.general_purpose_memory[..consts::INTERNAL_RAM_SIZE as usize])
+    }
+
+    fn get_prg_bank(&self, addr: Double) -> Option<usize> {
+        match addr.get_value() {
+            consts::MMC1_FIRST_PRG_ROM_RANGE_START..=consts::MMC1_SECOND_PRG_ROM_RANGE_END => Some(self.get_prg_rom_bank(addr.get_value())),
+            _ => None,
+        }
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.shift_register);
+        state.write_u8(self.shift_count);
+        state.write_u8(self.control_register);
+        state.write_u8(self.prg_bank_register);
+        state.write_bytes(&self.prg_ram_content);
+        state.write_bytes(&self.general_purpose_memory);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        let registers = [state.read_u8()?, state.read_u8()?, state.read_u8()?, state.read_u8()?];
+        let prg_ram_content = state.read_bytes(self.prg_ram_size, "prg ram")?;
+        let general_purpose_memory = state.read_bytes(consts::MEMORY_SIZE, "memory")?;
+
+        self.shift_register = registers[0];
+        self.shift_count = registers[1];
+        self.control_register = registers[2];
+        self.prg_bank_register = registers[3];
+        self.prg_ram_content.copy_from_slice(&prg_ram_content);
+        self.general_purpose_memory.copy_from_slice(&general_purpose_memory);
+
+        Ok(())
+    }
+}
+
+#[test]
+fn mmc1_prg_bank_switching() {
+    let prg_rom_content: Vec<u8> = (0..4).flat_map(|bank| vec![bank as u8; consts::MMC1_PRG_ROM_BANK_SIZE]).collect();
+    let mut mapper = MMC1Mapper::new(&prg_rom_content, 0x2000).unwrap();
+
+    // Power on state fixes the last bank at 0xC000
+    assert_eq!(mapper.get_memory_addr(0x8000u16.into()).unwrap(), Byte::new(0));
+    assert_eq!(mapper.get_memory_addr(0xFFFFu16.into()).unwrap(), Byte::new(3));
+
+    // Select bank 2 for 0x8000 by shifting in 0b00010
+    for bit in [0u8, 1, 0, 0, 0].iter() {
+        mapper.set_memory_addr(0xE000u16.into(), Byte::new(*bit)).unwrap();
+    }
+
+    assert_eq!(mapper.get_memory_addr(0x8000u16.into()).unwrap(), Byte::new(2));
+    assert_eq!(mapper.get_memory_addr(0xC000u16.into()).unwrap(), Byte::new(3));
+
+    mapper.set_memory_addr(0x6000u16.into(), Byte::new(0x42)).unwrap();
+    assert_eq!(mapper.get_memory_addr(0x6000u16.into()).unwrap(), Byte::new(0x42));
+}
+
+#[test]
+fn mmc1_save_state() {
+    let prg_rom_content: Vec<u8> = (0..4).flat_map(|bank| vec![bank as u8; consts::MMC1_PRG_ROM_BANK_SIZE]).collect();
+    let mut mapper = MMC1Mapper::new(&prg_rom_content, 0x2000).unwrap();
+
+    // Bank 1 at 0x8000, halfway through shifting in bank 2
+    for bit in [1u8, 0, 0, 0, 0, 0, 1].iter() {
+        mapper.set_memory_addr(0xE000u16.into(), Byte::new(*bit)).unwrap();
+    }
+    mapper.set_memory_addr(0x6000u16.into(), Byte::new(0x42)).unwrap();
+
+    let mut state = StateWriter::new();
+    mapper.save_state(&mut state);
+    let state = state.into_data();
+
+    let mut restored = MMC1Mapper::new(&prg_rom_content, 0x2000).unwrap();
+    restored.load_state(&mut StateReader::new(&state)).unwrap();
+    assert_eq!(restored.get_memory_addr(0x8000u16.into()).unwrap(), Byte::new(1));
+    assert_eq!(restored.get_memory_addr(0x6000u16.into()).unwrap(), Byte::new(0x42));
+
+    for bit in [0u8, 0, 0].iter() {
+        restored.set_memory_addr(0xE000u16.into(), Byte::new(*bit)).unwrap();
+    }
+    assert_eq!(restored.get_memory_addr(0x8000u16.into()).unwrap(), Byte::new(2));
+
+    // A state saved with another prg ram size doesn't fit
+    let mut smaller = MMC1Mapper::new(&prg_rom_content, 0x1000).unwrap();
+    assert!(smaller.load_state(&mut StateReader::new(&state)).is_err());
+}
@@ -4,28 +4,26 @@ use super::MapperError;
 use crate::core::Byte;
 use crate::core::Double;
 use crate::core::consts;
+use crate::savestate::{SaveStateError, StateReader, StateWriter};
 
 pub struct NROMMapper {
     prg_ram_size: usize,
-    prg_rom_size_8_kb: usize,
+    prg_rom_bank_count: usize,
     prg_rom_content: Vec<Byte>,
+    prg_ram_content: Vec<Byte>,
     general_purpose_memory: Vec<Byte>
 }
 
 impl NROMMapper {
-    pub fn new(prg_rom_content: &Vec<u8>, prg_ram_size: usize) -> NROMMapper {
-        let mut prg_rom_size_8_kb: usize = 0;
-        match prg_rom_content.len() {
-            0x2000 => {
-                prg_rom_size_8_kb = 1;
-            },
-            0x4000 => {
-                prg_rom_size_8_kb = 2;
-            },
-            _ => {
-                panic!("Invalid prg rom size");
+    pub fn new(prg_rom_content: &Vec<u8>, prg_ram_size: usize) -> Result<NROMMapper, MapperError> {
+        // NROM-128 has a single 16KB bank mirrored at 0xC000, NROM-256 fills both ranges
+        let prg_rom_bank_count = match prg_rom_content.len() {
+            0x4000 => 1,
+            0x8000 => 2,
+            size => {
+                return Err(MapperError::InvalidPrgRomSize(size));
             }
-        }
+        };
 
         let mut prg_rom_content_byte: Vec<Byte> = Vec::<Byte>::new();
         for b in prg_rom_content {
@@ -37,8 +35,9 @@ impl NROMMapper {
             general_purpose_memory.push(Byte::new(0x00));
         }
 
-        NROMMapper{prg_ram_size: prg_ram_size, prg_rom_size_8_kb:prg_rom_size_8_kb, 
-            prg_rom_content:prg_rom_content_byte, general_purpose_memory}
+        Ok(NROMMapper{prg_ram_size, prg_rom_bank_count,
+            prg_rom_content:prg_rom_content_byte, prg_ram_content: vec![Byte::new(0x00); prg_ram_size],
+            general_purpose_memory})
     }
 }
 
@@ -46,16 +45,16 @@ impl Mapper for NROMMapper {
     fn get_memory_addr(&self, addr: Double) -> Result<Byte, MapperError> {
         match addr.get_value() {
             consts::NROM_PRG_RAM_RANGE_START..=consts::NROM_PRG_RAM_RANGE_END => {
-                Ok(self.prg_rom_content[addr.get_value() as usize % self.prg_ram_size])
+                Ok(self.prg_ram_content[(addr.get_value() - consts::NROM_PRG_RAM_RANGE_START) as usize % self.prg_ram_size])
             },
             consts::NROM_FIRST_PRG_ROM_RANGE_START..=consts::NROM_FIRST_PRG_ROM_RANGE_END => {
                 Ok(self.prg_rom_content[addr.get_value() as usize - consts::NROM_FIRST_PRG_ROM_RANGE_START as usize])
             },
             consts::NROM_SECOND_PRG_ROM_RANGE_START..=consts::NROM_SECOND_PRG_ROM_RANGE_END => {
-                if self.prg_rom_size_8_kb == 2 {
-                    Ok(self.prg_rom_content[addr.get_value() as usize - consts::NROM_SECOND_PRG_ROM_RANGE_START as usize])
-                } else {
+                if self.prg_rom_bank_count == 2 {
                     Ok(self.prg_rom_content[addr.get_value() as usize - consts::NROM_FIRST_PRG_ROM_RANGE_START as usize])
+                } else {
+                    Ok(self.prg_rom_content[addr.get_value() as usize - consts::NROM_SECOND_PRG_ROM_RANGE_START as usize])
                 }
             }
             _ => {
@@ -67,7 +66,7 @@ impl Mapper for NROMMapper {
     fn set_memory_addr(&mut self, addr: Double, value: Byte) -> Result<(), MapperError> {
         match addr.get_value() {
             consts::NROM_PRG_RAM_RANGE_START..=consts::NROM_PRG_RAM_RANGE_END => {
-                self.prg_rom_content[addr.get_value() as usize % self.prg_ram_size] = value;
+                self.prg_ram_content[(addr.get_value() - consts::NROM_PRG_RAM_RANGE_START) as usize % self.prg_ram_size] = value;
                 Ok(())
             },
             consts::NROM_FIRST_PRG_ROM_RANGE_START..=consts::NROM_FIRST_PRG_ROM_RANGE_END => {
@@ -75,11 +74,11 @@ impl Mapper for NROMMapper {
                 Ok(())
             },
             consts::NROM_SECOND_PRG_ROM_RANGE_START..=consts::NROM_SECOND_PRG_ROM_RANGE_END => {
-                if self.prg_rom_size_8_kb == 1 {
-                    self.prg_rom_content[addr.get_value() as usize - consts::NROM_SECOND_PRG_ROM_RANGE_START as usize] = value;
+                if self.prg_rom_bank_count == 2 {
+                    self.prg_rom_content[addr.get_value() as usize - consts::NROM_FIRST_PRG_ROM_RANGE_START as usize] = value;
                     Ok(())
                 } else {
-                    self.prg_rom_content[addr.get_value() as usize - consts::NROM_FIRST_PRG_ROM_RANGE_START as usize] = value;
+                    self.prg_rom_content[addr.get_value() as usize - consts::NROM_SECOND_PRG_ROM_RANGE_START as usize] = value;
                     Ok(())
                 }
             }
@@ -89,4 +88,54 @@ impl Mapper for NROMMapper {
             }
         }
     }
-}
\ No newline at end of file
+
+    fn get_prg_ram_mut(&mut self) -> Option<&mut [Byte]> {
+        Some(&mut self.prg_ram_content)
+    }
+
+    fn get_internal_ram_mut(&mut self) -> Option<&mut [Byte]> {
+        Some(&mut self.general_purpose_memory[..consts::INTERNAL_RAM_SIZE as usize])
+    }
+
+    fn get_prg_bank(&self, addr: Double) -> Option<usize> {
+        // A single bank, mirrored when it's only 16KB
+        match addr.get_value() {
+            consts::NROM_FIRST_PRG_ROM_RANGE_START..=consts::NROM_SECOND_PRG_ROM_RANGE_END => Some(0),
+            _ => None,
+        }
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_bytes(&self.prg_ram_content);
+        state.write_bytes(&self.general_purpose_memory);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        let prg_ram_content = state.read_bytes(self.prg_ram_size, "prg ram")?;
+        let general_purpose_memory = state.read_bytes(consts::MEMORY_SIZE, "memory")?;
+        self.prg_ram_content.copy_from_slice(&prg_ram_content);
+        self.general_purpose_memory.copy_from_slice(&general_purpose_memory);
+
+        Ok(())
+    }
+}
+
+#[test]
+fn nrom_prg_rom_banks() {
+    let mut prg_rom_content = vec![0u8; 0x8000];
+    prg_rom_content[0x0000] = 1;
+    prg_rom_content[0x4000] = 2;
+
+    // NROM-256 maps both banks
+    let mapper = NROMMapper::new(&prg_rom_content, 0x2000).unwrap();
+    assert_eq!(mapper.get_memory_addr(0x8000u16.into()).unwrap(), Byte::new(1));
+    assert_eq!(mapper.get_memory_addr(0xC000u16.into()).unwrap(), Byte::new(2));
+
+    // NROM-128 mirrors its bank
+    let mapper = NROMMapper::new(&prg_rom_content[..0x4000].to_vec(), 0x2000).unwrap();
+    assert_eq!(mapper.get_memory_addr(0xC000u16.into()).unwrap(), Byte::new(1));
+
+    for size in [0, 0x2000, 0xC000] {
+        assert!(matches!(NROMMapper::new(&vec![0u8; size], 0x2000), Err(MapperError::InvalidPrgRomSize(_))));
+    }
+}
@@ -1,18 +1,99 @@
 mod mapper_nrom;
+mod mapper_mmc1;
+mod mapper_debug;
 
 pub use mapper_nrom::NROMMapper;
+pub use mapper_mmc1::MMC1Mapper;
+pub use mapper_debug::{DebugMapper, BusAccess, BusAccessKind};
 
 use crate::core::Double;
 use crate::core::Byte;
+use crate::cpu::CpuBus;
+use crate::savestate::{SaveStateError, StateReader, StateWriter};
 
 // Mapper Errors Enum
 #[derive(Debug)]
 pub enum MapperError {
     InvalidMemoryAddrRequseted(Double),
+    // The prg rom doesn't fit the mapper's banks
+    InvalidPrgRomSize(usize),
 }
 
 // Mapper Trait
 pub trait Mapper {
     fn get_memory_addr(&self, addr: Double) -> Result<Byte, MapperError> ;
     fn set_memory_addr(&mut self, addr: Double, value: Byte) -> Result<(), MapperError>;
-}
\ No newline at end of file
+
+    // The prg rom bank currently mapped at the address
+    fn get_prg_bank(&self, _addr: Double) -> Option<usize> {
+        None
+    }
+
+    // The battery backed prg ram, for frontends that keep save files
+    fn get_prg_ram_mut(&mut self) -> Option<&mut [Byte]> {
+        None
+    }
+
+    // The console's 2KB of ram at $0000
+    fn get_internal_ram_mut(&mut self) -> Option<&mut [Byte]> {
+        None
+    }
+
+    // Banking registers and ram, the rom itself isn't saved.
+    // Loading copies into the existing ram so pointers handed to frontends stay valid.
+    fn save_state(&self, state: &mut StateWriter);
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError>;
+}
+
+impl<M: Mapper + ?Sized> Mapper for Box<M> {
+    fn get_memory_addr(&self, addr: Double) -> Result<Byte, MapperError> {
+        (**self).get_memory_addr(addr)
+    }
+
+    fn set_memory_addr(&mut self, addr: Double, value: Byte) -> Result<(), MapperError> {
+        (**self).set_memory_addr(addr, value)
+    }
+
+    fn get_prg_bank(&self, addr: Double) -> Option<usize> {
+        (**self).get_prg_bank(addr)
+    }
+
+    fn get_prg_ram_mut(&mut self) -> Option<&mut [Byte]> {
+        (**self).get_prg_ram_mut()
+    }
+
+    fn get_internal_ram_mut(&mut self) -> Option<&mut [Byte]> {
+        (**self).get_internal_ram_mut()
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        (**self).save_state(state)
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        (**self).load_state(state)
+    }
+}
+
+// Every mapper can be the cpu bus, it handles the whole nes memory map
+impl<M: Mapper + ?Sized> CpuBus for M {
+    fn read(&self, addr: Double) -> Byte {
+        self.get_memory_addr(addr).unwrap()
+    }
+
+    fn write(&mut self, addr: Double, value: Byte) {
+        self.set_memory_addr(addr, value).unwrap()
+    }
+
+    fn get_prg_bank(&self, addr: Double) -> Option<usize> {
+        Mapper::get_prg_bank(self, addr)
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        Mapper::save_state(self, state)
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        Mapper::load_state(self, state)
+    }
+}
@@ -0,0 +1,243 @@
+// FCEUX's text movie format, implemented by the docs at : https://fceux.com/web/help/fm2.html
+// Movies starting from a save state carry a nessy save state, FCEUX can't load those.
+// The rom checksum is FCEUX's md5 of the rom, nessy writes its own rom hash in a comment instead.
+
+use super::{FrameInput, Movie, MovieError, MovieStart};
+
+use crate::core::consts;
+use crate::nes::Region;
+
+const FM2_VERSION: &str = "3";
+// Buttons in the order of the input log columns, from the highest bit of the button mask
+const FM2_BUTTONS: &str = "RLDUTSBA";
+const FM2_COMMAND_SOFT_RESET: u8 = 0x01;
+const ROM_HASH_COMMENT: &str = "nessyRomHash";
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_base64(data: &[u8]) -> String {
+    let mut text = String::new();
+    for chunk in data.chunks(3) {
+        let value = chunk.iter().enumerate().fold(0u32, |value, (i, b)| value | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64_ALPHABET[(value >> (18 - 6 * i)) as usize & 0x3F] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+
+    text
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let digits = text.trim_end_matches('=').bytes()
+        .map(|c| BASE64_ALPHABET.iter().position(|digit| *digit == c).map(|digit| digit as u32))
+        .collect::<Option<Vec<u32>>>()?;
+
+    let mut data = Vec::<u8>::new();
+    for chunk in digits.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+
+        let value = chunk.iter().enumerate().fold(0u32, |value, (i, digit)| value | digit << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            data.push((value >> (16 - 8 * i)) as u8);
+        }
+    }
+
+    Some(data)
+}
+
+fn format_buttons(buttons: u8) -> String {
+    FM2_BUTTONS.chars().enumerate().map(|(i, button)| if buttons & (0x80 >> i) != 0 { button } else { '.' }).collect()
+}
+
+fn parse_buttons(column: &str) -> Option<u8> {
+    if column.is_empty() {
+        return Some(0);
+    }
+    if column.chars().count() != FM2_BUTTONS.len() {
+        return None;
+    }
+
+    // Anything but a space or a dot is a pressed button
+    Some(column.chars().enumerate().fold(0u8, |buttons, (i, c)| if c == ' ' || c == '.' { buttons } else { buttons | 0x80 >> i }))
+}
+
+impl Movie {
+    pub fn to_fm2(&self, rom_filename: &str) -> String {
+        let mut lines = vec![
+            format!("version {}", FM2_VERSION),
+            String::from("emuVersion 0"),
+            String::from("rerecordCount 0"),
+            format!("palFlag {}", if self.get_region() == Region::Pal { 1 } else { 0 }),
+            format!("romFilename {}", rom_filename),
+            String::from("fourscore 0"),
+            String::from("microphone 0"),
+            String::from("port0 1"),
+            String::from("port1 1"),
+            String::from("port2 0"),
+            String::from("FDS 0"),
+            String::from("NewPPU 0"),
+            format!("comment author nessy {}", env!("CARGO_PKG_VERSION")),
+            format!("comment {} {:016X}", ROM_HASH_COMMENT, self.get_rom_hash()),
+        ];
+
+        if let MovieStart::SaveState(state) = self.get_start() {
+            lines.push(format!("savestate base64:{}", encode_base64(state)));
+        }
+
+        for input in self.get_frames() {
+            let commands = if input.soft_reset { FM2_COMMAND_SOFT_RESET } else { 0 };
+            lines.push(format!("|{}|{}|{}||", commands, format_buttons(input.buttons[0]), format_buttons(input.buttons[1])));
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    // Reads a movie for the rom, FCEUX movies don't say which rom they were recorded with so it's taken on trust
+    pub fn from_fm2(text: &str, rom_hash: u64) -> Result<Movie, MovieError> {
+        let invalid_line = |line: &str| MovieError::InvalidFm2(line.to_string());
+        let mut start = MovieStart::PowerOn;
+        let mut region = Region::Ntsc;
+        let mut frames = Vec::<FrameInput>::new();
+        let mut has_version = false;
+
+        for line in text.lines().map(|line| line.trim_end_matches('\r')).filter(|line| !line.is_empty()) {
+            if line.starts_with('|') {
+                let columns: Vec<&str> = line.split('|').collect();
+                if columns.len() < 4 {
+                    return Err(invalid_line(line));
+                }
+
+                let commands = columns[1].parse::<u8>().map_err(|_| invalid_line(line))?;
+                if commands & !FM2_COMMAND_SOFT_RESET != 0 {
+                    return Err(MovieError::Unsupported("hard reset, fds and vs commands"));
+                }
+
+                let buttons = [parse_buttons(columns[2]).ok_or_else(|| invalid_line(line))?,
+                    parse_buttons(columns[3]).ok_or_else(|| invalid_line(line))?];
+                frames.push(FrameInput{soft_reset: commands & FM2_COMMAND_SOFT_RESET != 0, buttons});
+                continue;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match (key, value) {
+                ("version", FM2_VERSION) => has_version = true,
+                ("version", _) => return Err(MovieError::Unsupported("fm2 versions other than 3")),
+                ("palFlag", "0") => region = Region::Ntsc,
+                ("palFlag", "1") => region = Region::Pal,
+                ("palFlag", _) => return Err(invalid_line(line)),
+                ("binary", "1") => return Err(MovieError::Unsupported("binary input logs")),
+                ("fourscore", "1") => return Err(MovieError::Unsupported("four score")),
+                ("port0", "0") | ("port0", "1") | ("port1", "0") | ("port1", "1") | ("port2", "0") => {},
+                ("port0", _) | ("port1", _) | ("port2", _) => return Err(MovieError::Unsupported("devices other than the standard controller")),
+                ("comment", comment) => {
+                    if let Some(hash) = comment.strip_prefix(ROM_HASH_COMMENT) {
+                        let found = u64::from_str_radix(hash.trim(), 16).map_err(|_| invalid_line(line))?;
+                        if found != rom_hash {
+                            return Err(MovieError::RomMismatch{expected: rom_hash, found});
+                        }
+                    }
+                },
+                ("savestate", state) => {
+                    let state = state.strip_prefix("base64:").and_then(decode_base64).ok_or_else(|| invalid_line(line))?;
+                    if !state.starts_with(consts::SAVE_STATE_MAGIC) {
+                        return Err(MovieError::Unsupported("FCEUX save states"));
+                    }
+                    start = MovieStart::SaveState(state);
+                },
+                // The other keys only describe the movie
+                _ => {},
+            }
+        }
+
+        if !has_version {
+            return Err(MovieError::InvalidFm2(String::from("missing version")));
+        }
+
+        Ok(Movie::new(rom_hash, region, start, frames))
+    }
+}
+
+#[test]
+fn fm2_base64() {
+    for data in [&b""[..], b"f", b"fo", b"foo", b"foob", b"fooba", b"foobar"].iter() {
+        assert_eq!(decode_base64(&encode_base64(data)).unwrap(), data.to_vec());
+    }
+    assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+    assert_eq!(encode_base64(b"fooba"), "Zm9vYmE=");
+    assert_eq!(decode_base64("Zm9vYg=="), Some(b"foob".to_vec()));
+    assert_eq!(decode_base64("Zm9v!"), None);
+}
+
+#[test]
+fn fm2_import_export() {
+    use super::player::get_test_input_cpu;
+
+    let mut cpu = get_test_input_cpu();
+    let mut movie = Movie::record_from_power_on(&cpu, 0x1234, Region::Ntsc).unwrap();
+    movie.record_frame(&mut cpu, FrameInput{soft_reset: false, buttons: [consts::BUTTON_A | consts::BUTTON_RIGHT, 0]}).unwrap();
+    movie.record_frame(&mut cpu, FrameInput{soft_reset: true, buttons: [0, consts::BUTTON_START]}).unwrap();
+
+    let text = movie.to_fm2("test.nes");
+    assert!(text.contains("romFilename test.nes\n"));
+    assert!(text.contains("palFlag 0\n"));
+    assert!(text.ends_with("|0|R......A|........||\n|1|........|....T...||\n"), "{}", text);
+    assert_eq!(Movie::from_fm2(&text, 0x1234).unwrap(), movie);
+    assert!(matches!(Movie::from_fm2(&text, 0x4321), Err(MovieError::RomMismatch{..})));
+
+    let continued = Movie::record_from_state(&cpu, 0x1234, Region::Ntsc);
+    assert_eq!(Movie::from_fm2(&continued.to_fm2("test.nes"), 0x1234).unwrap(), continued);
+
+    // An FCEUX movie, input columns use spaces or dots and any letter for pressed buttons
+    let fceux_movie = "version 3\nemuVersion 22020\nromFilename smb\nromChecksum base64:AAAA\nport0 1\nport1 0\nport2 0\n\
+        |0|   U   A|||\n|0|.......x|||\n";
+    let movie = Movie::from_fm2(fceux_movie, 0x1234).unwrap();
+    assert_eq!(movie.get_frames().iter().map(|input| input.buttons[0]).collect::<Vec<u8>>(),
+        vec![consts::BUTTON_UP | consts::BUTTON_A, consts::BUTTON_A]);
+
+    assert!(matches!(Movie::from_fm2("version 3\nbinary 1\n", 0), Err(MovieError::Unsupported(_))));
+    assert!(matches!(Movie::from_fm2("version 3\n|2|........|||\n", 0), Err(MovieError::Unsupported(_))));
+    assert!(matches!(Movie::from_fm2("version 3\nsavestate base64:AAAA\n", 0), Err(MovieError::Unsupported(_))));
+    assert!(matches!(Movie::from_fm2("|0|...|||\n", 0), Err(MovieError::InvalidFm2(_))));
+}
+
+#[test]
+fn fm2_pal_round_trip() {
+    use super::player::get_ram_hash;
+    use crate::nes::Nes;
+
+    let rom = crate::nes::console::get_test_rom();
+    let mut nes = Nes::new(&rom).unwrap();
+    nes.set_region(Region::Pal);
+    let rom_hash = nes.get_rom_hash();
+
+    let mut movie = Movie::record_from_power_on(nes.get_cpu(), rom_hash, nes.get_region()).unwrap();
+    for frame in 0..3u8 {
+        movie.record_frame(nes.get_cpu_mut(), FrameInput{soft_reset: false, buttons: [frame & consts::BUTTON_A, 0]}).unwrap();
+    }
+
+    // PAL frames are 33247.5 cycles, NTSC ones 29780.67
+    assert_eq!(nes.get_frame_counter(), 3);
+    assert!(nes.get_cpu().get_cycle_counter() > 3 * 33247);
+    let ram_hash = get_ram_hash(nes.get_cpu());
+
+    let text = movie.to_fm2("test.nes");
+    assert!(text.contains("palFlag 1\n"), "{}", text);
+    let imported = Movie::from_fm2(&text, rom_hash).unwrap();
+    assert_eq!(imported, movie);
+
+    // Played on an NTSC machine switched to the movie's region, it ends the same way
+    let mut ntsc_nes = Nes::new(&rom).unwrap();
+    assert_eq!(ntsc_nes.get_region(), Region::Ntsc);
+    ntsc_nes.set_region(imported.get_region());
+    imported.play_and_verify(ntsc_nes.get_cpu_mut(), rom_hash, ram_hash).unwrap();
+    assert_eq!(ntsc_nes.get_frame_counter(), 3);
+    assert_eq!(ntsc_nes.get_cpu().get_cycle_counter(), nes.get_cpu().get_cycle_counter());
+
+    assert!(matches!(Movie::from_fm2("version 3\npalFlag 2\n", 0), Err(MovieError::InvalidFm2(_))));
+}
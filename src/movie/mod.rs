@@ -0,0 +1,55 @@
+pub mod player;
+pub mod fm2;
+
+use std::fmt;
+
+use crate::cpu::CpuError;
+use crate::savestate::SaveStateError;
+
+pub use player::{Movie, get_framebuffer_hash, get_ram_hash, verify_framebuffer_hash};
+
+#[derive(Debug)]
+pub enum MovieError {
+    InvalidFm2(String),
+    Unsupported(&'static str),
+    // Power on movies play on a cpu that didn't run yet
+    NotAtPowerOn,
+    RomMismatch{expected: u64, found: u64},
+    RamHashMismatch{expected: u64, found: u64},
+    FramebufferHashMismatch{expected: u64, found: u64},
+    SaveStateFailed(SaveStateError),
+    CpuFailed(CpuError),
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovieError::InvalidFm2(line) => write!(f, "Invalid fm2 line : {}", line),
+            MovieError::Unsupported(feature) => write!(f, "Unsupported movie feature : {}", feature),
+            MovieError::NotAtPowerOn => write!(f, "The movie starts at power on, the machine already ran"),
+            MovieError::RomMismatch{expected, found} =>
+                write!(f, "Movie is for rom {:016X}, the running rom is {:016X}", found, expected),
+            MovieError::RamHashMismatch{expected, found} =>
+                write!(f, "Ram hash is {:016X} after playback, expected {:016X}", found, expected),
+            MovieError::FramebufferHashMismatch{expected, found} =>
+                write!(f, "Framebuffer hash is {:016X} after playback, expected {:016X}", found, expected),
+            MovieError::SaveStateFailed(err) => write!(f, "Loading the movie start failed : {}", err),
+            MovieError::CpuFailed(err) => write!(f, "Cpu failed during playback : {:?}", err),
+        }
+    }
+}
+
+// Where the movie starts
+#[derive(Debug, Clone, PartialEq)]
+pub enum MovieStart {
+    PowerOn,
+    SaveState(Vec<u8>),
+}
+
+// The input of one frame, applied before the frame runs
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FrameInput {
+    pub soft_reset: bool,
+    // A mask of the consts::BUTTON_ values for each controller port
+    pub buttons: [u8; 2],
+}
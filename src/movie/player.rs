@@ -0,0 +1,203 @@
+use super::{FrameInput, MovieError, MovieStart};
+
+use crate::core::Double;
+use crate::core::consts;
+use crate::core::get_fnv1a_hash;
+use crate::cpu::CpuBus;
+use crate::cpu::cpu::Cpu;
+use crate::input::InputBus;
+use crate::nes::Region;
+use crate::savestate;
+
+// The cycle counter of a cpu that only went through the reset sequence
+const POWER_ON_CYCLE_COUNTER: usize = 7;
+
+// Runs the machine for one frame of the region with the input of the frame
+pub fn run_frame<B: CpuBus>(cpu: &mut Cpu<InputBus<B>>, region: Region, input: &FrameInput) -> Result<(), MovieError> {
+    if input.soft_reset {
+        cpu.reset();
+    }
+
+    for (port, buttons) in input.buttons.iter().enumerate() {
+        cpu.get_bus_mut().get_controller_mut(port).set_buttons(*buttons);
+    }
+
+    let next_frame = region.get_frame(cpu.get_cycle_counter()) + 1;
+    while region.get_frame(cpu.get_cycle_counter()) < next_frame {
+        cpu.execute_instruction().map_err(MovieError::CpuFailed)?;
+    }
+
+    Ok(())
+}
+
+// Hashes the internal ram, a movie that played the same way ends with the same hash
+pub fn get_ram_hash<B: CpuBus>(cpu: &Cpu<B>) -> u64 {
+    let ram: Vec<u8> = (0..consts::INTERNAL_RAM_SIZE).map(|addr| cpu.peek_memory_addr(Double::from(addr)).get_value()).collect();
+    get_fnv1a_hash(&ram)
+}
+
+// Hashes the RGBA framebuffer of the console the movie played on
+pub fn get_framebuffer_hash(framebuffer: &[u8]) -> u64 {
+    get_fnv1a_hash(framebuffer)
+}
+
+// Checks the last frame of a playback looks the way it did when it was recorded
+pub fn verify_framebuffer_hash(framebuffer: &[u8], expected_framebuffer_hash: u64) -> Result<(), MovieError> {
+    let found = get_framebuffer_hash(framebuffer);
+    if found != expected_framebuffer_hash {
+        return Err(MovieError::FramebufferHashMismatch{expected: expected_framebuffer_hash, found});
+    }
+
+    Ok(())
+}
+
+// The controller input of every frame from a starting point, replaying it runs the machine the same way again
+#[derive(Debug, Clone, PartialEq)]
+pub struct Movie {
+    rom_hash: u64,
+    // The frame length the movie was recorded with
+    region: Region,
+    start: MovieStart,
+    frames: Vec<FrameInput>,
+}
+
+impl Movie {
+    pub fn new(rom_hash: u64, region: Region, start: MovieStart, frames: Vec<FrameInput>) -> Movie {
+        Movie{rom_hash, region, start, frames}
+    }
+
+    // Starts recording on a cpu that didn't run yet, frames are as long as they are in the region
+    pub fn record_from_power_on<B: CpuBus>(cpu: &Cpu<InputBus<B>>, rom_hash: u64, region: Region) -> Result<Movie, MovieError> {
+        if cpu.get_cycle_counter() != POWER_ON_CYCLE_COUNTER {
+            return Err(MovieError::NotAtPowerOn);
+        }
+
+        Ok(Movie::new(rom_hash, region, MovieStart::PowerOn, Vec::new()))
+    }
+
+    // Starts recording from the current state of the machine
+    pub fn record_from_state<B: CpuBus>(cpu: &Cpu<InputBus<B>>, rom_hash: u64, region: Region) -> Movie {
+        Movie::new(rom_hash, region, MovieStart::SaveState(savestate::save(cpu, rom_hash)), Vec::new())
+    }
+
+    pub fn get_rom_hash(&self) -> u64 {
+        self.rom_hash
+    }
+
+    pub fn get_region(&self) -> Region {
+        self.region
+    }
+
+    pub fn get_start(&self) -> &MovieStart {
+        &self.start
+    }
+
+    pub fn get_frames(&self) -> &[FrameInput] {
+        &self.frames
+    }
+
+    // Runs a frame and appends its input to the movie
+    pub fn record_frame<B: CpuBus>(&mut self, cpu: &mut Cpu<InputBus<B>>, input: FrameInput) -> Result<(), MovieError> {
+        run_frame(cpu, self.region, &input)?;
+        self.frames.push(input);
+
+        Ok(())
+    }
+
+    // Puts the machine at the movie start and runs every frame of the movie, at the region it was recorded with.
+    // A Nes playing it should be switched to the movie's region first so its frame counter agrees.
+    pub fn play<B: CpuBus>(&self, cpu: &mut Cpu<InputBus<B>>, rom_hash: u64) -> Result<(), MovieError> {
+        if rom_hash != self.rom_hash {
+            return Err(MovieError::RomMismatch{expected: rom_hash, found: self.rom_hash});
+        }
+
+        match &self.start {
+            MovieStart::PowerOn if cpu.get_cycle_counter() != POWER_ON_CYCLE_COUNTER => return Err(MovieError::NotAtPowerOn),
+            MovieStart::PowerOn => {},
+            MovieStart::SaveState(state) => savestate::load(cpu, state, rom_hash).map_err(MovieError::SaveStateFailed)?,
+        }
+
+        for input in self.frames.iter() {
+            run_frame(cpu, self.region, input)?;
+        }
+
+        Ok(())
+    }
+
+    // Plays the movie and checks the ram ends up the way it did when it was recorded
+    pub fn play_and_verify<B: CpuBus>(&self, cpu: &mut Cpu<InputBus<B>>, rom_hash: u64, expected_ram_hash: u64) -> Result<(), MovieError> {
+        self.play(cpu, rom_hash)?;
+
+        let found = get_ram_hash(cpu);
+        if found != expected_ram_hash {
+            return Err(MovieError::RamHashMismatch{expected: expected_ram_hash, found});
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub fn get_test_input_cpu() -> Cpu<InputBus<crate::mapper::DebugMapper>> {
+    // Strobes the controller and adds the A button of port 0 to $10 and of port 1 to $11, over and over
+    let program = [
+        0xA9, 0x01, 0x8D, 0x16, 0x40, // LDA #$01 ; STA $4016
+        0xA9, 0x00, 0x8D, 0x16, 0x40, // LDA #$00 ; STA $4016
+        0xAD, 0x16, 0x40, 0x29, 0x01, // LDA $4016 ; AND #$01
+        0x18, 0x65, 0x10, 0x85, 0x10, // CLC ; ADC $10 ; STA $10
+        0xAD, 0x17, 0x40, 0x29, 0x01, // LDA $4017 ; AND #$01
+        0x18, 0x65, 0x11, 0x85, 0x11, // CLC ; ADC $11 ; STA $11
+        0x4C, 0x00, 0x02,             // JMP $0200
+    ];
+
+    let mut mapper = crate::mapper::DebugMapper::new();
+    mapper.load(0x0200, &program);
+    mapper.load(consts::RESET_VECTOR_ADDR, &[0x00, 0x02]);
+    Cpu::new_with_entry_point(InputBus::new(mapper), Double::from(0x0200u16))
+}
+
+#[test]
+fn movie_playback() {
+    let inputs: Vec<FrameInput> = (0..8)
+        .map(|frame| FrameInput{soft_reset: false, buttons: [if frame % 3 == 0 { consts::BUTTON_A } else { 0 }, (frame % 2) as u8]})
+        .collect();
+
+    let mut cpu = get_test_input_cpu();
+    let mut movie = Movie::record_from_power_on(&cpu, 0x1234, Region::Ntsc).unwrap();
+    for input in inputs.iter().take(4) {
+        movie.record_frame(&mut cpu, *input).unwrap();
+    }
+
+    // A second movie picks up from the middle of the first one
+    let mut continued = Movie::record_from_state(&cpu, 0x1234, Region::Ntsc);
+    for input in inputs.iter().skip(4) {
+        continued.record_frame(&mut cpu, *input).unwrap();
+    }
+    movie.frames.extend_from_slice(continued.get_frames());
+    let ram_hash = get_ram_hash(&cpu);
+    assert_ne!(cpu.peek_memory_addr(Double::from(0x10u16)).get_value(), 0);
+
+    movie.play_and_verify(&mut get_test_input_cpu(), 0x1234, ram_hash).unwrap();
+    continued.play_and_verify(&mut get_test_input_cpu(), 0x1234, ram_hash).unwrap();
+
+    // Different input ends somewhere else
+    let mut altered = movie.clone();
+    altered.frames[1].buttons[0] = consts::BUTTON_A;
+    assert!(matches!(altered.play_and_verify(&mut get_test_input_cpu(), 0x1234, ram_hash), Err(MovieError::RamHashMismatch{..})));
+
+    assert!(matches!(movie.play(&mut cpu, 0x1234), Err(MovieError::NotAtPowerOn)));
+    assert!(matches!(movie.play(&mut get_test_input_cpu(), 0x4321), Err(MovieError::RomMismatch{..})));
+}
+
+#[test]
+fn movie_framebuffer_hash() {
+    let mut nes = crate::nes::Nes::new(&crate::nes::console::get_test_rom()).unwrap();
+    let rom_hash = nes.get_rom_hash();
+    let movie = Movie::new(rom_hash, nes.get_region(), MovieStart::PowerOn, vec![FrameInput::default(); 2]);
+    let framebuffer_hash = get_framebuffer_hash(nes.get_framebuffer());
+
+    movie.play(nes.get_cpu_mut(), rom_hash).unwrap();
+    verify_framebuffer_hash(nes.get_framebuffer(), framebuffer_hash).unwrap();
+    assert!(matches!(verify_framebuffer_hash(nes.get_framebuffer(), !framebuffer_hash),
+        Err(MovieError::FramebufferHashMismatch{..})));
+}
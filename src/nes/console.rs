@@ -0,0 +1,254 @@
+use super::{NesError, Region};
+
+use crate::core::Byte;
+use crate::core::Double;
+use crate::core::consts;
+use crate::cpu::cpu::Cpu;
+use crate::input::InputBus;
+use crate::mapper::Mapper;
+use crate::rom_parser::ines::InesRom;
+use crate::savestate;
+use crate::tracer::Tracer;
+
+pub type NesCpu = Cpu<InputBus<Box<dyn Mapper>>>;
+
+// The whole console behind one type, for frontends and tools that embed nessy.
+// There is no ppu or apu yet, the framebuffer stays black and the audio is silence at the output rate.
+pub struct Nes {
+    cpu: NesCpu,
+    rom_hash: u64,
+    // Sets the frame length, the cpu only counts cycles
+    region: Region,
+    framebuffer: Vec<u8>,
+    audio_samples: Vec<f32>,
+    // Cycles run since the last audio sample, scaled by the sample rate
+    audio_cycle_remainder: usize,
+}
+
+impl Nes {
+    // Loads an ines rom and powers the console on
+    pub fn new(rom: &[u8]) -> Result<Nes, NesError> {
+        let rom = InesRom::new(rom.to_vec()).map_err(NesError::InvalidRom)?;
+        let mapper = rom.get_mapper().map_err(NesError::InvalidRom)?;
+
+        let mut framebuffer = vec![0u8; consts::FRAME_WIDTH * consts::FRAME_HEIGHT * consts::FRAME_BYTES_PER_PIXEL];
+        for pixel in framebuffer.chunks_mut(consts::FRAME_BYTES_PER_PIXEL) {
+            pixel[3] = 0xFF;
+        }
+
+        Ok(Nes{
+            cpu: Cpu::new(InputBus::new(mapper)),
+            rom_hash: rom.get_hash(),
+            region: if rom.is_pal() { Region::Pal } else { Region::Ntsc },
+            framebuffer,
+            audio_samples: Vec::new(),
+            audio_cycle_remainder: 0,
+        })
+    }
+
+    pub fn get_cpu(&self) -> &NesCpu {
+        &self.cpu
+    }
+
+    pub fn get_cpu_mut(&mut self) -> &mut NesCpu {
+        &mut self.cpu
+    }
+
+    // For tools that drive the cpu themselves
+    pub fn into_cpu(self) -> NesCpu {
+        self.cpu
+    }
+
+    pub fn get_rom_hash(&self) -> u64 {
+        self.rom_hash
+    }
+
+    // From the rom header, frontends may override it
+    pub fn get_region(&self) -> Region {
+        self.region
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    // Frames since power on, counted from the cpu cycles as there's no ppu to count them yet
+    pub fn get_frame_counter(&self) -> usize {
+        self.region.get_frame(self.cpu.get_cycle_counter())
+    }
+
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+    }
+
+    // A mask of the consts::BUTTON_ values, port 0 is the first controller
+    pub fn set_buttons(&mut self, port: usize, buttons: u8) {
+        self.cpu.get_bus_mut().get_controller_mut(port).set_buttons(buttons);
+    }
+
+    pub fn get_buttons(&self, port: usize) -> u8 {
+        self.cpu.get_bus().get_controller(port).get_buttons()
+    }
+
+    // Runs until the next frame starts
+    pub fn run_frame(&mut self) -> Result<(), NesError> {
+        self.audio_samples.clear();
+
+        let start_cycle = self.cpu.get_cycle_counter();
+        let region = self.region;
+        let next_frame = self.get_frame_counter() + 1;
+        let result = self.run_while(|cpu| region.get_frame(cpu.get_cycle_counter()) < next_frame);
+        self.add_audio_samples(self.cpu.get_cycle_counter() - start_cycle);
+
+        result
+    }
+
+    // Runs whole instructions until at least the given cycles passed, returns the cycles that ran
+    pub fn run_cycles(&mut self, cycles: usize) -> Result<usize, NesError> {
+        self.audio_samples.clear();
+
+        let start_cycle = self.cpu.get_cycle_counter();
+        let result = self.run_while(|cpu| cpu.get_cycle_counter() - start_cycle < cycles);
+        let cycles_run = self.cpu.get_cycle_counter() - start_cycle;
+        self.add_audio_samples(cycles_run);
+
+        result.map(|_| cycles_run)
+    }
+
+    // Runs with the tracer until it stops or the frame counter reaches frame_limit, returns the tracer
+    pub fn run_traced(&mut self, mut tracer: Tracer, frame_limit: usize) -> (Tracer, Result<(), NesError>) {
+        tracer.set_region(self.region);
+        self.cpu.set_tracer(Some(tracer));
+
+        let region = self.region;
+        let result = self.run_while(|cpu| region.get_frame(cpu.get_cycle_counter()) < frame_limit
+            && !cpu.get_tracer().is_some_and(Tracer::is_stopped));
+
+        (self.cpu.take_tracer().unwrap(), result)
+    }
+
+    fn run_while(&mut self, should_run: impl Fn(&NesCpu) -> bool) -> Result<(), NesError> {
+        while should_run(&self.cpu) {
+            self.cpu.execute_instruction().map_err(NesError::CpuFailed)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_audio_samples(&mut self, cycles: usize) {
+        self.audio_cycle_remainder += cycles * consts::AUDIO_SAMPLE_RATE;
+
+        let cycles_per_second = self.region.get_cpu_cycles_per_second();
+        let sample_count = self.audio_cycle_remainder / cycles_per_second;
+        self.audio_cycle_remainder %= cycles_per_second;
+        self.audio_samples.resize(self.audio_samples.len() + sample_count, 0.0);
+    }
+
+    // FRAME_WIDTH * FRAME_HEIGHT RGBA pixels, row by row. Always opaque black, nothing draws without a ppu
+    pub fn get_framebuffer(&self) -> &[u8] {
+        &self.framebuffer
+    }
+
+    // Mono samples at consts::AUDIO_SAMPLE_RATE from the last run_frame or run_cycles.
+    // All zeros, there is no apu, only the count follows the emulated time
+    pub fn get_audio_samples(&self) -> &[f32] {
+        &self.audio_samples
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        savestate::save(&self.cpu, self.rom_hash)
+    }
+
+    // Fails without changing the console when the state doesn't load
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), NesError> {
+        savestate::load(&mut self.cpu, state, self.rom_hash).map_err(NesError::SaveStateFailed)
+    }
+
+    // The battery backed prg ram, None when the mapper has none
+    pub fn get_save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        self.cpu.get_bus_mut().get_bus_mut().get_prg_ram_mut().map(Byte::as_u8_slice_mut)
+    }
+
+    // The 2KB of internal ram
+    pub fn get_system_ram_mut(&mut self) -> Option<&mut [u8]> {
+        self.cpu.get_bus_mut().get_bus_mut().get_internal_ram_mut().map(Byte::as_u8_slice_mut)
+    }
+
+    // Reads memory without side effects
+    pub fn peek_memory(&self, addr: u16) -> u8 {
+        self.cpu.peek_memory_addr(Double::from(addr)).get_value()
+    }
+}
+
+#[cfg(test)]
+pub fn get_test_rom() -> Vec<u8> {
+    // An NROM image with one prg bank that counts in $10 forever
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+    let mut prg_rom = vec![0u8; consts::INES_PRG_ROM_BANK_SIZE];
+    prg_rom[..5].copy_from_slice(&[0xE6, 0x10, 0x4C, 0x00, 0xC0]); // INC $10 ; JMP $C000
+    prg_rom[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0xC0]);
+    rom.extend_from_slice(&prg_rom);
+
+    rom
+}
+
+#[test]
+fn nes_facade() {
+    let rom = get_test_rom();
+    let mut nes = Nes::new(&rom).unwrap();
+    assert_eq!(nes.get_framebuffer().len(), consts::FRAME_WIDTH * consts::FRAME_HEIGHT * 4);
+
+    nes.run_frame().unwrap();
+    assert_eq!(nes.get_frame_counter(), 1);
+    assert_ne!(nes.peek_memory(0x10), 0);
+    // A frame is a 60th of a second
+    assert!((730..=740).contains(&nes.get_audio_samples().len()), "{}", nes.get_audio_samples().len());
+
+    nes.set_buttons(0, consts::BUTTON_A);
+    assert_eq!(nes.get_buttons(0), consts::BUTTON_A);
+
+    let state = nes.save_state();
+    let counter = nes.peek_memory(0x10);
+    let cycles = nes.run_cycles(1000).unwrap();
+    assert!(cycles >= 1000);
+    assert_ne!(nes.peek_memory(0x10), counter);
+
+    nes.load_state(&state).unwrap();
+    assert_eq!(nes.peek_memory(0x10), counter);
+    assert!(matches!(nes.load_state(&state[..4]), Err(NesError::SaveStateFailed(_))));
+
+    assert_eq!(nes.get_system_ram_mut().unwrap()[0x10], counter);
+    assert_eq!(nes.get_save_ram_mut().unwrap().len(), 0x2000);
+
+    // PAL frames are longer
+    assert_eq!(nes.get_region(), Region::Ntsc);
+    nes.set_region(Region::Pal);
+    nes.run_frame().unwrap();
+    let start_cycle = nes.get_cpu().get_cycle_counter();
+    nes.run_frame().unwrap();
+    assert!(nes.get_cpu().get_cycle_counter() - start_cycle > 33200);
+
+    assert!(matches!(Nes::new(&rom[..8]), Err(NesError::InvalidRom(_))));
+    assert!(matches!(Nes::new(&rom[..0x100]), Err(NesError::InvalidRom(_))));
+}
+
+#[test]
+fn nes_run_traced() {
+    use crate::tracer::{TraceFormat, TraceOutput, TraceTrigger};
+
+    // Without a stop trigger the frame limit ends the run
+    let mut nes = Nes::new(&get_test_rom()).unwrap();
+    let (tracer, result) = nes.run_traced(Tracer::new(TraceFormat::Nestest, TraceOutput::ring_buffer(4)), 2);
+    result.unwrap();
+    assert_eq!(nes.get_frame_counter(), 2);
+    assert_eq!(tracer.get_lines().len(), 4);
+    assert!(nes.get_cpu().get_tracer().is_none());
+
+    let mut tracer = Tracer::new(TraceFormat::Nestest, TraceOutput::ring_buffer(4));
+    tracer.set_stop_trigger(TraceTrigger::Frame(1));
+    let mut nes = Nes::new(&get_test_rom()).unwrap();
+    let (tracer, result) = nes.run_traced(tracer, 2);
+    result.unwrap();
+    assert!(tracer.is_stopped());
+    assert_eq!(nes.get_frame_counter(), 1);
+}
@@ -0,0 +1,82 @@
+pub mod console;
+
+use std::fmt;
+
+use crate::core::consts;
+use crate::cpu::CpuError;
+use crate::rom_parser::ParserError;
+use crate::savestate::SaveStateError;
+
+pub use console::Nes;
+
+#[derive(Debug)]
+pub enum NesError {
+    InvalidRom(ParserError),
+    SaveStateFailed(SaveStateError),
+    CpuFailed(CpuError),
+}
+
+impl fmt::Display for NesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NesError::InvalidRom(err) => write!(f, "Invalid rom : {:?}", err),
+            NesError::SaveStateFailed(err) => write!(f, "Save state failed : {}", err),
+            NesError::CpuFailed(err) => write!(f, "Cpu failed : {:?}", err),
+        }
+    }
+}
+
+// The tv standard the console runs at, it sets the frame length and the cpu clock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Region {
+    Ntsc,
+    Pal,
+}
+
+impl Region {
+    pub fn get_cpu_cycles_per_second(&self) -> usize {
+        match self {
+            Region::Ntsc => consts::CPU_CYCLES_PER_SECOND,
+            Region::Pal => consts::PAL_CPU_CYCLES_PER_SECOND,
+        }
+    }
+
+    // Ppu dots per pair of frames and per cpu cycle, as fractions so PAL's 3.2 stays exact.
+    // NTSC skips a dot on odd frames, PAL doesn't.
+    fn get_dots_per_frame_pair_and_cycle(&self) -> (usize, usize, usize) {
+        match self {
+            Region::Ntsc => (2 * consts::PPU_DOTS_PER_SCANLINE * consts::PPU_SCANLINES_PER_FRAME - 1, consts::PPU_DOTS_PER_CPU_CYCLE, 1),
+            Region::Pal => (2 * consts::PPU_DOTS_PER_SCANLINE * consts::PAL_PPU_SCANLINES_PER_FRAME,
+                consts::PAL_PPU_DOTS_PER_CPU_CYCLE_NUMERATOR, consts::PAL_PPU_DOTS_PER_CPU_CYCLE_DENOMINATOR),
+        }
+    }
+
+    // The frame a cycle counter is in, frame 0 is even so it has every dot
+    pub fn get_frame(&self, cycle_counter: usize) -> usize {
+        let (dots_per_frame_pair, dots_numerator, dots_denominator) = self.get_dots_per_frame_pair_and_cycle();
+        let dots = cycle_counter * dots_numerator / dots_denominator;
+        let even_frame_dots = dots_per_frame_pair.div_ceil(2);
+
+        2 * (dots / dots_per_frame_pair) + (dots % dots_per_frame_pair >= even_frame_dots) as usize
+    }
+
+    pub fn get_frames_per_second(&self) -> f64 {
+        let (dots_per_frame_pair, dots_numerator, dots_denominator) = self.get_dots_per_frame_pair_and_cycle();
+        (2 * self.get_cpu_cycles_per_second() * dots_numerator) as f64 / (dots_denominator * dots_per_frame_pair) as f64
+    }
+}
+
+#[test]
+fn region_timing() {
+    assert!((Region::Ntsc.get_frames_per_second() - 60.0988).abs() < 0.0001);
+    assert!((Region::Pal.get_frames_per_second() - 50.0070).abs() < 0.0001);
+
+    // NTSC frames are 29780.67 cycles, or 29780.33 on odd frames which skip a dot. PAL ones are 33247.5
+    assert_eq!(Region::Ntsc.get_frame(29780), 0);
+    assert_eq!(Region::Ntsc.get_frame(29781), 1);
+    assert_eq!(Region::Ntsc.get_frame(59560), 1);
+    assert_eq!(Region::Ntsc.get_frame(59561), 2);
+    assert_eq!(Region::Ntsc.get_frame(29780 * 1000 + 500), 1000);
+    assert_eq!(Region::Pal.get_frame(33247), 0);
+    assert_eq!(Region::Pal.get_frame(33248), 1);
+}
@@ -1,11 +1,18 @@
-#[test]
-fn nestest_rom() {
-    use std::fs::File;
-    use std::io::Read;
+use std::fs::File;
+use std::io::Read;
+
+use crate::core::consts;
+use crate::rom_parser::ines::InesRom;
+use crate::cpu::cpu::Cpu;
+use crate::tracer::get_nestest_trace_line;
 
-    use crate::rom_parser::ines::InesRom;
-    use crate::cpu::cpu::Cpu;
+// Lines of the golden log shown before the first mismatching line
+const NESTEST_LOG_CONTEXT_LINES: usize = 5;
 
+// The whole rom takes about 9000 instructions, leave some headroom
+const NESTEST_MAX_INSTRUCTIONS: usize = 10000;
+
+fn get_nestest_cpu() -> Cpu {
     let target_rom_path = std::path::Path::new("samples").join("nestest.nes");
     let mut file = File::open(target_rom_path).unwrap();
     let mut rom_buffer = Vec::<u8>::new();
@@ -17,38 +24,75 @@ fn nestest_rom() {
         Ok(m) => m,
         Err(err) => panic!("Failed getting mapper from rom parser : {:?}", err),
     };
-    
-    let cpu_result = Cpu::new(mapper);
 
-    if cpu_result.is_err() {
-        panic!("Failed creating cpu instance : {:?}", cpu_result.unwrap_err());
+    Cpu::new(mapper)
+}
+
+// Returns the column of the first differing character
+fn get_first_different_column(expected: &str, actual: &str) -> usize {
+    expected.chars().zip(actual.chars())
+        .position(|(expected_char, actual_char)| expected_char != actual_char)
+        .unwrap_or_else(|| std::cmp::min(expected.len(), actual.len()))
+}
+
+fn format_divergence(expected_lines: &[&str], line_index: usize, actual: &str) -> String {
+    let context_start = line_index.saturating_sub(NESTEST_LOG_CONTEXT_LINES);
+    let expected = expected_lines[line_index];
+
+    let mut report = format!("nestest.log diverged at line {}\n", line_index + 1);
+    for line in &expected_lines[context_start..line_index] {
+        report += &format!("           {}\n", line);
     }
 
-    let mut cpu = cpu_result.unwrap();
-
-    loop {
-        let instruction_out = cpu.execute_instruction();
-        if instruction_out.is_err() {
-            let cpu_error = instruction_out.unwrap_err();
-            log::info!("Stopping execution due to error {:?}", cpu_error);
-            match cpu_error {
-                crate::cpu::CpuError::StackEmpty => {
-                    if cpu.get_program_counter().get_value() == 0xC66Eu16 {
-                        log::info!("Test PASSED");
-                        break;
-                    } else {
-                        log::error!("Test FAILED");
-                        return;
-                    }
-                },
-                _ => {
-                    log::error!("Test FAILED");
-                    return;
-                }
-            }
+    report += &format!("expected : {}\n", expected);
+    report += &format!("actual   : {}\n", actual);
+    report += &format!("           {}^", " ".repeat(get_first_different_column(expected, actual)));
+
+    report
+}
+
+#[test]
+fn nestest_rom() {
+    let mut cpu = get_nestest_cpu();
+
+    for _ in 0..NESTEST_MAX_INSTRUCTIONS {
+        // The tests end with an RTS from an empty stack
+        if cpu.get_program_counter().get_value() == 0xC66Eu16 && cpu.get_stack_pointer().get_value() == consts::STACK_SIZE {
+            break;
+        }
+
+        if let Err(cpu_error) = cpu.execute_instruction() {
+            panic!("Cpu error {:?} at {}", cpu_error, cpu.get_program_counter());
         }
     }
 
-    log::info!("Memory addr 0x02 : {}", cpu.get_memory_addr(0x02u16.into()));
-    log::info!("Memory addr 0x03 : {}", cpu.get_memory_addr(0x03u16.into()));
-}
\ No newline at end of file
+    assert_eq!(cpu.get_program_counter().get_value(), 0xC66Eu16, "Didn't finish within {} instructions", NESTEST_MAX_INSTRUCTIONS);
+    assert_eq!(cpu.get_stack_pointer().get_value(), consts::STACK_SIZE);
+
+    // $02 and $03 hold the codes of the first failed official and unofficial opcode tests
+    assert_eq!(cpu.get_memory_addr(0x02u16.into()).get_value(), 0x00);
+    assert_eq!(cpu.get_memory_addr(0x03u16.into()).get_value(), 0x00);
+}
+
+#[test]
+fn nestest_log() {
+    let mut golden_log = String::new();
+    File::open(std::path::Path::new("samples").join("nestest.log")).unwrap()
+        .read_to_string(&mut golden_log).unwrap();
+    let expected_lines: Vec<&str> = golden_log.lines().collect();
+
+    // Automation mode, runs all the tests without a ppu
+    let mut cpu = get_nestest_cpu();
+    cpu.set_program_counter(0xC000u16.into());
+
+    for (line_index, expected) in expected_lines.iter().enumerate() {
+        let actual = get_nestest_trace_line(&cpu);
+        if actual != *expected {
+            panic!("{}", format_divergence(&expected_lines, line_index, &actual));
+        }
+
+        if let Err(cpu_error) = cpu.execute_instruction() {
+            panic!("Cpu error {:?} after line {}\n{}", cpu_error, line_index + 1, expected);
+        }
+    }
+}
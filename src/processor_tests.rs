@@ -0,0 +1,290 @@
+// Runs Tom Harte's SingleStepTests (ProcessorTests) for the NES 6502
+// Test vectors are taken from : https://github.com/SingleStepTests/ProcessorTests/tree/main/nes6502
+//
+// The vectors are not shipped with the repo, place the `v1` directory (00.json .. ff.json) in
+// samples/ProcessorTests/nes6502/v1 or point NESSY_PROCESSOR_TESTS_DIR to it, then run `cargo test -- --ignored`.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::core::Byte;
+use crate::core::Double;
+use crate::cpu::StatusRegister;
+use crate::cpu::cpu::Cpu;
+use crate::cpu::instructions::{Instruction, get_instruction_set};
+use crate::mapper::{DebugMapper, BusAccessKind};
+
+const PROCESSOR_TESTS_DIR_ENV: &str = "NESSY_PROCESSOR_TESTS_DIR";
+
+// The B flag and bit 5 do not exist in the status register, only in the pushed copy of it
+const STATUS_COMPARE_MASK: u8 = 0xCF;
+
+// The cpu isn't cycle accurate, these are the ways its bus accesses knowingly differ from the hardware, keyed by
+// addressing mode. None of them changes a result : writes must still match and every read must be one the hardware
+// makes too, the cpu only skips reads the hardware throws away or repeats one. Anything else fails the opcode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BusInexactness {
+    // Implied and accumulator, no dummy read of the next byte, stack ops also skip the dummy stack read
+    ImpliedDummyRead,
+    // Branches, no dummy reads when taken or crossing a page
+    BranchDummyRead,
+    // Indexed modes, no dummy read of the unindexed address or of the address before the page fix up
+    IndexedDummyRead,
+    // Read modify write, the value is re-read and the dummy write of the old value is missing
+    ReadModifyWrite,
+    // JSR writes the return address before reading the high byte of the target and skips the dummy stack read,
+    // JMP ($nnnn) re-reads its operand
+    JumpOrder,
+    // The unofficial NOPs don't fetch their operand
+    UnofficialNopOperand,
+}
+
+const READ_MODIFY_WRITE_NAMES: [&str; 12] = ["ASL", "LSR", "ROL", "ROR", "INC", "DEC", "SLO", "RLA", "SRE", "RRA", "DCP", "ISC"];
+
+fn get_bus_inexactness(instruction: &Instruction) -> Option<BusInexactness> {
+    let name = instruction.name.as_str();
+    match instruction.mode.as_str() {
+        "Accumulator" | "Implied" => Some(BusInexactness::ImpliedDummyRead),
+        _ if READ_MODIFY_WRITE_NAMES.contains(&name) => Some(BusInexactness::ReadModifyWrite),
+        _ if name == "SKB" || name == "IGN" => Some(BusInexactness::UnofficialNopOperand),
+        _ if name == "JSR" || name == "JMP" && instruction.mode == "Indirect" => Some(BusInexactness::JumpOrder),
+        "Relative" => Some(BusInexactness::BranchDummyRead),
+        "ZeroPage,X" | "ZeroPage,Y" | "Absolute,X" | "Absolute,Y" | "(Indirect,X)" | "(Indirect),Y" => {
+            Some(BusInexactness::IndexedDummyRead)
+        },
+        _ => None,
+    }
+}
+
+type Access<'a> = (u16, u8, &'a str);
+
+// Whether the accesses only differ from the hardware's the way the inexactness allows
+fn is_inexact_bus_match(inexactness: BusInexactness, accesses: &[Access], expected_accesses: &[Access]) -> bool {
+    let writes: Vec<&Access> = accesses.iter().filter(|access| access.2 == "write").collect();
+    let mut expected_writes: Vec<&Access> = expected_accesses.iter().filter(|access| access.2 == "write").collect();
+    if inexactness == BusInexactness::ReadModifyWrite {
+        // The dummy write is the one followed by a write to the same address
+        let dummy_writes: Vec<usize> = (1..expected_writes.len())
+            .filter(|i| expected_writes[i - 1].0 == expected_writes[*i].0).map(|i| i - 1).collect();
+        for i in dummy_writes.into_iter().rev() {
+            expected_writes.remove(i);
+        }
+    }
+
+    let expected_reads: Vec<&Access> = expected_accesses.iter().filter(|access| access.2 == "read").collect();
+    let reads_match = accesses.iter().filter(|access| access.2 == "read").all(|read| expected_reads.contains(&read));
+
+    writes == expected_writes && reads_match
+}
+
+#[derive(Deserialize)]
+struct ProcessorState {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+#[derive(Deserialize)]
+struct ProcessorTest {
+    name: String,
+    initial: ProcessorState,
+    #[serde(rename = "final")]
+    final_state: ProcessorState,
+    cycles: Vec<(u16, u8, String)>,
+}
+
+struct OpcodeReport {
+    opcode: u8,
+    test_count: usize,
+    failures: Vec<String>,
+    inexactness: Option<BusInexactness>,
+    // Bus differences the inexactness allows, reported without failing
+    inexact_bus_accesses: Vec<String>,
+}
+
+fn get_processor_tests_dir() -> PathBuf {
+    match std::env::var(PROCESSOR_TESTS_DIR_ENV) {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => Path::new("samples").join("ProcessorTests").join("nes6502").join("v1"),
+    }
+}
+
+fn load_opcode_tests(path: &Path) -> Vec<ProcessorTest> {
+    let file = File::open(path).unwrap();
+    match serde_json::from_reader(BufReader::new(file)) {
+        Ok(tests) => tests,
+        Err(err) => panic!("Failed parsing test vectors {:?} : {}", path, err),
+    }
+}
+
+// Runs a single test vector, returns a description of every mismatch found and the allowed bus difference apart
+fn run_processor_test(test: &ProcessorTest, inexactness: Option<BusInexactness>) -> (Vec<String>, Option<String>) {
+    let mut mapper = DebugMapper::new();
+    for (addr, value) in &test.initial.ram {
+        mapper.load(*addr, &[*value]);
+    }
+
+    let bus_log = mapper.get_bus_log();
+    let mut cpu = Cpu::new(mapper);
+
+    cpu.set_program_counter(Double::from(test.initial.pc));
+    cpu.set_stack_pointer(Byte::new(test.initial.s));
+    cpu.set_reg_a(Byte::new(test.initial.a));
+    cpu.set_reg_x(Byte::new(test.initial.x));
+    cpu.set_reg_y(Byte::new(test.initial.y));
+    cpu.set_status(StatusRegister::from(Byte::new(test.initial.p)));
+
+    bus_log.borrow_mut().clear();
+    let start_cycle = cpu.get_cycle_counter();
+
+    let mut mismatches = Vec::<String>::new();
+    if let Err(err) = cpu.execute_instruction() {
+        mismatches.push(format!("Cpu error {:?}", err));
+        return (mismatches, None);
+    }
+
+    let accesses: Vec<(u16, u8, &str)> = bus_log.borrow().iter()
+        .map(|access| (access.addr.get_value(), access.value.get_value(), if access.kind == BusAccessKind::Write { "write" } else { "read" }))
+        .collect();
+    let expected_accesses: Vec<(u16, u8, &str)> = test.cycles.iter()
+        .map(|(addr, value, kind)| (*addr, *value, kind.as_str()))
+        .collect();
+
+    let expected = &test.final_state;
+    let registers = [
+        ("PC", cpu.get_program_counter().get_value(), expected.pc),
+        ("SP", cpu.get_stack_pointer().get_value() as u16, expected.s as u16),
+        ("A", cpu.get_reg_a().get_value() as u16, expected.a as u16),
+        ("X", cpu.get_reg_x().get_value() as u16, expected.x as u16),
+        ("Y", cpu.get_reg_y().get_value() as u16, expected.y as u16),
+        ("P", (Byte::from(cpu.get_status()).get_value() & STATUS_COMPARE_MASK) as u16,
+            (expected.p & STATUS_COMPARE_MASK) as u16),
+    ];
+
+    for (name, actual, expected) in registers.iter() {
+        if actual != expected {
+            mismatches.push(format!("{} is {:02X}, expected {:02X}", name, actual, expected));
+        }
+    }
+
+    for (addr, value) in &expected.ram {
+        let actual = cpu.get_memory_addr(Double::from(*addr)).get_value();
+        if actual != *value {
+            mismatches.push(format!("RAM[{:04X}] is {:02X}, expected {:02X}", addr, actual, value));
+        }
+    }
+
+    let cycles = cpu.get_cycle_counter() - start_cycle;
+    if cycles != test.cycles.len() {
+        mismatches.push(format!("Took {} cycles, expected {}", cycles, test.cycles.len()));
+    }
+
+    let mut inexact_bus_access = None;
+    if accesses != expected_accesses {
+        let bus_mismatch = format!("Bus accesses are {:X?}, expected {:X?}", accesses, expected_accesses);
+        match inexactness {
+            Some(inexactness) if is_inexact_bus_match(inexactness, &accesses, &expected_accesses) => {
+                inexact_bus_access = Some(bus_mismatch);
+            },
+            _ => mismatches.push(bus_mismatch),
+        }
+    }
+
+    (mismatches, inexact_bus_access)
+}
+
+fn run_opcode_tests(opcode: u8, inexactness: Option<BusInexactness>, tests: &[ProcessorTest]) -> OpcodeReport {
+    let mut report = OpcodeReport{opcode, test_count: tests.len(), failures: Vec::new(), inexactness,
+        inexact_bus_accesses: Vec::new()};
+
+    for test in tests {
+        let (mismatches, inexact_bus_access) = run_processor_test(test, inexactness);
+        if let Some(inexact_bus_access) = inexact_bus_access {
+            report.inexact_bus_accesses.push(format!("[{}] {}", test.name, inexact_bus_access));
+        }
+
+        if !mismatches.is_empty() {
+            report.failures.push(format!("[{}] {}", test.name, mismatches.join(", ")));
+        }
+    }
+
+    report
+}
+
+#[test]
+#[ignore = "needs the ProcessorTests vectors"]
+fn processor_tests() {
+    let tests_dir = get_processor_tests_dir();
+    if !tests_dir.is_dir() {
+        panic!("ProcessorTests not found at {:?} (set {})", tests_dir, PROCESSOR_TESTS_DIR_ENV);
+    }
+
+    let instruction_set = get_instruction_set();
+    let mut reports = Vec::<OpcodeReport>::new();
+    for opcode in 0x00..=0xFFu8 {
+        let opcode_path = tests_dir.join(format!("{:02x}.json", opcode));
+        if !opcode_path.is_file() {
+            continue;
+        }
+
+        let inexactness = instruction_set.get(&opcode).and_then(get_bus_inexactness);
+        reports.push(run_opcode_tests(opcode, inexactness, &load_opcode_tests(&opcode_path)));
+    }
+
+    for report in reports.iter().filter(|report| !report.inexact_bus_accesses.is_empty()) {
+        println!("Opcode {:02X} : bus accesses differ in {}/{} tests ({:?}), first : {}", report.opcode,
+            report.inexact_bus_accesses.len(), report.test_count, report.inexactness.unwrap(), report.inexact_bus_accesses[0]);
+    }
+
+    let failed_reports: Vec<&OpcodeReport> = reports.iter().filter(|report| !report.failures.is_empty()).collect();
+    for report in &failed_reports {
+        println!("Opcode {:02X} : {}/{} failed, first failure : {}", report.opcode, report.failures.len(),
+            report.test_count, report.failures[0]);
+    }
+
+    println!("ProcessorTests : {}/{} opcodes passed", reports.len() - failed_reports.len(), reports.len());
+
+    if !failed_reports.is_empty() {
+        let failed_opcodes: Vec<String> = failed_reports.iter().map(|report| format!("{:02X}", report.opcode)).collect();
+        panic!("ProcessorTests failed for opcodes : {}", failed_opcodes.join(" "));
+    }
+}
+
+#[test]
+fn processor_tests_bus_inexactness() {
+    let instruction_set = get_instruction_set();
+    let get_inexactness = |opcode: u8| get_bus_inexactness(&instruction_set[&opcode]);
+    assert_eq!(get_inexactness(0xA9), None);
+    assert_eq!(get_inexactness(0x8D), None);
+    assert_eq!(get_inexactness(0x4C), None);
+    assert_eq!(get_inexactness(0xE8), Some(BusInexactness::ImpliedDummyRead));
+    assert_eq!(get_inexactness(0xFE), Some(BusInexactness::ReadModifyWrite));
+    assert_eq!(get_inexactness(0x6C), Some(BusInexactness::JumpOrder));
+    assert_eq!(get_inexactness(0xBD), Some(BusInexactness::IndexedDummyRead));
+
+    // INC $10 : the hardware writes the old value back before the new one
+    let expected = [(0x0200, 0xE6, "read"), (0x0201, 0x10, "read"), (0x0010, 0x04, "read"), (0x0010, 0x04, "write"),
+        (0x0010, 0x05, "write")];
+    let skipped_dummy_write = [(0x0200, 0xE6, "read"), (0x0201, 0x10, "read"), (0x0010, 0x04, "read"), (0x0010, 0x04, "read"),
+        (0x0010, 0x05, "write")];
+    assert!(is_inexact_bus_match(BusInexactness::ReadModifyWrite, &skipped_dummy_write, &expected));
+
+    // A wrong value, an extra write or a read the hardware doesn't make still fail
+    let wrong_value = [(0x0200, 0xE6, "read"), (0x0201, 0x10, "read"), (0x0010, 0x04, "read"), (0x0010, 0x06, "write")];
+    assert!(!is_inexact_bus_match(BusInexactness::ReadModifyWrite, &wrong_value, &expected));
+    assert!(!is_inexact_bus_match(BusInexactness::IndexedDummyRead, &skipped_dummy_write, &expected));
+
+    // LDA $10,X with X = 1 : the hardware reads $10 before adding X
+    let expected = [(0x0200, 0xB5, "read"), (0x0201, 0x10, "read"), (0x0010, 0x00, "read"), (0x0011, 0x2A, "read")];
+    let skipped_dummy_read = [(0x0200, 0xB5, "read"), (0x0201, 0x10, "read"), (0x0011, 0x2A, "read")];
+    let wrong_addr = [(0x0200, 0xB5, "read"), (0x0201, 0x10, "read"), (0x0012, 0x2A, "read")];
+    assert!(is_inexact_bus_match(BusInexactness::IndexedDummyRead, &skipped_dummy_read, &expected));
+    assert!(!is_inexact_bus_match(BusInexactness::IndexedDummyRead, &wrong_addr, &expected));
+}
@@ -9,7 +9,8 @@ use crate::core::consts;
 use crate::cpu::cpu::Cpu;
 use crate::core::Byte;
 use crate::core::Double;
-use crate::mapper::{Mapper, NROMMapper};
+use crate::mapper::{Mapper, MapperError, NROMMapper, MMC1Mapper};
+use crate::savestate;
 
 #[derive(Debug)]
 enum MirroringMode {
@@ -18,7 +19,7 @@ enum MirroringMode {
     Invalid
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum TVSystem {
     Ntsc,
     Pal,
@@ -73,6 +74,11 @@ impl InesRom {
             chr_rom_content: Vec::new(), trainer_content: Vec::new(), prg_rom_entry_addr: 0xC000};
 
             
+            if rom.rom_content.len() < 0x10 {
+                log::error!("Rom is shorter than the INES header");
+                return Err(ParserError::InvalidRom);
+            }
+
             let header: Vec<u8> = rom.rom_content[0..0x10].to_vec();
             if header[0] != ('N' as u8) || header[1] != ('E' as u8) || header[2] != ('S' as u8) {
                 log::error!("Invalid rom header");
@@ -81,7 +87,7 @@ impl InesRom {
                 log::debug!("Valid INES header found");
             }
             
-        rom.prg_rom_size = header[4] as usize * 0x4000;
+        rom.prg_rom_size = header[4] as usize * consts::INES_PRG_ROM_BANK_SIZE;
         rom.chr_rom_size = header[5] as usize * 0x2000;
 
         if rom.chr_rom_size == 0 {
@@ -153,24 +159,47 @@ impl InesRom {
         log::debug!("Parsing INES Rom content");
         let mut rom_index = 0x10;
 
+        let trainer_size = if rom.contains_trainer { 0x200 } else { 0 };
+        if rom.rom_content.len() < rom_index + trainer_size + rom.prg_rom_size {
+            log::error!("Rom is shorter than its prg rom size");
+            return Err(ParserError::InvalidRom);
+        }
+
         if rom.contains_trainer {
             rom.trainer_content = rom.rom_content[rom_index..rom_index + 0x200].to_vec();
             rom_index += 0x200;
         }
 
         rom.prg_rom_content = rom.rom_content[rom_index..rom_index + rom.prg_rom_size as usize].to_vec();
-        log::trace!("First bytes of prg rom : {:X?}", rom.prg_rom_content[0..3].to_vec());
+        log::trace!("First bytes of prg rom : {:X?}", rom.prg_rom_content.iter().take(3).collect::<Vec<_>>());
 
         log::debug!("INES Parser : {:?}", rom);
 
         Ok(rom)
     }
 
+    pub fn is_pal(&self) -> bool {
+        self.tv_system == TVSystem::Pal
+    }
+
+    pub fn get_prg_rom_content(&self) -> &[u8] {
+        &self.prg_rom_content
+    }
+
+    // Identifies the rom in save states
+    pub fn get_hash(&self) -> u64 {
+        savestate::get_rom_hash(&self.rom_content)
+    }
+
     pub fn get_mapper(&self) -> Result<Box<dyn Mapper>, ParserError> {
         match self.mapper {
             consts::NROM_MAPPER_ID => {
-                let mapper_struct = Box::new(NROMMapper::new(&self.prg_rom_content, self.prg_ram_size));
-                Ok(mapper_struct)
+                let mapper_struct = NROMMapper::new(&self.prg_rom_content, self.prg_ram_size).map_err(get_mapper_error)?;
+                Ok(Box::new(mapper_struct))
+            },
+            consts::MMC1_MAPPER_ID => {
+                let mapper_struct = MMC1Mapper::new(&self.prg_rom_content, self.prg_ram_size).map_err(get_mapper_error)?;
+                Ok(Box::new(mapper_struct))
             },
             _ => {
                 Err(ParserError::UnknownMapperID(self.mapper))
@@ -178,3 +207,9 @@ impl InesRom {
         }
     }
 }
+
+// The mapper rejected the rom's layout
+fn get_mapper_error(err: MapperError) -> ParserError {
+    log::error!("Rom doesn't fit its mapper : {:?}", err);
+    ParserError::InvalidRom
+}
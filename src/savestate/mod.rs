@@ -0,0 +1,41 @@
+pub mod state;
+pub mod rewind;
+
+use std::fmt;
+use std::io;
+
+pub use state::{StateReader, StateWriter, save, load, get_rom_hash};
+#[cfg(not(target_arch = "wasm32"))]
+pub use state::{save_to_file, load_from_file};
+pub use rewind::RewindBuffer;
+
+#[derive(Debug)]
+pub enum SaveStateError {
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    // The state was saved while running another rom
+    RomMismatch{expected: u64, found: u64},
+    UnexpectedEnd,
+    InvalidValue(&'static str),
+    Io(io::Error),
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveStateError::InvalidMagic => write!(f, "Not a nessy save state"),
+            SaveStateError::UnsupportedVersion(version) => write!(f, "Unsupported save state version {}", version),
+            SaveStateError::RomMismatch{expected, found} =>
+                write!(f, "Save state is for rom {:016X}, the running rom is {:016X}", found, expected),
+            SaveStateError::UnexpectedEnd => write!(f, "Save state is truncated"),
+            SaveStateError::InvalidValue(name) => write!(f, "Invalid {} in save state", name),
+            SaveStateError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for SaveStateError {
+    fn from(err: io::Error) -> SaveStateError {
+        SaveStateError::Io(err)
+    }
+}
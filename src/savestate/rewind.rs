@@ -0,0 +1,243 @@
+use std::collections::VecDeque;
+
+use super::SaveStateError;
+use super::state::{save, load};
+
+use crate::cpu::CpuBus;
+use crate::cpu::cpu::Cpu;
+use crate::nes::Region;
+
+// The snapshots never leave the buffer, they don't need to identify the rom
+const REWIND_ROM_HASH: u64 = 0;
+
+fn write_varint(data: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        data.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+fn read_varint(data: &[u8], position: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let b = data[*position];
+        *position += 1;
+        value |= ((b & 0x7F) as usize) << shift;
+        if b & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+// Xors the states and run length encodes the zeros, most of the machine doesn't change between two snapshots.
+// The delta is runs of unchanged bytes followed by changed bytes, each run starting with the two lengths.
+fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::<u8>::new();
+    write_varint(&mut delta, to.len());
+
+    let xored: Vec<u8> = (0..to.len()).map(|i| to[i] ^ from.get(i).copied().unwrap_or(0)).collect();
+    let mut i = 0;
+    while i < xored.len() {
+        let unchanged_start = i;
+        while i < xored.len() && xored[i] == 0 {
+            i += 1;
+        }
+        let changed_start = i;
+        while i < xored.len() && xored[i] != 0 {
+            i += 1;
+        }
+
+        write_varint(&mut delta, changed_start - unchanged_start);
+        write_varint(&mut delta, i - changed_start);
+        delta.extend_from_slice(&xored[changed_start..i]);
+    }
+
+    delta
+}
+
+fn apply_delta(from: &[u8], delta: &[u8]) -> Vec<u8> {
+    let mut position = 0;
+    let length = read_varint(delta, &mut position);
+    let mut state: Vec<u8> = (0..length).map(|i| from.get(i).copied().unwrap_or(0)).collect();
+
+    let mut i = 0;
+    while position < delta.len() {
+        i += read_varint(delta, &mut position);
+        let changed = read_varint(delta, &mut position);
+        for b in &delta[position..position + changed] {
+            state[i] ^= b;
+            i += 1;
+        }
+        position += changed;
+    }
+
+    state
+}
+
+struct Snapshot {
+    frame: usize,
+    // The full state for the newest snapshot, the delta to the older snapshot from the next one for the others
+    data: Vec<u8>,
+}
+
+// Snapshots the machine every few frames so it can be stepped back in time.
+// Only the newest snapshot is kept whole, the older ones are deltas going backwards from it,
+// and the oldest are dropped once the buffer goes over its memory budget.
+pub struct RewindBuffer {
+    // Sets the frame length
+    region: Region,
+    interval_frames: usize,
+    memory_budget: usize,
+    // Oldest first
+    snapshots: VecDeque<Snapshot>,
+    memory_used: usize,
+    next_capture_frame: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(region: Region, interval_frames: usize, memory_budget: usize) -> RewindBuffer {
+        RewindBuffer{region, interval_frames: std::cmp::max(interval_frames, 1), memory_budget, snapshots: VecDeque::new(),
+            memory_used: 0, next_capture_frame: 0}
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn get_memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    // The frames the buffer can rewind to, oldest first
+    pub fn get_frames(&self) -> Vec<usize> {
+        self.snapshots.iter().map(|snapshot| snapshot.frame).collect()
+    }
+
+    // Called after every instruction, snapshots the machine once a new capture frame is reached
+    pub fn capture_if_due<B: CpuBus>(&mut self, cpu: &Cpu<B>) {
+        if self.region.get_frame(cpu.get_cycle_counter()) >= self.next_capture_frame {
+            self.capture(cpu);
+        }
+    }
+
+    pub fn capture<B: CpuBus>(&mut self, cpu: &Cpu<B>) {
+        let frame = self.region.get_frame(cpu.get_cycle_counter());
+        let state = save(cpu, REWIND_ROM_HASH);
+
+        // The previous newest snapshot becomes a delta from this one
+        if let Some(newest) = self.snapshots.back_mut() {
+            let delta = encode_delta(&state, &newest.data);
+            self.memory_used = self.memory_used - newest.data.len() + delta.len();
+            newest.data = delta;
+        }
+
+        self.memory_used += state.len();
+        self.snapshots.push_back(Snapshot{frame, data: state});
+        self.next_capture_frame = frame + self.interval_frames;
+
+        // The newest snapshot is always kept, even when it's over the budget alone
+        while self.memory_used > self.memory_budget && self.snapshots.len() > 1 {
+            let oldest = self.snapshots.pop_front().unwrap();
+            self.memory_used -= oldest.data.len();
+        }
+    }
+
+    // Loads the newest snapshot and drops it, running the cpu again resumes from there.
+    // Returns the frame the machine went back to, None once the buffer is empty.
+    pub fn rewind<B: CpuBus>(&mut self, cpu: &mut Cpu<B>) -> Result<Option<usize>, SaveStateError> {
+        let newest = match self.snapshots.pop_back() {
+            Some(newest) => newest,
+            None => return Ok(None),
+        };
+        self.memory_used -= newest.data.len();
+
+        if let Some(previous) = self.snapshots.back_mut() {
+            let state = apply_delta(&newest.data, &previous.data);
+            self.memory_used = self.memory_used - previous.data.len() + state.len();
+            previous.data = state;
+        }
+
+        if let Err(err) = load(cpu, &newest.data, REWIND_ROM_HASH) {
+            self.clear();
+            return Err(err);
+        }
+        // Captured again once the machine resumes, so the next rewind can come back to it
+        self.next_capture_frame = newest.frame;
+
+        Ok(Some(newest.frame))
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.memory_used = 0;
+        self.next_capture_frame = 0;
+    }
+}
+
+#[test]
+fn rewind_delta_encoding() {
+    let from = [0u8, 1, 2, 3, 4, 5, 6, 7];
+    let to = [0u8, 1, 9, 3, 4, 5, 6, 8, 10];
+
+    let delta = encode_delta(&from, &to);
+    assert_eq!(delta, vec![9, 2, 1, 2 ^ 9, 4, 2, 7 ^ 8, 10]);
+    assert_eq!(apply_delta(&from, &delta), to.to_vec());
+    assert_eq!(apply_delta(&to, &encode_delta(&to, &from)), from.to_vec());
+}
+
+#[test]
+fn rewind_frames() {
+    use crate::core::Double;
+
+    // INC $10 ; JMP $0200, about 4 frames per $100 increments
+    let mut mapper = crate::mapper::DebugMapper::new();
+    mapper.load(0x0200, &[0xE6, 0x10, 0x4C, 0x00, 0x02]);
+    let mut cpu = Cpu::new_with_entry_point(mapper, Double::from(0x0200u16));
+
+    let mut rewind = RewindBuffer::new(Region::Ntsc, 2, 1 << 20);
+    let mut counters = Vec::<(usize, u8)>::new();
+    while Region::Ntsc.get_frame(cpu.get_cycle_counter()) < 20 {
+        let before = rewind.len();
+        rewind.capture_if_due(&cpu);
+        if rewind.len() != before {
+            counters.push((Region::Ntsc.get_frame(cpu.get_cycle_counter()), cpu.get_memory_addr(Double::from(0x10u16)).get_value()));
+        }
+        cpu.execute_instruction().unwrap();
+    }
+
+    assert_eq!(rewind.get_frames(), vec![0, 2, 4, 6, 8, 10, 12, 14, 16, 18]);
+    // A whole state is 64KB of memory, the deltas only hold the counter and the registers
+    assert!(rewind.get_memory_used() < 0x10000 + 9 * 0x20, "{}", rewind.get_memory_used());
+
+    for (frame, counter) in counters.iter().rev().take(3) {
+        assert_eq!(rewind.rewind(&mut cpu).unwrap(), Some(*frame));
+        assert_eq!(cpu.get_memory_addr(Double::from(0x10u16)).get_value(), *counter);
+    }
+
+    // Resuming captures again from the frame the machine went back to
+    while Region::Ntsc.get_frame(cpu.get_cycle_counter()) < 16 {
+        rewind.capture_if_due(&cpu);
+        cpu.execute_instruction().unwrap();
+    }
+    assert_eq!(rewind.get_frames(), vec![0, 2, 4, 6, 8, 10, 12, 14]);
+
+    // Going over the budget drops the oldest snapshots
+    // Deltas between identical states are 7 bytes, the budget fits the whole state and one of them
+    let state_size = save(&cpu, REWIND_ROM_HASH).len();
+    let mut small = RewindBuffer::new(Region::Ntsc, 1, state_size + 8);
+    for _ in 0..4 {
+        small.capture(&cpu);
+    }
+    assert_eq!(small.len(), 2);
+    assert_eq!(small.get_memory_used(), state_size + 7);
+
+    while rewind.rewind(&mut cpu).unwrap().is_some() {}
+    assert_eq!(cpu.get_cycle_counter(), 7);
+}
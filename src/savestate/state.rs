@@ -0,0 +1,206 @@
+use super::SaveStateError;
+
+use crate::core::Byte;
+use crate::core::consts;
+use crate::core::get_fnv1a_hash;
+use crate::cpu::CpuBus;
+use crate::cpu::cpu::Cpu;
+
+// Magic, version and rom hash
+const HEADER_SIZE: usize = 4 + 2 + 8;
+
+// Little endian values appended one after the other, the reader takes them back in the same order.
+// Every part of the machine writes its own fields, the layout is only known by its save and load functions.
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl Default for StateWriter {
+    fn default() -> StateWriter {
+        StateWriter::new()
+    }
+}
+
+impl StateWriter {
+    pub fn new() -> StateWriter {
+        StateWriter{data: Vec::new()}
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // Length prefixed
+    pub fn write_bytes(&mut self, bytes: &[Byte]) {
+        self.data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        self.data.extend(bytes.iter().map(|b| b.get_value()));
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+pub struct StateReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> StateReader<'a> {
+        StateReader{data, position: 0}
+    }
+
+    fn read_slice(&mut self, length: usize) -> Result<&'a [u8], SaveStateError> {
+        let slice = self.data.get(self.position..self.position + length).ok_or(SaveStateError::UnexpectedEnd)?;
+        self.position += length;
+
+        Ok(slice)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, SaveStateError> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, SaveStateError> {
+        let bytes = self.read_slice(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, SaveStateError> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.read_slice(8)?);
+
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    // Reads bytes written by write_bytes, the length must match what the caller expects
+    pub fn read_bytes(&mut self, expected_length: usize, name: &'static str) -> Result<Vec<Byte>, SaveStateError> {
+        let mut length = [0u8; 4];
+        length.copy_from_slice(self.read_slice(4)?);
+        if u32::from_le_bytes(length) as usize != expected_length {
+            return Err(SaveStateError::InvalidValue(name));
+        }
+
+        Ok(self.read_slice(expected_length)?.iter().map(|b| Byte::new(*b)).collect())
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.position == self.data.len()
+    }
+}
+
+// Over the whole rom file, identifies the rom a state was saved with
+pub fn get_rom_hash(rom_content: &[u8]) -> u64 {
+    get_fnv1a_hash(rom_content)
+}
+
+// Snapshots the cpu and everything on its bus
+pub fn save<B: CpuBus>(cpu: &Cpu<B>, rom_hash: u64) -> Vec<u8> {
+    let mut state = StateWriter::new();
+    state.data.extend_from_slice(consts::SAVE_STATE_MAGIC);
+    state.write_u16(consts::SAVE_STATE_VERSION);
+    state.write_u64(rom_hash);
+
+    cpu.save_state(&mut state);
+    cpu.get_bus().save_state(&mut state);
+
+    state.into_data()
+}
+
+// Restores a snapshot taken by save, the header is checked before anything changes
+pub fn load<B: CpuBus>(cpu: &mut Cpu<B>, data: &[u8], rom_hash: u64) -> Result<(), SaveStateError> {
+    let mut state = StateReader::new(data);
+    if state.read_slice(consts::SAVE_STATE_MAGIC.len()).ok() != Some(&consts::SAVE_STATE_MAGIC[..]) {
+        return Err(SaveStateError::InvalidMagic);
+    }
+
+    let version = state.read_u16()?;
+    if version != consts::SAVE_STATE_VERSION {
+        return Err(SaveStateError::UnsupportedVersion(version));
+    }
+
+    let found = state.read_u64()?;
+    if found != rom_hash {
+        return Err(SaveStateError::RomMismatch{expected: rom_hash, found});
+    }
+
+    // A state that breaks halfway would leave the machine half restored, the current state is put back
+    let backup = save(cpu, rom_hash);
+    let result = load_machine(cpu, &mut state);
+    if result.is_err() {
+        load_machine(cpu, &mut StateReader::new(&backup[HEADER_SIZE..]))
+            .expect("Failed restoring the state saved before loading");
+    }
+
+    result
+}
+
+fn load_machine<B: CpuBus>(cpu: &mut Cpu<B>, state: &mut StateReader) -> Result<(), SaveStateError> {
+    cpu.load_state(state)?;
+    cpu.get_bus_mut().load_state(state)?;
+
+    if !state.is_at_end() {
+        return Err(SaveStateError::InvalidValue("trailing data"));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_to_file<B: CpuBus>(cpu: &Cpu<B>, rom_hash: u64, path: &str) -> Result<(), SaveStateError> {
+    Ok(std::fs::write(path, save(cpu, rom_hash))?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_from_file<B: CpuBus>(cpu: &mut Cpu<B>, rom_hash: u64, path: &str) -> Result<(), SaveStateError> {
+    load(cpu, &std::fs::read(path)?, rom_hash)
+}
+
+#[test]
+fn save_state_round_trip() {
+    use crate::core::Double;
+    use crate::tracer::get_nestest_trace_line;
+
+    // LDX #$00 ; INX ; STX $10 ; CPX #$05 ; BNE $0202 ; BRK
+    let mut mapper = crate::mapper::DebugMapper::new();
+    mapper.load(0x0200, &[0xA2, 0x00, 0xE8, 0x86, 0x10, 0xE0, 0x05, 0xD0, 0xF9, 0x00]);
+    let mut cpu = Cpu::new_with_entry_point(mapper, Double::from(0x0200u16));
+
+    for _ in 0..5 {
+        cpu.execute_instruction().unwrap();
+    }
+    let snapshot = save(&cpu, 0x1234);
+    let snapshot_trace = get_nestest_trace_line(&cpu);
+
+    while cpu.get_program_counter().get_value() != 0x0209 {
+        cpu.execute_instruction().unwrap();
+    }
+    assert_eq!(cpu.get_memory_addr(Double::from(0x10u16)).get_value(), 0x05);
+
+    load(&mut cpu, &snapshot, 0x1234).unwrap();
+    assert_eq!(get_nestest_trace_line(&cpu), snapshot_trace);
+    assert_eq!(cpu.get_memory_addr(Double::from(0x10u16)).get_value(), 0x01);
+
+    // A truncated state fails after the cpu was loaded, the machine is put back as it was
+    cpu.execute_instruction().unwrap();
+    let current_trace = get_nestest_trace_line(&cpu);
+    assert!(matches!(load(&mut cpu, &snapshot[..snapshot.len() - 1], 0x1234), Err(SaveStateError::UnexpectedEnd)));
+    assert_eq!(get_nestest_trace_line(&cpu), current_trace);
+
+    assert!(matches!(load(&mut cpu, &snapshot, 0x4321), Err(SaveStateError::RomMismatch{expected: 0x4321, found: 0x1234})));
+    assert!(matches!(load(&mut cpu, b"NES\x1A", 0x1234), Err(SaveStateError::InvalidMagic)));
+
+    let mut future_version = snapshot.clone();
+    future_version[4] = 0xFF;
+    assert!(matches!(load(&mut cpu, &future_version, 0x1234), Err(SaveStateError::UnsupportedVersion(0x00FF))));
+    assert_eq!(get_nestest_trace_line(&cpu), current_trace);
+}
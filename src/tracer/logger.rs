@@ -0,0 +1,273 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use super::{TraceFormat, TraceTrigger};
+use super::nestest::get_nestest_trace_line;
+
+use crate::core::Byte;
+use crate::core::Double;
+use crate::cpu::CpuBus;
+use crate::cpu::cpu::Cpu;
+use crate::nes::Region;
+
+// Binary records are little endian : pc (2), instruction bytes padded with zeros (3), a, x, y, p, sp, cycle counter (8)
+pub const BINARY_RECORD_SIZE: usize = 18;
+
+pub enum TraceOutput {
+    File(BufWriter<File>),
+    // Keeps the last records only, for looking back at what led to a crash
+    RingBuffer{records: VecDeque<Vec<u8>>, capacity: usize},
+}
+
+impl TraceOutput {
+    pub fn file(path: &str) -> io::Result<TraceOutput> {
+        Ok(TraceOutput::File(BufWriter::new(File::create(path)?)))
+    }
+
+    pub fn ring_buffer(capacity: usize) -> TraceOutput {
+        TraceOutput::RingBuffer{records: VecDeque::with_capacity(capacity), capacity}
+    }
+}
+
+// Records the instructions a cpu runs, the cpu only calls it when one is set
+pub struct Tracer {
+    format: TraceFormat,
+    output: TraceOutput,
+    // Inclusive
+    pc_range: Option<(u16, u16)>,
+    bank: Option<usize>,
+    start_trigger: Option<TraceTrigger>,
+    stop_trigger: Option<TraceTrigger>,
+    // Sets the frame length for frame triggers
+    region: Region,
+    started: bool,
+    stopped: bool,
+}
+
+fn is_trigger_reached(trigger: TraceTrigger, pc: u16, frame: usize) -> bool {
+    match trigger {
+        TraceTrigger::Addr(addr) => pc == addr,
+        TraceTrigger::Frame(trigger_frame) => frame >= trigger_frame,
+    }
+}
+
+impl Tracer {
+    pub fn new(format: TraceFormat, output: TraceOutput) -> Tracer {
+        Tracer{format, output, pc_range: None, bank: None, start_trigger: None, stop_trigger: None,
+            region: Region::Ntsc, started: true, stopped: false}
+    }
+
+    pub fn set_pc_range(&mut self, start_addr: u16, end_addr: u16) {
+        self.pc_range = Some((start_addr, end_addr));
+    }
+
+    // Only traces instructions running from this prg rom bank
+    pub fn set_bank(&mut self, bank: usize) {
+        self.bank = Some(bank);
+    }
+
+    // Nothing is traced before the trigger, the instruction that reaches it is the first one traced
+    pub fn set_start_trigger(&mut self, trigger: TraceTrigger) {
+        self.start_trigger = Some(trigger);
+        self.started = false;
+    }
+
+    // Tracing stops for good at the trigger, the instruction that reaches it isn't traced
+    pub fn set_stop_trigger(&mut self, trigger: TraceTrigger) {
+        self.stop_trigger = Some(trigger);
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    // The records kept by a ring buffer, oldest first
+    pub fn get_records(&self) -> Vec<&[u8]> {
+        match &self.output {
+            TraceOutput::RingBuffer{records, ..} => records.iter().map(|record| record.as_slice()).collect(),
+            TraceOutput::File(_) => Vec::new(),
+        }
+    }
+
+    pub fn get_lines(&self) -> Vec<String> {
+        self.get_records().iter().map(|record| String::from_utf8_lossy(record).to_string()).collect()
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.output {
+            TraceOutput::File(writer) => writer.flush(),
+            TraceOutput::RingBuffer{..} => Ok(()),
+        }
+    }
+
+    // Called before every instruction the cpu runs
+    pub fn trace<B: CpuBus>(&mut self, cpu: &Cpu<B>) {
+        if self.stopped {
+            return;
+        }
+
+        let pc = cpu.get_program_counter().get_value();
+        let frame = self.region.get_frame(cpu.get_cycle_counter());
+
+        if self.stop_trigger.is_some_and(|trigger| is_trigger_reached(trigger, pc, frame)) {
+            self.stopped = true;
+            if let Err(err) = self.flush() {
+                log::warn!("Failed flushing the trace : {}", err);
+            }
+            return;
+        }
+
+        if !self.started {
+            self.started = self.start_trigger.is_some_and(|trigger| is_trigger_reached(trigger, pc, frame));
+            if !self.started {
+                return;
+            }
+        }
+
+        if self.pc_range.is_some_and(|(start_addr, end_addr)| pc < start_addr || pc > end_addr) {
+            return;
+        }
+
+        if self.bank.is_some() && cpu.get_bus().get_prg_bank(Double::from(pc)) != self.bank {
+            return;
+        }
+
+        let record = match self.format {
+            TraceFormat::Nestest => get_nestest_trace_line(cpu).into_bytes(),
+            TraceFormat::Mesen => Tracer::get_mesen_line(cpu).into_bytes(),
+            TraceFormat::Binary => Tracer::get_binary_record(cpu),
+        };
+
+        self.write_record(record);
+    }
+
+    fn write_record(&mut self, record: Vec<u8>) {
+        match &mut self.output {
+            TraceOutput::File(writer) => {
+                let newline: &[u8] = if self.format == TraceFormat::Binary { &[] } else { b"\n" };
+                if let Err(err) = writer.write_all(&record).and_then(|_| writer.write_all(newline)) {
+                    log::warn!("Failed writing the trace, tracing stopped : {}", err);
+                    self.stopped = true;
+                }
+            },
+            // Nothing is kept without capacity
+            TraceOutput::RingBuffer{capacity: 0, ..} => {},
+            TraceOutput::RingBuffer{records, capacity} => {
+                if records.len() == *capacity {
+                    records.pop_front();
+                }
+                records.push_back(record);
+            },
+        }
+    }
+
+    fn get_mesen_line<B: CpuBus>(cpu: &Cpu<B>) -> String {
+        let pc = cpu.get_program_counter().get_value();
+        let instruction = cpu.disassemble_at(pc);
+        let instruction_bytes: Vec<String> = instruction.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+
+        let disassembly = match cpu.get_effective_addr(&instruction) {
+            Some(addr) if instruction.get_target_addr().is_none() => format!("{} [${:04X}]", instruction, addr),
+            _ => instruction.to_string(),
+        };
+
+        let status = Byte::from(cpu.get_status());
+        let flags: String = "NVUBDIZC".chars().enumerate()
+            .map(|(i, flag)| if status[7 - i] { flag } else { flag.to_ascii_lowercase() }).collect();
+
+        format!("{:04X}  {:<8}  {:<24} A:{:02X} X:{:02X} Y:{:02X} S:{:02X} P:{} CYC:{}", pc, instruction_bytes.join(" "),
+            disassembly, cpu.get_reg_a().get_value(), cpu.get_reg_x().get_value(), cpu.get_reg_y().get_value(),
+            cpu.get_stack_pointer().get_value(), flags, cpu.get_cycle_counter())
+    }
+
+    fn get_binary_record<B: CpuBus>(cpu: &Cpu<B>) -> Vec<u8> {
+        let pc = cpu.get_program_counter().get_value();
+        let mut instruction_bytes = cpu.disassemble_at(pc).bytes;
+        instruction_bytes.resize(3, 0);
+
+        let mut record = Vec::<u8>::with_capacity(BINARY_RECORD_SIZE);
+        record.extend_from_slice(&pc.to_le_bytes());
+        record.extend_from_slice(&instruction_bytes);
+        record.extend_from_slice(&[cpu.get_reg_a().get_value(), cpu.get_reg_x().get_value(), cpu.get_reg_y().get_value(),
+            Byte::from(cpu.get_status()).get_value(), cpu.get_stack_pointer().get_value()]);
+        record.extend_from_slice(&(cpu.get_cycle_counter() as u64).to_le_bytes());
+
+        record
+    }
+}
+
+#[cfg(test)]
+fn run_traced_test_program(tracer: Tracer) -> Tracer {
+    // LDX #$00 ; INX ; STX $10 ; CPX #$05 ; BNE $0202 ; BRK
+    let mut mapper = crate::mapper::DebugMapper::new();
+    mapper.load(0x0200, &[0xA2, 0x00, 0xE8, 0x86, 0x10, 0xE0, 0x05, 0xD0, 0xF9, 0x00]);
+    let mut cpu = Cpu::new_with_entry_point(mapper, Double::from(0x0200u16));
+
+    cpu.set_tracer(Some(tracer));
+    // Stops once the BRK is traced
+    loop {
+        let program_counter = cpu.get_program_counter().get_value();
+        cpu.execute_instruction().unwrap();
+        if program_counter == 0x0209 {
+            break;
+        }
+    }
+
+    cpu.take_tracer().unwrap()
+}
+
+#[test]
+fn tracer_formats() {
+    let tracer = run_traced_test_program(Tracer::new(TraceFormat::Mesen, TraceOutput::ring_buffer(2)));
+    assert_eq!(tracer.get_lines(), vec![
+        "0207  D0 F9     BNE $0202                A:00 X:05 Y:00 S:FD P:nvUbdIZC CYC:56",
+        "0209  00        BRK                      A:00 X:05 Y:00 S:FD P:nvUbdIZC CYC:58",
+    ]);
+
+    let mut tracer = Tracer::new(TraceFormat::Mesen, TraceOutput::ring_buffer(1));
+    tracer.set_pc_range(0x0203, 0x0203);
+    let tracer = run_traced_test_program(tracer);
+    assert_eq!(tracer.get_lines(), vec!["0203  86 10     STX $10 [$0010]          A:00 X:05 Y:00 S:FD P:nvUbdIzc CYC:51"]);
+
+    let tracer = run_traced_test_program(Tracer::new(TraceFormat::Binary, TraceOutput::ring_buffer(1)));
+    assert_eq!(tracer.get_records(), vec![&[0x09, 0x02, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x27, 0xFD, 58, 0, 0, 0, 0, 0, 0, 0][..]]);
+
+    let tracer = run_traced_test_program(Tracer::new(TraceFormat::Nestest, TraceOutput::ring_buffer(0)));
+    assert!(tracer.get_records().is_empty());
+}
+
+#[test]
+fn tracer_triggers() {
+    let mut tracer = Tracer::new(TraceFormat::Nestest, TraceOutput::ring_buffer(100));
+    tracer.set_start_trigger(TraceTrigger::Addr(0x0203));
+    tracer.set_stop_trigger(TraceTrigger::Addr(0x0207));
+    let tracer = run_traced_test_program(tracer);
+
+    // STX and CPX of the first loop only
+    let lines = tracer.get_lines();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("0203  86 10     STX $10 = 00"));
+    assert!(lines[1].starts_with("0205  E0 05     CPX #$05"));
+    assert!(tracer.is_stopped());
+
+    // Every prg rom bank filter excludes the debug mapper, it has no banks
+    let mut tracer = Tracer::new(TraceFormat::Nestest, TraceOutput::ring_buffer(100));
+    tracer.set_bank(0);
+    assert!(run_traced_test_program(tracer).get_lines().is_empty());
+
+    let mut tracer = Tracer::new(TraceFormat::Nestest, TraceOutput::ring_buffer(100));
+    tracer.set_start_trigger(TraceTrigger::Frame(1));
+    assert!(run_traced_test_program(tracer).get_lines().is_empty());
+
+    assert_eq!(TraceTrigger::parse("$C000"), Some(TraceTrigger::Addr(0xC000)));
+    assert_eq!(TraceTrigger::parse("frame:60"), Some(TraceTrigger::Frame(60)));
+    assert_eq!(TraceTrigger::parse("frame:z"), None);
+    assert_eq!(TraceTrigger::parse("F000"), Some(TraceTrigger::Addr(0xF000)));
+    assert_eq!(TraceTrigger::parse("FFFC"), Some(TraceTrigger::Addr(0xFFFC)));
+    assert_eq!(TraceTrigger::parse("fffa"), Some(TraceTrigger::Addr(0xFFFA)));
+}
@@ -0,0 +1,46 @@
+pub mod logger;
+pub mod nestest;
+
+pub use logger::{Tracer, TraceOutput};
+pub use nestest::get_nestest_trace_line;
+
+// How every traced instruction is written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceFormat {
+    // Lines like nestest.log, so traces can be diffed against it
+    Nestest,
+    // Lines like the Mesen trace logger, with the effective address and flag letters
+    Mesen,
+    // Fixed size records, see logger::BINARY_RECORD_SIZE for the layout
+    Binary,
+}
+
+impl TraceFormat {
+    pub fn parse(name: &str) -> Option<TraceFormat> {
+        match name.to_lowercase().as_str() {
+            "nestest" => Some(TraceFormat::Nestest),
+            "mesen" => Some(TraceFormat::Mesen),
+            "binary" | "bin" => Some(TraceFormat::Binary),
+            _ => None,
+        }
+    }
+}
+
+// Starts or stops tracing when reached
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceTrigger {
+    // The instruction at the address is about to run
+    Addr(u16),
+    // The frame the cpu cycle counter is in, counted from power on
+    Frame(usize),
+}
+
+impl TraceTrigger {
+    // Parses $C000 / C000 for an address and frame:60 for a frame, a letter prefix would clash with hex addresses
+    pub fn parse(text: &str) -> Option<TraceTrigger> {
+        match text.strip_prefix("frame:") {
+            Some(frame) => frame.parse::<usize>().ok().map(TraceTrigger::Frame),
+            None => u16::from_str_radix(text.trim_start_matches('$'), 16).ok().map(TraceTrigger::Addr),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// The nestest.log line format, built from the registers and memory peeks any cpu exposes
+use crate::core::Byte;
+use crate::core::Double;
+use crate::core::consts;
+use crate::cpu::CpuBus;
+use crate::cpu::cpu::Cpu;
+use crate::disasm::DisassembledInstruction;
+use crate::disasm::disassembler::get_effective_addr;
+
+fn peek_trace_addr<B: CpuBus>(cpu: &Cpu<B>, addr: u16) -> u8 {
+    // Reading io registers has side effects, nestest.log shows them as FF
+    match addr {
+        consts::IO_REGISTERS_RANGE_START..=consts::IO_REGISTERS_RANGE_END => 0xFF,
+        _ => cpu.peek_memory_addr(Double::from(addr)).get_value(),
+    }
+}
+
+fn peek_zero_page_pointer<B: CpuBus>(cpu: &Cpu<B>, zero_page_addr: u8) -> u16 {
+    let least = peek_trace_addr(cpu, zero_page_addr as u16) as u16;
+    let most = peek_trace_addr(cpu, zero_page_addr.wrapping_add(1) as u16) as u16;
+
+    least + most * 0x100
+}
+
+fn get_nestest_operand<B: CpuBus>(cpu: &Cpu<B>, instruction: &DisassembledInstruction) -> String {
+    let reg_x = cpu.get_reg_x().get_value();
+    let effective_addr = get_effective_addr(instruction, cpu.get_variant(), reg_x, cpu.get_reg_y().get_value(),
+        |addr| peek_trace_addr(cpu, addr)).unwrap_or(0);
+    let value = peek_trace_addr(cpu, effective_addr);
+
+    let details = match instruction.mode.as_str() {
+        "ZeroPage" => format!(" = {:02X}", value),
+        "ZeroPage,X" | "ZeroPage,Y" => format!(" @ {:02X} = {:02X}", effective_addr, value),
+        // Jumps don't access the target address
+        "Absolute" if instruction.get_target_addr().is_some() => String::new(),
+        "Absolute" => format!(" = {:02X}", value),
+        "Absolute,X" | "Absolute,Y" => format!(" @ {:04X} = {:02X}", effective_addr, value),
+        "Indirect" => format!(" = {:04X}", effective_addr),
+        "(Indirect,X)" => {
+            let pointer_addr = instruction.bytes[1].wrapping_add(reg_x);
+            format!(" @ {:02X} = {:04X} = {:02X}", pointer_addr, effective_addr, value)
+        },
+        "(Indirect),Y" => {
+            let base_addr = peek_zero_page_pointer(cpu, instruction.bytes[1]);
+            format!(" = {:04X} @ {:04X} = {:02X}", base_addr, effective_addr, value)
+        },
+        _ => String::new(),
+    };
+
+    format!("{}{}", instruction.operand, details)
+}
+
+// Formats the current instruction and cpu state like a line of nestest.log, the ppu position assumes NTSC
+pub fn get_nestest_trace_line<B: CpuBus>(cpu: &Cpu<B>) -> String {
+    let pc = cpu.get_program_counter().get_value();
+    let instruction = cpu.disassemble_at(pc);
+    let instruction_bytes: Vec<String> = instruction.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+
+    let unofficial_marker = if consts::UNOFFICIAL_OPCODES.contains(&instruction.get_opcode()) { '*' } else { ' ' };
+
+    let cycle_counter = cpu.get_cycle_counter();
+    let ppu_dot = cycle_counter * consts::PPU_DOTS_PER_CPU_CYCLE;
+    let ppu_scanline = (ppu_dot / consts::PPU_DOTS_PER_SCANLINE) % consts::PPU_SCANLINES_PER_FRAME;
+
+    format!("{:04X}  {:<8} {}{:3} {:<28}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:3},{:3} CYC:{}",
+        pc, instruction_bytes.join(" "), unofficial_marker, instruction.name, get_nestest_operand(cpu, &instruction),
+        cpu.get_reg_a().get_value(), cpu.get_reg_x().get_value(), cpu.get_reg_y().get_value(),
+        Byte::from(cpu.get_status()).get_value(), cpu.get_stack_pointer().get_value(),
+        ppu_scanline, ppu_dot % consts::PPU_DOTS_PER_SCANLINE, cycle_counter)
+}
@@ -0,0 +1,44 @@
+// Forwards the log crate to the browser console through a function nessy.js provides
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+extern "C" {
+    // level is the log::Level value, 1 for errors up to 5 for traces
+    fn nessy_console_log(level: u32, message: *const u8, length: usize);
+}
+
+struct ConsoleLogger;
+
+static CONSOLE_LOGGER: ConsoleLogger = ConsoleLogger;
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let message = format!("{}", record.args());
+            unsafe { nessy_console_log(record.level() as u32, message.as_ptr(), message.len()) };
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+// level is 0 for none up to 5 for traces, later calls only change the level
+#[no_mangle]
+pub extern "C" fn nessy_init_logging(level: u32) {
+    let level = match level {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+
+    let _ = log::set_logger(&CONSOLE_LOGGER);
+    log::set_max_level(level);
+    log::debug!("Logging to the console at {}", level);
+}
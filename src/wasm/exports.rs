@@ -0,0 +1,43 @@
+// Javascript only sees the linear memory, buffers passed to the C API are allocated through these
+
+// Returns a buffer of size bytes, freed with nessy_free and the same size
+#[no_mangle]
+pub extern "C" fn nessy_alloc(size: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(size);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+
+    ptr
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nessy_free(ptr: *mut u8, size: usize) {
+    if !ptr.is_null() {
+        drop(Vec::from_raw_parts(ptr, 0, size));
+    }
+}
+
+#[test]
+fn wasm_calls() {
+    use crate::capi::exports::*;
+
+    let rom = crate::nes::console::get_test_rom();
+    unsafe {
+        // The way nessy.js loads a rom
+        let rom_buffer = nessy_alloc(rom.len());
+        std::slice::from_raw_parts_mut(rom_buffer, rom.len()).copy_from_slice(&rom);
+        let nes = nessy_create(rom_buffer, rom.len());
+        nessy_free(rom_buffer, rom.len());
+        assert!(!nes.is_null());
+
+        // And how it reads the audio samples, the count goes to a scratch buffer
+        assert_eq!(nessy_run_frame(nes), crate::capi::NESSY_OK);
+        let scratch = nessy_alloc(std::mem::size_of::<usize>());
+        assert!(!nessy_get_audio_samples(nes, scratch as *mut usize).is_null());
+        assert!((730..=740).contains(&*(scratch as *const usize)));
+        nessy_free(scratch, std::mem::size_of::<usize>());
+
+        nessy_destroy(nes);
+        nessy_free(std::ptr::null_mut(), 0);
+    }
+}
@@ -0,0 +1,6 @@
+// The browser build, the C API exports plus what javascript needs to reach them.
+// examples/web/nessy.js wraps the module, build it with cargo build --lib --release --target wasm32-unknown-unknown
+#[allow(clippy::missing_safety_doc)]
+pub mod exports;
+#[cfg(target_arch = "wasm32")]
+pub mod console;